@@ -1,30 +1,48 @@
-use std::{collections::BTreeMap, process::Command};
+use std::{
+    collections::BTreeMap,
+    process::Command,
+    time::{Duration, Instant},
+};
 
 use iced::{
     advanced::graphics::image::image_rs::ImageFormat,
     daemon::{Appearance, DefaultStyle},
-    keyboard::{key, on_key_press, Modifiers},
+    event::{listen_with, Status},
+    keyboard::{self, key, on_key_press, on_key_release, Modifiers},
+    time::every,
     widget::horizontal_space,
     window::{
-        self, change_mode, close, close_events, gain_focus, get_scale_factor, icon,
-        settings::PlatformSpecific, Id, Mode, Position,
+        self, change_mode, close, close_events, enable_mouse_passthrough, gain_focus,
+        get_scale_factor, icon, resize_events, settings::PlatformSpecific, Id, Level, Mode,
+        Position,
     },
-    Point, Size, Subscription, Task,
+    Color, Event, Point, Size, Subscription, Task,
 };
 use mouse_position::mouse_position::Mouse;
-use rfd::FileDialog;
-use xcap::Monitor;
+use rfd::{FileDialog, MessageDialog, MessageLevel};
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+use xcap::{image::RgbaImage, Monitor};
 
 use crate::{
-    config::{utils::shorten_path, Config},
-    consts::APPICON,
+    config::{utils::shorten_path, Config, DEFAULT_WINDOW_SIZE},
+    consts::{APPICON, NO_DISPLAYS},
     ipc::ipc_listener,
     key_listener::global_key_listener,
-    theme::{Element, Theme},
+    rpc::{RpcRequest, RpcResponse},
+    theme::{system_theme, Element, Palette, Theme, ThemeMode},
     tray_icon::{tray_icon_listener, tray_menu_listener},
     windows::{
-        capture_window::{CaptureEvent, CaptureWindow},
+        capture_window::{
+            capture::{quick_capture, silent_capture, with_clipboard},
+            contact_sheet::save_contact_sheet,
+            snippet::render_snippet,
+            CaptureEvent, CaptureWindow,
+        },
         config_window::{ConfigEvent, ConfigureWindow},
+        live_window::LiveEvent,
+        picker_window::PickerWindow,
+        pin_window::PinWindow,
         AppWindow,
     },
 };
@@ -32,60 +50,221 @@ use crate::{
 pub struct App {
     pub config: Config,
     pub windows: BTreeMap<Id, AppWindow>,
+    /// File written by the most recent capture
+    pub last_capture: Option<String>,
+    /// Capture waiting out `delay_secs`, keyed by when it was requested
+    pub pending_capture: Option<(Instant, Monitor)>,
+    /// No config existed at launch, the configure window opens with the first run setup
+    pub first_run: bool,
 }
 
 #[derive(Debug, Clone)]
 pub enum AppEvent {
     OpenConfigureWindow,
+    /// Raises the overlay, or else any open window, opening the configure window when there
+    /// is none
+    FocusWindow,
     OpenDirectory,
+    OpenLastCapture,
+    CreateContactSheet,
     UpdateDirectory(Id),
     GetScaleFactor(Id, f32),
     OpenCaptureWindow,
+    /// The delay of the capture requested at this instant is over
+    DelayedCapture(Instant),
+    OpenSnippetWindow,
+    OpenClipboardImage,
+    SilentRecapture,
+    /// Saves the primary monitor without opening the overlay
+    QuickCapture,
+    PickMonitor,
+    /// Opens `image` in its own always-on-top window, at native size on a monitor with this
+    /// scale factor
+    PinImage(RgbaImage, f32),
+    DragPin(Id),
+    /// Sent by `Esc` in any window, closes it if it is a pin and cancels in a capture or monitor
+    /// picker window, so a pin over an overlay can be closed without losing the capture
+    Escape(Id),
+    ToggleLiveOverlay,
+    ToggleLiveDrawing,
+    ClearLiveOverlay,
+    SelectMonitor(usize),
+    CaptureMonitor(usize),
+    /// Picks every monitor at once in the monitor picker
+    SelectDesktop,
+    /// Opens one overlay over the whole virtual desktop
+    CaptureDesktop,
     Undo,
+    Redo,
+    UndoCrop,
+    RedoCrop,
+    RestoreSelection,
+    SelectShape(bool),
+    DeleteShape,
+    TranslateShapes(f32, f32),
+    HideToolbar(bool),
+    ToggleMode,
     Done,
+    SaveAndContinue,
+    CopyToClipboard,
+    SaveCapture(Id),
     Cancel,
     RequestClose(Id),
     WindowClosed(Id),
+    WindowResized(Id, Size),
     ExitApp,
     Config(Id, ConfigEvent),
     Capture(Id, CaptureEvent),
+    Live(Id, LiveEvent),
+    CheckOverlayTimeout,
+    /// Steps the countdown of every overlay waiting on a timed capture
+    CheckCountdown,
+    /// Reads the OS appearance off the UI thread, as the query can block for a while
+    CheckSystemTheme,
+    /// Animates windows that follow the OS appearance to it when it has changed
+    SystemTheme(Theme),
+    Rpc(RpcRequest, Sender<RpcResponse>),
+    /// A capture requested over RPC was taken, answers the request with it
+    RpcCaptured(Value, Result<Value, String>, Sender<RpcResponse>),
+    /// Tells the user something went wrong with a blocking dialog
+    ShowError(String),
 }
 
 impl App {
     pub fn new() -> (App, Task<AppEvent>) {
-        let (config, is_initial) = Config::new();
+        let (mut config, is_initial) = Config::new();
+        let warning = config.validate_directory();
+        if config.theme_mode == ThemeMode::Auto {
+            config.theme = system_theme();
+        }
         (
             App {
                 config,
                 windows: BTreeMap::new(),
+                last_capture: None,
+                pending_capture: None,
+                first_run: is_initial,
             },
-            if is_initial {
-                Task::done(AppEvent::OpenConfigureWindow)
-            } else {
-                Task::none()
+            Task::batch([
+                if is_initial {
+                    Task::done(AppEvent::OpenConfigureWindow)
+                } else {
+                    Task::none()
+                },
+                warning.map_or(Task::none(), |warning| {
+                    Task::done(AppEvent::ShowError(warning))
+                }),
+            ]),
+        )
+    }
+
+    /// Opens the overlay on `monitor` once `delay_secs` have passed, requesting it again while
+    /// waiting cancels it
+    fn schedule_capture(&mut self, monitor: Monitor) -> Task<AppEvent> {
+        let delay = self.config.delay_secs;
+        if delay == 0 {
+            return self.open_capture_window(monitor);
+        }
+        if self.pending_capture.take().is_some() {
+            println!("Delayed capture cancelled");
+            return Task::none();
+        }
+        let requested = Instant::now();
+        self.pending_capture = Some((requested, monitor));
+        println!("Capturing in {} s, trigger it again to cancel", delay);
+        Task::perform(
+            tokio::time::sleep(Duration::from_secs(delay as u64)),
+            move |_| AppEvent::DelayedCapture(requested),
+        )
+    }
+
+    fn open_capture_window(&mut self, monitor: Monitor) -> Task<AppEvent> {
+        match CaptureWindow::new(monitor.clone(), &self.config) {
+            Some(capture_window) => self.open_overlay(&monitor, capture_window),
+            None => Task::none(),
+        }
+    }
+
+    fn open_overlay(&mut self, monitor: &Monitor, capture_window: CaptureWindow) -> Task<AppEvent> {
+        let position = Point::new(monitor.x() as f32, monitor.y() as f32);
+        self.open_overlay_at(position, None, capture_window)
+    }
+
+    /// Opens the overlay at `position`, fullscreen on the monitor there or spanning `size`
+    /// across monitors
+    fn open_overlay_at(
+        &mut self,
+        position: Point,
+        size: Option<Size>,
+        capture_window: CaptureWindow,
+    ) -> Task<AppEvent> {
+        let (id, open_task) = window::open(window::Settings {
+            size: size.unwrap_or(window::Settings::default().size),
+            position: Position::Specific(position),
+            transparent: true,
+            decorations: false,
+            level: match size {
+                Some(_) => Level::AlwaysOnTop,
+                None => Level::Normal,
             },
+            #[cfg(target_os = "windows")]
+            platform_specific: PlatformSpecific {
+                drag_and_drop: false,
+                skip_taskbar: true,
+                undecorated_shadow: false,
+            },
+            ..Default::default()
+        });
+        self.windows
+            .insert(id, AppWindow::Capture(Box::new(capture_window)));
+        let open_task = open_task.discard().chain(gain_focus(id));
+        let open_task = match size {
+            Some(_) => open_task,
+            None => open_task.chain(change_mode(id, Mode::Fullscreen)),
+        };
+        open_task.chain(
+            get_scale_factor(id)
+                .map(move |scale_factor| AppEvent::GetScaleFactor(id, scale_factor)),
         )
     }
 
+    fn live_window_id(&self) -> Option<Id> {
+        self.windows
+            .iter()
+            .find_map(|(id, window)| matches!(window, AppWindow::Live(_)).then_some(*id))
+    }
+
     pub fn title(&self, id: Id) -> String {
         match self.windows.get(&id) {
             Some(AppWindow::Configure(_)) => String::from("Capter"),
             Some(AppWindow::Capture(_)) => String::from("Capter: Capture"),
+            Some(AppWindow::Picker(_)) => String::from("Capter: Select Monitor"),
+            Some(AppWindow::Live(_)) => String::from("Capter: Live Annotation"),
+            Some(AppWindow::Pin(_)) => String::from("Capter: Pinned Capture"),
             None => String::new(),
         }
     }
 
     pub fn update(&mut self, message: AppEvent) -> Task<AppEvent> {
         match message {
+            AppEvent::FocusWindow => {
+                let window = self
+                    .windows
+                    .iter()
+                    .rev()
+                    .find(|(_, window)| matches!(window, AppWindow::Capture(_)))
+                    .or(self.windows.first_key_value());
+                match window {
+                    Some((id, _)) => gain_focus(*id),
+                    None => Task::done(AppEvent::OpenConfigureWindow),
+                }
+            }
             AppEvent::OpenConfigureWindow => {
                 if self.windows.is_empty() {
                     let (id, open_task) = window::open(window::Settings {
-                        size: Size {
-                            width: 700.0,
-                            height: 430.0,
-                        },
+                        size: configure_window_size(&self.config),
+                        min_size: Some(Size::new(DEFAULT_WINDOW_SIZE.0, DEFAULT_WINDOW_SIZE.1)),
                         position: Position::Centered,
-                        resizable: false,
                         icon: Some(icon::from_file_data(APPICON, Some(ImageFormat::Png)).unwrap()),
                         #[cfg(target_os = "macos")]
                         platform_specific: PlatformSpecific {
@@ -103,8 +282,8 @@ impl App {
                     self.windows.insert(
                         id,
                         AppWindow::Configure(Box::new(ConfigureWindow::new(
-                            shorten_path(self.config.directory.clone()),
-                            self.config.theme.clone(),
+                            self.config.clone(),
+                            std::mem::take(&mut self.first_run),
                         ))),
                     );
                     return open_task.discard().chain(gain_focus(id));
@@ -112,26 +291,48 @@ impl App {
                 Task::none()
             }
             AppEvent::OpenDirectory => {
-                #[cfg(target_os = "windows")]
-                let cmd = "explorer";
-                #[cfg(target_os = "linux")]
-                let cmd = "xdg-open";
-                #[cfg(target_os = "macos")]
-                let cmd = "open";
-                Command::new(cmd)
-                    .arg(&self.config.directory)
-                    .spawn()
-                    .unwrap();
+                open_path(&self.config.directory);
                 Task::none()
             }
+            AppEvent::OpenLastCapture => {
+                match &self.last_capture {
+                    Some(path) => open_path(path),
+                    None => println!("No capture has been saved yet"),
+                }
+                Task::none()
+            }
+            AppEvent::CreateContactSheet => {
+                // Unsaved changes in an open settings window apply right away
+                let config = self
+                    .windows
+                    .values()
+                    .find_map(|window| match window {
+                        AppWindow::Configure(config_window) => Some(&config_window.config),
+                        _ => None,
+                    })
+                    .unwrap_or(&self.config);
+                match save_contact_sheet(config) {
+                    Ok(path) => {
+                        open_path(&path);
+                        self.last_capture = Some(path);
+                        Task::none()
+                    }
+                    Err(error) => Task::done(AppEvent::ShowError(error)),
+                }
+            }
             AppEvent::UpdateDirectory(id) => {
                 if let Some(path) = FileDialog::new()
                     .set_directory(self.config.directory.clone())
                     .pick_folder()
                 {
                     self.config.directory = path.into_os_string().into_string().unwrap();
+                    let warning = self.config.validate_directory();
                     if let Some(AppWindow::Configure(config_window)) = self.windows.get_mut(&id) {
                         config_window.path = shorten_path(self.config.directory.clone());
+                        config_window.config.directory = self.config.directory.clone();
+                    }
+                    if let Some(warning) = warning {
+                        return Task::done(AppEvent::ShowError(warning));
                     }
                 }
                 Task::none()
@@ -149,44 +350,322 @@ impl App {
                         AppWindow::Capture(_)
                     )
                 {
-                    let (x, y) = match Mouse::get_mouse_position() {
-                        Mouse::Position { x, y } => (x, y),
-                        Mouse::Error => (0, 0),
-                    };
-                    let monitor = Monitor::from_point(x, y).unwrap();
+                    if let Some(monitor) = cursor_monitor() {
+                        return self.schedule_capture(monitor);
+                    }
+                }
+                Task::none()
+            }
+            AppEvent::OpenSnippetWindow => {
+                if self
+                    .windows
+                    .values()
+                    .any(|window| matches!(window, AppWindow::Capture(_)))
+                {
+                    return Task::none();
+                }
+                let text = with_clipboard(|clipboard| clipboard.get_text());
+                match text {
+                    Ok(text) if !text.trim().is_empty() => {
+                        let Some(monitor) = cursor_monitor() else {
+                            return Task::none();
+                        };
+                        let image = render_snippet(&text, &self.config.snippet);
+                        match CaptureWindow::from_image(
+                            monitor.clone(),
+                            image,
+                            "Clipboard Text",
+                            &self.config,
+                        ) {
+                            Some(capture_window) => self.open_overlay(&monitor, capture_window),
+                            None => Task::none(),
+                        }
+                    }
+                    _ => {
+                        println!("No text on the clipboard");
+                        Task::none()
+                    }
+                }
+            }
+            AppEvent::OpenClipboardImage => {
+                if self
+                    .windows
+                    .values()
+                    .any(|window| matches!(window, AppWindow::Capture(_)))
+                {
+                    return Task::none();
+                }
+                let image = with_clipboard(|clipboard| clipboard.get_image())
+                    .ok()
+                    .and_then(|image| {
+                        RgbaImage::from_raw(
+                            image.width as u32,
+                            image.height as u32,
+                            image.bytes.into_owned(),
+                        )
+                    });
+                let Some(image) = image else {
+                    return Task::done(AppEvent::ShowError(String::from(
+                        "No image on the clipboard",
+                    )));
+                };
+                let Some(monitor) = cursor_monitor() else {
+                    return Task::none();
+                };
+                match CaptureWindow::from_image(
+                    monitor.clone(),
+                    image,
+                    "Clipboard Image",
+                    &self.config,
+                ) {
+                    Some(capture_window) => self.open_overlay(&monitor, capture_window),
+                    None => Task::none(),
+                }
+            }
+            AppEvent::SilentRecapture => {
+                match self.config.last_region {
+                    Some(region) => {
+                        if let Some(path) = silent_capture(region, &self.config) {
+                            self.last_capture = Some(path);
+                        }
+                    }
+                    None => println!("Nothing has been captured yet"),
+                }
+                Task::none()
+            }
+            AppEvent::QuickCapture => {
+                if let Some(path) = quick_capture(&self.config) {
+                    self.last_capture = Some(path);
+                }
+                Task::none()
+            }
+            AppEvent::PickMonitor => {
+                if self.pending_capture.take().is_some() {
+                    println!("Delayed capture cancelled");
+                    return Task::none();
+                }
+                // The overlay doesn't get key presses once another window is clicked
+                if let Some(id) = self.windows.iter().find_map(|(id, window)| {
+                    matches!(window, AppWindow::Capture(capture_window)
+                        if capture_window.countdown.is_some())
+                    .then_some(*id)
+                }) {
+                    return Task::done(AppEvent::Capture(id, CaptureEvent::Cancel));
+                }
+                if self
+                    .windows
+                    .values()
+                    .any(|window| matches!(window, AppWindow::Capture(_) | AppWindow::Picker(_)))
+                {
+                    return Task::none();
+                }
+                let monitors = Monitor::all().unwrap_or_default();
+                if monitors.len() <= 1 {
+                    return Task::done(AppEvent::OpenCaptureWindow);
+                }
+                let mut tasks = Vec::new();
+                for (index, monitor) in monitors.into_iter().enumerate() {
+                    const SIZE: Size = Size::new(240.0, 300.0);
                     let (id, open_task) = window::open(window::Settings {
+                        size: SIZE,
                         position: Position::Specific(Point::new(
-                            monitor.x() as f32,
-                            monitor.y() as f32,
+                            monitor.x() as f32 + (monitor.width() as f32 - SIZE.width) / 2.0,
+                            monitor.y() as f32 + (monitor.height() as f32 - SIZE.height) / 2.0,
                         )),
-                        transparent: true,
                         decorations: false,
-                        #[cfg(target_os = "windows")]
-                        platform_specific: PlatformSpecific {
-                            drag_and_drop: false,
-                            skip_taskbar: true,
-                            undecorated_shadow: false,
-                        },
+                        resizable: false,
+                        level: Level::AlwaysOnTop,
                         ..Default::default()
                     });
-                    let capture_window = CaptureWindow::new(monitor);
-                    self.windows
-                        .insert(id, AppWindow::Capture(Box::new(capture_window)));
-                    return open_task
-                        .discard()
-                        .chain(gain_focus(id))
-                        .chain(change_mode(id, Mode::Fullscreen))
-                        .chain(
-                            get_scale_factor(id).map(move |scale_factor| {
-                                AppEvent::GetScaleFactor(id, scale_factor)
-                            }),
-                        );
+                    self.windows.insert(
+                        id,
+                        AppWindow::Picker(PickerWindow::new(index, monitor.name().to_string())),
+                    );
+                    tasks.push(if index == 0 {
+                        open_task.discard().chain(gain_focus(id))
+                    } else {
+                        open_task.discard()
+                    });
                 }
-                Task::none()
+                Task::batch(tasks)
             }
+            AppEvent::PinImage(image, scale_factor) => {
+                let pin_window = PinWindow::new(image, scale_factor);
+                let (id, open_task) = window::open(window::Settings {
+                    size: pin_window.window_size(),
+                    position: Position::Centered,
+                    transparent: true,
+                    decorations: false,
+                    resizable: false,
+                    level: Level::AlwaysOnTop,
+                    ..Default::default()
+                });
+                self.windows.insert(id, AppWindow::Pin(pin_window));
+                open_task.discard().chain(gain_focus(id))
+            }
+            AppEvent::DragPin(id) => window::drag(id),
+            AppEvent::Escape(id) => match self.windows.get(&id) {
+                Some(AppWindow::Pin(_)) => close(id),
+                Some(AppWindow::Capture(_) | AppWindow::Picker(_)) => Task::done(AppEvent::Cancel),
+                _ => Task::none(),
+            },
+            AppEvent::ToggleLiveOverlay => {
+                if let Some(id) = self.live_window_id() {
+                    return close(id);
+                }
+                let Some(monitor) = cursor_monitor() else {
+                    return Task::none();
+                };
+                let (id, open_task) = window::open(window::Settings {
+                    position: Position::Specific(Point::new(
+                        monitor.x() as f32,
+                        monitor.y() as f32,
+                    )),
+                    transparent: true,
+                    decorations: false,
+                    resizable: false,
+                    level: Level::AlwaysOnTop,
+                    #[cfg(target_os = "windows")]
+                    platform_specific: PlatformSpecific {
+                        drag_and_drop: false,
+                        skip_taskbar: true,
+                        undecorated_shadow: false,
+                    },
+                    ..Default::default()
+                });
+                self.windows.insert(id, AppWindow::Live(Box::default()));
+                open_task
+                    .discard()
+                    .chain(change_mode(id, Mode::Fullscreen))
+                    .chain(enable_mouse_passthrough(id))
+            }
+            AppEvent::ToggleLiveDrawing => match self.live_window_id() {
+                Some(id) => {
+                    Task::done(AppEvent::Live(id, LiveEvent::ToggleDrawing)).chain(gain_focus(id))
+                }
+                None => Task::none(),
+            },
+            AppEvent::ClearLiveOverlay => match self.live_window_id() {
+                Some(id) => Task::done(AppEvent::Live(id, LiveEvent::Clear)),
+                None => Task::none(),
+            },
+            AppEvent::SelectMonitor(index) => {
+                let pickers: Vec<Id> = self
+                    .windows
+                    .iter()
+                    .filter_map(|(id, window)| {
+                        matches!(window, AppWindow::Picker(_)).then_some(*id)
+                    })
+                    .collect();
+                if pickers.is_empty() || index >= pickers.len() {
+                    return Task::none();
+                }
+                Task::batch(pickers.into_iter().map(close))
+                    .chain(Task::done(AppEvent::CaptureMonitor(index)))
+            }
+            AppEvent::CaptureMonitor(index) => {
+                match Monitor::all().unwrap_or_default().into_iter().nth(index) {
+                    Some(monitor) => self.schedule_capture(monitor),
+                    None => Task::none(),
+                }
+            }
+            AppEvent::SelectDesktop => {
+                let pickers: Vec<Id> = self
+                    .windows
+                    .iter()
+                    .filter_map(|(id, window)| {
+                        matches!(window, AppWindow::Picker(_)).then_some(*id)
+                    })
+                    .collect();
+                if pickers.is_empty() {
+                    return Task::none();
+                }
+                Task::batch(pickers.into_iter().map(close))
+                    .chain(Task::done(AppEvent::CaptureDesktop))
+            }
+            AppEvent::CaptureDesktop => match CaptureWindow::desktop(&self.config) {
+                Ok((capture_window, (x, y))) => {
+                    // Monitors report physical pixels, windows are placed in logical ones
+                    let scale_factor = capture_window.scale_factor;
+                    let size = Size::new(
+                        capture_window.image.width() as f32 / scale_factor,
+                        capture_window.image.height() as f32 / scale_factor,
+                    );
+                    let position = Point::new(x as f32 / scale_factor, y as f32 / scale_factor);
+                    self.open_overlay_at(position, Some(size), capture_window)
+                }
+                Err(error) => Task::done(AppEvent::ShowError(error)),
+            },
+            AppEvent::DelayedCapture(requested) => match self.pending_capture.take() {
+                Some((pending, monitor)) if pending == requested => {
+                    self.open_capture_window(monitor)
+                }
+                // Cancelled, or replaced by a later request that keeps waiting
+                pending => {
+                    self.pending_capture = pending;
+                    Task::none()
+                }
+            },
             AppEvent::Undo => {
                 if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
-                    return Task::done(AppEvent::Capture(*id, CaptureEvent::Undo));
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::UndoAnnotation));
+                }
+                Task::none()
+            }
+            AppEvent::Redo => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::Redo));
+                }
+                Task::none()
+            }
+            AppEvent::UndoCrop => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::UndoCrop));
+                }
+                Task::none()
+            }
+            AppEvent::RedoCrop => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::RedoCrop));
+                }
+                Task::none()
+            }
+            AppEvent::RestoreSelection => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::RestoreSelection));
+                }
+                Task::none()
+            }
+            AppEvent::SelectShape(forward) => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::SelectShape(forward)));
+                }
+                Task::none()
+            }
+            AppEvent::TranslateShapes(dx, dy) => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(
+                        *id,
+                        CaptureEvent::TranslateAllShapes { dx, dy },
+                    ));
+                }
+                Task::none()
+            }
+            AppEvent::HideToolbar(hidden) => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::HideToolbar(hidden)));
+                }
+                Task::none()
+            }
+            AppEvent::ToggleMode => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::ToggleMode));
+                }
+                Task::none()
+            }
+            AppEvent::DeleteShape => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::DeleteSelectedShape));
                 }
                 Task::none()
             }
@@ -196,19 +675,84 @@ impl App {
                 }
                 Task::none()
             }
+            AppEvent::SaveAndContinue => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::SaveAndContinue));
+                }
+                Task::none()
+            }
+            AppEvent::CopyToClipboard => {
+                if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
+                    return Task::done(AppEvent::Capture(*id, CaptureEvent::CopyToClipboard));
+                }
+                Task::none()
+            }
+            AppEvent::SaveCapture(id) => {
+                if let Some(AppWindow::Capture(capture_window)) = self.windows.get_mut(&id) {
+                    capture_window.save_version(self.config.directory.clone());
+                    if capture_window.saved_path.is_some() {
+                        self.last_capture = capture_window.saved_path.clone();
+                    }
+                }
+                Task::none()
+            }
             AppEvent::Cancel => {
+                if self.pending_capture.take().is_some() {
+                    println!("Delayed capture cancelled");
+                    return Task::none();
+                }
+                let pickers: Vec<Id> = self
+                    .windows
+                    .iter()
+                    .filter_map(|(id, window)| {
+                        matches!(window, AppWindow::Picker(_)).then_some(*id)
+                    })
+                    .collect();
+                if !pickers.is_empty() {
+                    return Task::batch(pickers.into_iter().map(close));
+                }
                 if let Some((id, AppWindow::Capture(_))) = self.windows.last_key_value() {
                     return Task::done(AppEvent::Capture(*id, CaptureEvent::Cancel));
                 }
                 Task::none()
             }
             AppEvent::RequestClose(id) => close(id),
+            AppEvent::WindowResized(id, size) => {
+                if let Some(AppWindow::Configure(config_window)) = self.windows.get_mut(&id) {
+                    config_window.config.window_width = size.width;
+                    config_window.config.window_height = size.height;
+                }
+                Task::none()
+            }
             AppEvent::WindowClosed(id) => {
                 match self.windows.remove(&id) {
                     Some(AppWindow::Capture(capture_window)) => {
-                        capture_window.take_screenshot(self.config.directory.clone());
+                        // Remembered even for cancelled captures
+                        self.config.default_color = capture_window.config.default_color;
+                        self.config.default_stroke = capture_window.config.default_stroke;
+                        self.config.last_selection = capture_window.config.last_selection;
+                        self.config.update_config();
+                        if !capture_window.discard {
+                            if let Some(region) = capture_window.capture_region() {
+                                self.config.last_region = Some(region);
+                                self.config.update_config();
+                            }
+                            if let Some(path) =
+                                capture_window.take_screenshot(self.config.directory.clone())
+                            {
+                                self.last_capture = Some(path);
+                            }
+                        }
                     }
+                    Some(AppWindow::Picker(_) | AppWindow::Live(_) | AppWindow::Pin(_)) => (),
                     Some(AppWindow::Configure(config_window)) => {
+                        // Only what was edited in the window, captures meanwhile remembered
+                        // their selection and tools
+                        let edits = config_window.config.changed_fields(&config_window.opened);
+                        match self.config.with_fields(edits) {
+                            Ok(config) => self.config = config,
+                            Err(error) => println!("Could not apply the settings: {}", error),
+                        }
                         self.config.theme = config_window.theme.target().clone();
                         self.config.update_config();
                     }
@@ -232,6 +776,75 @@ impl App {
                 }
                 Task::none()
             }
+            AppEvent::Live(id, message) => {
+                if let Some(AppWindow::Live(live_window)) = self.windows.get_mut(&id) {
+                    return live_window.update(id, message);
+                }
+                Task::none()
+            }
+            AppEvent::Rpc(request, reply) => self.handle_rpc(request, reply),
+            AppEvent::RpcCaptured(id, result, reply) => {
+                if let Some(path) = result
+                    .as_ref()
+                    .ok()
+                    .and_then(|result| result.get("path"))
+                    .and_then(Value::as_str)
+                {
+                    self.last_capture = Some(path.to_string());
+                }
+                reply.try_send(RpcResponse::new(id, result)).ok();
+                Task::none()
+            }
+            AppEvent::ShowError(message) => {
+                println!("{}", message);
+                MessageDialog::new()
+                    .set_level(MessageLevel::Error)
+                    .set_title("Capter")
+                    .set_description(message)
+                    .show();
+                Task::none()
+            }
+            AppEvent::CheckOverlayTimeout => Task::batch(
+                self.windows
+                    .iter()
+                    .filter(|(_, window)| matches!(window, AppWindow::Capture(_)))
+                    .map(|(id, _)| Task::done(AppEvent::Capture(*id, CaptureEvent::CheckTimeout))),
+            ),
+            AppEvent::CheckCountdown => Task::batch(
+                self.windows
+                    .iter()
+                    .filter(|(_, window)| {
+                        matches!(window, AppWindow::Capture(capture_window)
+                            if capture_window.countdown.is_some())
+                    })
+                    .map(|(id, _)| Task::done(AppEvent::Capture(*id, CaptureEvent::CountdownTick))),
+            ),
+            AppEvent::CheckSystemTheme => Task::perform(
+                async {
+                    tokio::task::spawn_blocking(system_theme)
+                        .await
+                        .unwrap_or_default()
+                },
+                AppEvent::SystemTheme,
+            ),
+            AppEvent::SystemTheme(theme) => {
+                if self.config.theme_mode == ThemeMode::Auto {
+                    self.config.theme = theme.clone();
+                }
+                // Only a changed target restarts the spring, so a running animation is left alone
+                Task::batch(self.windows.iter().filter_map(|(id, window)| match window {
+                    AppWindow::Configure(config_window)
+                        if config_window.config.theme_mode == ThemeMode::Auto
+                            && *config_window.theme.target() != theme =>
+                    {
+                        Some(Task::done(AppEvent::Config(
+                            *id,
+                            ConfigEvent::UpdateTheme(theme.clone().into()),
+                        )))
+                    }
+                    _ => None,
+                }))
+            }
         }
     }
 
@@ -243,6 +856,11 @@ impl App {
             Some(AppWindow::Capture(capture_window)) => capture_window
                 .view()
                 .map(move |message| AppEvent::Capture(id, message)),
+            Some(AppWindow::Picker(picker_window)) => picker_window.view(),
+            Some(AppWindow::Pin(pin_window)) => pin_window.view(id),
+            Some(AppWindow::Live(live_window)) => live_window
+                .view()
+                .map(move |message| AppEvent::Live(id, message)),
             None => horizontal_space().into(),
         };
 
@@ -250,19 +868,25 @@ impl App {
     }
 
     pub fn theme(&self, id: Id) -> Theme {
+        let transparent = || {
+            Theme::Custom(Palette {
+                background: Color::TRANSPARENT,
+                ..self.config.theme.palette()
+            })
+        };
         match self.windows.get(&id) {
             Some(AppWindow::Configure(config_window)) => config_window.theme.value().clone(),
+            // Only the shapes, or the pinned image and its shadow, are drawn with the desktop
+            // visible underneath
+            Some(AppWindow::Live(_) | AppWindow::Pin(_)) => transparent(),
+            // A timed capture's countdown floats over the windows it lets clicks through to
+            Some(AppWindow::Capture(capture_window)) if capture_window.countdown.is_some() => {
+                transparent()
+            }
             _ => self.config.theme.clone(),
         }
     }
 
-    pub fn scale_factor(&self, id: Id) -> f64 {
-        match self.windows.get(&id) {
-            Some(AppWindow::Capture(capture_window)) => (1.0 / capture_window.scale_factor) as f64,
-            _ => 1.0,
-        }
-    }
-
     pub fn style(&self, theme: &Theme) -> Appearance {
         theme.default_style()
     }
@@ -270,24 +894,84 @@ impl App {
     pub fn subscription(&self) -> Subscription<AppEvent> {
         let window_events = close_events().map(AppEvent::WindowClosed);
 
+        let resize_events = resize_events().map(|(id, size)| AppEvent::WindowResized(id, size));
+
         let app_key_listener = on_key_press(|key, modifiers| match (key, modifiers) {
-            (key::Key::Named(key::Named::Escape), _) => Some(AppEvent::Cancel),
             (key::Key::Named(key::Named::Enter), _) => Some(AppEvent::Done),
+            (key::Key::Named(key::Named::Space), _) => Some(AppEvent::HideToolbar(true)),
+            (key::Key::Named(key::Named::Tab), m) => Some(AppEvent::SelectShape(!m.shift())),
+            (key::Key::Named(key::Named::Delete | key::Named::Backspace), _) => {
+                Some(AppEvent::DeleteShape)
+            }
+            (key::Key::Named(arrow), m) if m.alt() => {
+                let step = if m.shift() { 10.0 } else { 1.0 };
+                match arrow {
+                    key::Named::ArrowLeft => Some(AppEvent::TranslateShapes(-step, 0.0)),
+                    key::Named::ArrowRight => Some(AppEvent::TranslateShapes(step, 0.0)),
+                    key::Named::ArrowUp => Some(AppEvent::TranslateShapes(0.0, -step)),
+                    key::Named::ArrowDown => Some(AppEvent::TranslateShapes(0.0, step)),
+                    _ => None,
+                }
+            }
             (key::Key::Character(char), m)
                 if m.contains(Modifiers::SHIFT) && m.contains(Modifiers::ALT) =>
             {
-                match char.as_str() {
-                    "s" => Some(AppEvent::OpenCaptureWindow),
+                match char.to_ascii_lowercase().as_str() {
+                    "s" => Some(AppEvent::PickMonitor),
+                    "z" => Some(AppEvent::RedoCrop),
                     _ => None,
                 }
             }
+            (key::Key::Character(char), m) if m.is_empty() && char.as_str() == "m" => {
+                Some(AppEvent::ToggleMode)
+            }
+            (key::Key::Character(char), m) if m.is_empty() && char.as_str() == "`" => {
+                Some(AppEvent::RestoreSelection)
+            }
+            (key::Key::Character(char), m) if m.is_empty() => char
+                .as_str()
+                .parse::<usize>()
+                .ok()
+                .map(|number| match number.checked_sub(1) {
+                    Some(index) => AppEvent::SelectMonitor(index),
+                    None => AppEvent::SelectDesktop,
+                }),
+            (key::Key::Character(char), Modifiers::ALT) if char.as_str() == "z" => {
+                Some(AppEvent::UndoCrop)
+            }
+            (key::Key::Character(char), m)
+                if m == Modifiers::CTRL | Modifiers::SHIFT && char.eq_ignore_ascii_case("z") =>
+            {
+                Some(AppEvent::Redo)
+            }
             (key::Key::Character(char), Modifiers::CTRL) => match char.as_str() {
                 "z" => Some(AppEvent::Undo),
+                "y" => Some(AppEvent::Redo),
+                "s" => Some(AppEvent::SaveAndContinue),
+                "c" => Some(AppEvent::CopyToClipboard),
                 _ => None,
             },
             _ => None,
         });
 
+        let app_key_release_listener = on_key_release(|key, _| match key {
+            key::Key::Named(key::Named::Space) => Some(AppEvent::HideToolbar(false)),
+            _ => None,
+        });
+
+        // Unlike the listeners above this knows which window the key was pressed in, keys a
+        // widget used are still left alone
+        let escape_listener = listen_with(|event, status, id| match (event, status) {
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key: key::Key::Named(key::Named::Escape),
+                    ..
+                }),
+                Status::Ignored,
+            ) => Some(AppEvent::Escape(id)),
+            _ => None,
+        });
+
         let global_key_listener = Subscription::run(global_key_listener);
 
         let tray_icon_listener = Subscription::run(tray_icon_listener);
@@ -296,9 +980,47 @@ impl App {
 
         let ipc = Subscription::run(ipc_listener);
 
+        let overlay_timeout = if self.config.overlay_timeout_secs > 0
+            && self
+                .windows
+                .values()
+                .any(|window| matches!(window, AppWindow::Capture(_)))
+        {
+            every(Duration::from_secs(1)).map(|_| AppEvent::CheckOverlayTimeout)
+        } else {
+            Subscription::none()
+        };
+
+        let countdown = if self.windows.values().any(|window| {
+            matches!(window, AppWindow::Capture(capture_window)
+                if capture_window.countdown.is_some())
+        }) {
+            every(Duration::from_millis(250)).map(|_| AppEvent::CheckCountdown)
+        } else {
+            Subscription::none()
+        };
+
+        // The configure window's copy of the config is the current one while it is open
+        let follows_system = self.config.theme_mode == ThemeMode::Auto
+            || self.windows.values().any(|window| {
+                matches!(window, AppWindow::Configure(config_window)
+                    if config_window.config.theme_mode == ThemeMode::Auto)
+            });
+        let system_theme = if follows_system {
+            every(Duration::from_secs(2)).map(|_| AppEvent::CheckSystemTheme)
+        } else {
+            Subscription::none()
+        };
+
         Subscription::batch([
+            overlay_timeout,
+            countdown,
+            system_theme,
             window_events,
+            resize_events,
             app_key_listener,
+            app_key_release_listener,
+            escape_listener,
             global_key_listener,
             tray_icon_listener,
             tray_menu_listener,
@@ -306,3 +1028,46 @@ impl App {
         ])
     }
 }
+
+/// Saved size of the configure window, shrunk to fit the monitor under the cursor
+fn configure_window_size(config: &Config) -> Size {
+    // xcap doesn't report the work area, leave room for panels and the taskbar
+    const MARGIN: f32 = 80.0;
+    let (mut width, mut height) = (config.window_width, config.window_height);
+    if let Some(monitor) = cursor_monitor() {
+        width = width.min(monitor.width() as f32 - MARGIN);
+        height = height.min(monitor.height() as f32 - MARGIN);
+    }
+    Size::new(
+        width.max(DEFAULT_WINDOW_SIZE.0),
+        height.max(DEFAULT_WINDOW_SIZE.1),
+    )
+}
+
+/// Monitor under the cursor, falling back to the first one
+fn cursor_monitor() -> Option<Monitor> {
+    let (x, y) = match Mouse::get_mouse_position() {
+        Mouse::Position { x, y } => (x, y),
+        Mouse::Error => (0, 0),
+    };
+    let monitor = Monitor::from_point(x, y)
+        .ok()
+        .or_else(|| Monitor::all().ok()?.into_iter().next());
+    if monitor.is_none() {
+        println!("{}", NO_DISPLAYS);
+    }
+    monitor
+}
+
+/// Opens a file or folder with the system default application
+fn open_path(path: &str) {
+    #[cfg(target_os = "windows")]
+    let cmd = "explorer";
+    #[cfg(target_os = "linux")]
+    let cmd = "xdg-open";
+    #[cfg(target_os = "macos")]
+    let cmd = "open";
+    if let Err(error) = Command::new(cmd).arg(path).spawn() {
+        println!("Could not open {}: {}", path, error);
+    }
+}
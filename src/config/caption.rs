@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use super::watermark::Corner;
+
+/// Current time burned into a corner of every export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampCaption {
+    pub enabled: bool,
+    /// `chrono` format string
+    pub format: String,
+    pub corner: Corner,
+    pub font_size: f32,
+    pub foreground: String,
+    pub background: String,
+    pub background_opacity: f32,
+}
+
+impl Default for TimestampCaption {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: String::from("%Y-%m-%d %H:%M:%S"),
+            corner: Corner::BottomLeft,
+            font_size: 18.0,
+            foreground: String::from("#ffffff"),
+            background: String::from("#000000"),
+            background_opacity: 0.6,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Grid of thumbnails of the latest captures in the screenshots directory
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContactSheet {
+    pub columns: u32,
+    /// Largest side of each thumbnail in pixels
+    pub thumbnail_size: u32,
+    /// How many of the most recent captures are included
+    pub count: u32,
+    /// Prints each file name under its thumbnail
+    pub labels: bool,
+}
+
+impl Default for ContactSheet {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            thumbnail_size: 240,
+            count: 16,
+            labels: true,
+        }
+    }
+}
@@ -1,34 +1,523 @@
-use std::io::{Read, Write};
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+    ops::RangeInclusive,
+    path::Path,
+};
 
+use caption::TimestampCaption;
+use contact_sheet::ContactSheet;
+use preset::{default_presets, AnnotationPreset};
+use profile::CaptureProfile;
 use serde::{Deserialize, Serialize};
-use utils::{default_path, open_config};
+use serde_json::{Map, Value};
+use snippet::SnippetStyle;
+use utils::{
+    config_path, default_path, ensure_directory, is_valid_filename_template, open_config,
+    DEFAULT_FILENAME_TEMPLATE,
+};
+use watermark::Watermark;
+use xcap::image::imageops::FilterType;
 
+pub mod caption;
+pub mod contact_sheet;
+pub mod palette;
+pub mod preset;
+pub mod profile;
+pub mod snippet;
 pub mod utils;
+pub mod watermark;
 
-use crate::theme::Theme;
+use crate::{
+    theme::{Theme, ThemeMode},
+    windows::capture_window::models::{
+        ShapeColor, ShapeStroke, ShapeType, DEFAULT_HIGHLIGHT_OPACITY,
+    },
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub theme: Theme,
+    /// `Auto` replaces `theme` with the OS preference and follows it while running
+    pub theme_mode: ThemeMode,
     pub directory: String,
+    pub profiles: Vec<CaptureProfile>,
+    pub on_empty_done: EmptyDoneAction,
+    pub crop_snap_step: u32,
+    /// Manual selections snap to window edges within `window_snap_distance` logical pixels
+    pub window_snap: bool,
+    pub window_snap_distance: f32,
+    pub focus_blur_radius: f32,
+    pub reveal_format: RevealFormat,
+    pub merge_highlights: bool,
+    /// Seconds of inactivity after which an idle overlay cancels itself, 0 disables it
+    pub overlay_timeout_secs: u64,
+    /// Multiplier applied to the exported image, shapes stay sharp as they are drawn at it
+    pub export_scale: f32,
+    pub export_filter: ExportFilter,
+    pub snippet: SnippetStyle,
+    /// Mirrors every capture as soon as the overlay opens
+    pub flip_on_open: ImageFlip,
+    /// Exports whose largest side exceeds this are downscaled to it
+    pub max_dimension: Option<u32>,
+    pub watermark: Watermark,
+    /// Where finished captures go
+    pub on_done: DoneAction,
+    /// Region saved by the last capture, reused by the silent recapture hotkey
+    pub last_region: Option<CaptureRegion>,
+    /// Last finished manual selection as `(x, y, width, height)` in image pixels, restored
+    /// with the backtick key
+    pub last_selection: Option<(u32, u32, u32, u32)>,
+    pub timestamp: TimestampCaption,
+    pub presets: Vec<AnnotationPreset>,
+    /// How far from a shape, in logical pixels, a click still picks it
+    pub pick_tolerance: f32,
+    /// Exports stay a direct copy of the captured pixels, ignoring scale, size limits, the
+    /// watermark and the timestamp
+    pub exact_pixels: bool,
+    /// Windows whose title contains any of these are blanked out of every capture
+    pub exclude_windows: Vec<String>,
+    pub tile_grid: TileGrid,
+    /// Double-clicking a window in crop mode captures it straight away
+    pub double_click_window: bool,
+    /// Where crop mode shows the selection's size
+    pub readout: ReadoutPlacement,
+    /// Crop mode shows a zoomed view of the pixels around the cursor
+    pub magnifier: bool,
+    /// Crop mode shows the hex and RGBA values of the pixel under the cursor
+    pub pixel_readout: bool,
+    pub contact_sheet: ContactSheet,
+    /// Share of identical pixels above which a capture is reported as blank, silent and remote
+    /// captures are then not saved. 0 disables it
+    pub blank_threshold: f32,
+    /// GIMP `.gpl` or JSON list of hex colors shown as extra swatches, none when empty
+    pub palette_path: String,
+    /// A click without dragging in crop mode selects the element under it
+    pub detect_element: bool,
+    /// Seconds to wait before grabbing the screen, so menus and tooltips can be opened
+    pub delay_secs: u32,
+    /// Tool picked when the overlay opens
+    pub default_tool: ShapeType,
+    /// Size of the selection placed with the Fixed crop tool, in image pixels
+    pub fixed_size: FixedSize,
+    /// Last picked annotation color, the overlay opens with it
+    pub default_color: ShapeColor,
+    /// Last picked stroke width, the overlay opens with it
+    pub default_stroke: ShapeStroke,
+    /// Multiplies the size of the overlay's toolbar buttons and text
+    pub ui_scale: f32,
+    /// Document the vector export writes
+    pub vector_format: VectorFormat,
+    /// Printed above the capture in PDF exports, none when empty
+    pub pdf_title: String,
+    /// Name of saved captures, see `expand_filename_template`
+    pub filename_template: String,
+    pub format: ImageFormat,
+    /// Size the configure window was last resized to, in logical pixels
+    pub window_width: f32,
+    pub window_height: f32,
+    /// Opacity of highlight fills, from 0.1 to 0.9
+    pub highlight_opacity: f32,
+}
+
+/// Size the configure window opens at the first time, and the smallest it can be resized to
+pub const DEFAULT_WINDOW_SIZE: (f32, f32) = (700.0, 430.0);
+
+/// Highlight opacities the settings allow
+pub const HIGHLIGHT_OPACITY_RANGE: RangeInclusive<f32> = 0.1..=0.9;
+
+/// Longest capture delay the settings allow, in seconds
+pub const MAX_DELAY_SECS: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixedSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for FixedSize {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+        }
+    }
+}
+
+impl FixedSize {
+    /// Next common video or social media size, wrapping around
+    pub fn next(&self) -> Self {
+        const SIZES: [(u32, u32); 5] = [
+            (1280, 720),
+            (1920, 1080),
+            (2560, 1440),
+            (1080, 1080),
+            (1080, 1920),
+        ];
+        let index = SIZES
+            .iter()
+            .position(|size| *size == (self.width, self.height))
+            .map_or(0, |index| (index + 1) % SIZES.len());
+        let (width, height) = SIZES[index];
+        Self { width, height }
+    }
+}
+
+impl Display for FixedSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} x {}", self.width, self.height)
+    }
+}
+
+/// Saved captures are split into `rows` x `columns` separate files, 1 x 1 keeps one image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileGrid {
+    pub rows: u32,
+    pub columns: u32,
+}
+
+impl Default for TileGrid {
+    fn default() -> Self {
+        Self {
+            rows: 1,
+            columns: 1,
+        }
+    }
+}
+
+impl TileGrid {
+    pub fn is_single(&self) -> bool {
+        self.rows <= 1 && self.columns <= 1
+    }
+}
+
+/// Part of a monitor in image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRegion {
+    pub monitor: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Output of a finished capture, `CopyClipboard` never writes a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DoneAction {
+    SaveFile,
+    CopyClipboard,
+    #[default]
+    Both,
+    SaveAndCopyPath,
+}
+
+impl DoneAction {
+    pub fn next(&self) -> Self {
+        match self {
+            DoneAction::Both => DoneAction::SaveFile,
+            DoneAction::SaveFile => DoneAction::CopyClipboard,
+            DoneAction::CopyClipboard => DoneAction::SaveAndCopyPath,
+            DoneAction::SaveAndCopyPath => DoneAction::Both,
+        }
+    }
+}
+
+impl Display for DoneAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SaveFile => write!(f, "Save File"),
+            Self::CopyClipboard => write!(f, "Copy Image"),
+            Self::Both => write!(f, "Save and Copy"),
+            Self::SaveAndCopyPath => write!(f, "Save, Copy Path"),
+        }
+    }
+}
+
+/// What `Done` does in draw mode when nothing has been drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum EmptyDoneAction {
+    #[default]
+    ReturnToCrop,
+    FinishCapture,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             theme: Theme::Light,
+            theme_mode: ThemeMode::default(),
             directory: default_path(),
+            profiles: Vec::new(),
+            on_empty_done: EmptyDoneAction::default(),
+            crop_snap_step: 0,
+            window_snap: true,
+            window_snap_distance: 8.0,
+            focus_blur_radius: 8.0,
+            reveal_format: RevealFormat::default(),
+            merge_highlights: false,
+            overlay_timeout_secs: 0,
+            export_scale: 1.0,
+            export_filter: ExportFilter::default(),
+            snippet: SnippetStyle::default(),
+            flip_on_open: ImageFlip::default(),
+            max_dimension: None,
+            watermark: Watermark::default(),
+            on_done: DoneAction::default(),
+            last_region: None,
+            last_selection: None,
+            timestamp: TimestampCaption::default(),
+            presets: default_presets(),
+            pick_tolerance: 6.0,
+            exact_pixels: false,
+            exclude_windows: Vec::new(),
+            tile_grid: TileGrid::default(),
+            double_click_window: false,
+            readout: ReadoutPlacement::default(),
+            magnifier: true,
+            pixel_readout: false,
+            contact_sheet: ContactSheet::default(),
+            blank_threshold: 0.99,
+            palette_path: String::new(),
+            detect_element: false,
+            delay_secs: 0,
+            default_tool: ShapeType::default(),
+            fixed_size: FixedSize::default(),
+            default_color: ShapeColor::default(),
+            default_stroke: ShapeStroke::default(),
+            ui_scale: 1.0,
+            vector_format: VectorFormat::default(),
+            pdf_title: String::new(),
+            filename_template: String::from(DEFAULT_FILENAME_TEMPLATE),
+            format: ImageFormat::default(),
+            window_width: DEFAULT_WINDOW_SIZE.0,
+            window_height: DEFAULT_WINDOW_SIZE.1,
+            highlight_opacity: DEFAULT_HIGHLIGHT_OPACITY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReadoutPlacement {
+    #[default]
+    ScreenBottom,
+    /// Next to the selection's corner, with its origin
+    Selection,
+}
+
+impl ReadoutPlacement {
+    pub fn toggle(&self) -> Self {
+        match self {
+            ReadoutPlacement::ScreenBottom => ReadoutPlacement::Selection,
+            ReadoutPlacement::Selection => ReadoutPlacement::ScreenBottom,
+        }
+    }
+}
+
+impl Display for ReadoutPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ScreenBottom => write!(f, "Screen Bottom"),
+            Self::Selection => write!(f, "At Selection"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ImageFlip {
+    #[default]
+    Off,
+    Horizontal,
+    Vertical,
+}
+
+impl ImageFlip {
+    pub fn next(&self) -> Self {
+        match self {
+            ImageFlip::Off => ImageFlip::Horizontal,
+            ImageFlip::Horizontal => ImageFlip::Vertical,
+            ImageFlip::Vertical => ImageFlip::Off,
+        }
+    }
+}
+
+impl Display for ImageFlip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => write!(f, "Off"),
+            Self::Horizontal => write!(f, "Horizontal"),
+            Self::Vertical => write!(f, "Vertical"),
+        }
+    }
+}
+
+/// Filter used to resample the capture when `export_scale` isn't 1
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ExportFilter {
+    #[default]
+    Smooth,
+    Pixelated,
+}
+
+impl ExportFilter {
+    pub fn toggle(&self) -> Self {
+        match self {
+            ExportFilter::Smooth => ExportFilter::Pixelated,
+            ExportFilter::Pixelated => ExportFilter::Smooth,
+        }
+    }
+
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            ExportFilter::Smooth => FilterType::Lanczos3,
+            ExportFilter::Pixelated => FilterType::Nearest,
+        }
+    }
+}
+
+impl Display for ExportFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Smooth => write!(f, "Smooth"),
+            Self::Pixelated => write!(f, "Pixelated"),
+        }
+    }
+}
+
+/// Output of the annotation reveal export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RevealFormat {
+    #[default]
+    Frames,
+    Gif,
+}
+
+impl RevealFormat {
+    pub fn toggle(&self) -> Self {
+        match self {
+            RevealFormat::Frames => RevealFormat::Gif,
+            RevealFormat::Gif => RevealFormat::Frames,
+        }
+    }
+}
+
+impl Display for RevealFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Frames => write!(f, "PNG Frames"),
+            Self::Gif => write!(f, "GIF"),
+        }
+    }
+}
+
+/// Output of the vector export, shapes stay editable over the embedded capture
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum VectorFormat {
+    #[default]
+    Svg,
+    Pdf,
+}
+
+impl VectorFormat {
+    pub fn toggle(&self) -> Self {
+        match self {
+            VectorFormat::Svg => VectorFormat::Pdf,
+            VectorFormat::Pdf => VectorFormat::Svg,
+        }
+    }
+}
+
+impl Display for VectorFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Svg => write!(f, "SVG"),
+            Self::Pdf => write!(f, "PDF"),
+        }
+    }
+}
+
+/// Encoding of saved captures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    /// Lossy, with `quality` from 1 to 100, transparency is flattened onto white
+    Jpeg { quality: u8 },
+    /// Lossless
+    WebP,
+}
+
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+impl ImageFormat {
+    pub fn next(&self) -> Self {
+        match self {
+            ImageFormat::Png => ImageFormat::Jpeg {
+                quality: DEFAULT_JPEG_QUALITY,
+            },
+            ImageFormat::Jpeg { .. } => ImageFormat::WebP,
+            ImageFormat::WebP => ImageFormat::Png,
+        }
+    }
+
+    /// Same format with the JPEG quality brought into 1..=100
+    pub fn validated(&self) -> Self {
+        match self {
+            ImageFormat::Jpeg { quality } => ImageFormat::Jpeg {
+                quality: (*quality).clamp(1, 100),
+            },
+            format => *format,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg { .. } => "jpg",
+            ImageFormat::WebP => "webp",
+        }
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Png => write!(f, "PNG"),
+            Self::Jpeg { .. } => write!(f, "JPEG"),
+            Self::WebP => write!(f, "WebP"),
+        }
+    }
+}
+
+impl EmptyDoneAction {
+    pub fn toggle(&self) -> Self {
+        match self {
+            EmptyDoneAction::ReturnToCrop => EmptyDoneAction::FinishCapture,
+            EmptyDoneAction::FinishCapture => EmptyDoneAction::ReturnToCrop,
+        }
+    }
+}
+
+impl Display for EmptyDoneAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReturnToCrop => write!(f, "Return to Crop"),
+            Self::FinishCapture => write!(f, "Finish Capture"),
         }
     }
 }
 
 impl Config {
+    /// Loaded config and whether this is the first run, with no config file written yet
     pub fn new() -> (Self, bool) {
+        let first_run = !Path::new(&config_path()).exists();
         match open_config() {
             Ok(mut file) => {
                 let mut file_content = String::new();
                 let _ = file.read_to_string(&mut file_content).unwrap();
-                let bool = file_content.is_empty();
+                let bool = first_run || file_content.is_empty();
                 let config: Config = match toml::from_str::<Config>(&file_content) {
                     Ok(config) => config,
                     Err(_) => {
@@ -43,6 +532,85 @@ impl Config {
         }
     }
 
+    /// Creates `directory` if it is missing, switching to the default one when that fails.
+    /// Returns a warning for the user when the directory was switched
+    pub fn validate_directory(&mut self) -> Option<String> {
+        let error = ensure_directory(&self.directory).err()?;
+        self.directory = default_path();
+        self.update_config();
+        Some(format!("{}, saving to {} instead", error, self.directory))
+    }
+
+    /// Scale exports are resampled by, always 1 with `exact_pixels`
+    pub fn resample_scale(&self) -> f32 {
+        if self.exact_pixels {
+            1.0
+        } else {
+            self.export_scale
+        }
+    }
+
+    /// Largest side exports are downscaled to, never with `exact_pixels`
+    pub fn size_limit(&self) -> Option<u32> {
+        if self.exact_pixels {
+            None
+        } else {
+            self.max_dimension
+        }
+    }
+
+    /// Top level fields that differ from `other`, as JSON
+    pub fn changed_fields(&self, other: &Config) -> Map<String, Value> {
+        let (Ok(Value::Object(fields)), Ok(Value::Object(other))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return Map::new();
+        };
+        fields
+            .into_iter()
+            .filter(|(key, value)| other.get(key) != Some(value))
+            .collect()
+    }
+
+    /// Copy with the given JSON fields set, the rest keep their values, fails on fields `Config`
+    /// doesn't have
+    pub fn with_fields(&self, fields: Map<String, Value>) -> Result<Config, String> {
+        let mut config = serde_json::to_value(self).map_err(|error| error.to_string())?;
+        if let Value::Object(ref mut current) = config {
+            if let Some(key) = fields.keys().find(|key| !current.contains_key(*key)) {
+                return Err(format!("Unknown config field {}", key));
+            }
+            current.extend(fields);
+        }
+        serde_json::from_value(config).map_err(|error| error.to_string())
+    }
+
+    /// Fails on values the settings window wouldn't accept, creating `directory` if it's
+    /// different from `previous`
+    pub fn check(&self, previous: &Config) -> Result<(), String> {
+        let template = &self.filename_template;
+        if !is_valid_filename_template(template) && !template.is_empty() {
+            return Err(format!("Invalid filename template {}", template));
+        }
+        if self.format != self.format.validated() {
+            return Err(String::from("JPEG quality must be between 1 and 100"));
+        }
+        if !HIGHLIGHT_OPACITY_RANGE.contains(&self.highlight_opacity) {
+            return Err(format!(
+                "Highlight opacity must be between {} and {}",
+                HIGHLIGHT_OPACITY_RANGE.start(),
+                HIGHLIGHT_OPACITY_RANGE.end()
+            ));
+        }
+        if self.delay_secs > MAX_DELAY_SECS {
+            return Err(format!("Delay can't be over {} seconds", MAX_DELAY_SECS));
+        }
+        if self.directory != previous.directory {
+            ensure_directory(&self.directory)?;
+        }
+        Ok(())
+    }
+
     pub fn update_config(&self) {
         match open_config() {
             Ok(mut file) => {
@@ -54,3 +622,47 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_fields_carry_only_edits() {
+        let opened = Config::default();
+        let mut edited = opened.clone();
+        edited.crop_snap_step = 8;
+
+        let mut current = opened.clone();
+        current.default_stroke = ShapeStroke::Broad;
+        current.last_selection = Some((10, 20, 300, 200));
+        let merged = current.with_fields(edited.changed_fields(&opened)).unwrap();
+
+        assert_eq!(merged.crop_snap_step, 8);
+        assert_eq!(merged.default_stroke, ShapeStroke::Broad);
+        assert_eq!(merged.last_selection, Some((10, 20, 300, 200)));
+    }
+
+    #[test]
+    fn with_fields_rejects_unknown_fields() {
+        let mut fields = Map::new();
+        fields.insert(String::from("crop_snap_stp"), Value::from(8));
+        assert!(Config::default().with_fields(fields).is_err());
+    }
+
+    #[test]
+    fn check_rejects_what_the_settings_window_would() {
+        let config = Config::default();
+        let checked = |fields: Value| {
+            let Value::Object(fields) = fields else {
+                unreachable!()
+            };
+            config.with_fields(fields).unwrap().check(&config)
+        };
+
+        assert!(checked(serde_json::json!({ "crop_snap_step": 8 })).is_ok());
+        assert!(checked(serde_json::json!({ "filename_template": "../Capture" })).is_err());
+        assert!(checked(serde_json::json!({ "highlight_opacity": 1.0 })).is_err());
+        assert!(checked(serde_json::json!({ "delay_secs": 61 })).is_err());
+    }
+}
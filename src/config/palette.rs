@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use super::snippet::hex_color;
+
+/// Reads the colors of a GIMP `.gpl` palette or a JSON list of `#rrggbb` strings, an empty
+/// path loads none. Entries that can't be read are skipped with a warning
+pub fn load_palette(path: &str) -> Vec<[u8; 3]> {
+    if path.is_empty() {
+        return Vec::new();
+    }
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            println!("Could not read palette {}: {}", path, error);
+            return Vec::new();
+        }
+    };
+    let is_gpl = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gpl"));
+    if is_gpl {
+        parse_gpl(&contents)
+    } else {
+        parse_json(&contents)
+    }
+}
+
+/// Lines of `R G B name` after the header, `#` starts a comment
+fn parse_gpl(contents: &str) -> Vec<[u8; 3]> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with('#')
+                && !line.starts_with("GIMP Palette")
+                && !line.starts_with("Name:")
+                && !line.starts_with("Columns:")
+        })
+        .filter_map(|line| {
+            let mut channels = line
+                .split_whitespace()
+                .take(3)
+                .map(|channel| channel.parse::<u8>().ok());
+            match (channels.next(), channels.next(), channels.next()) {
+                (Some(Some(r)), Some(Some(g)), Some(Some(b))) => Some([r, g, b]),
+                _ => {
+                    println!("Skipping palette entry {}", line);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn parse_json(contents: &str) -> Vec<[u8; 3]> {
+    match serde_json::from_str::<Vec<String>>(contents) {
+        Ok(entries) => entries
+            .iter()
+            .filter_map(|entry| {
+                let color = hex_color(entry);
+                if color.is_none() {
+                    println!("Skipping palette entry {}", entry);
+                }
+                color
+            })
+            .collect(),
+        Err(error) => {
+            println!("Palette is not a list of hex colors: {}", error);
+            Vec::new()
+        }
+    }
+}
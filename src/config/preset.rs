@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::windows::capture_window::models::{ShapeStroke, ShapeType};
+
+/// A named tool configuration shown as a quick button while annotating
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationPreset {
+    pub name: String,
+    pub shape_type: ShapeType,
+    /// `#rrggbb`
+    pub color: String,
+    #[serde(default)]
+    pub stroke_width: ShapeStroke,
+    #[serde(default)]
+    pub is_filled: bool,
+    #[serde(default = "solid")]
+    pub is_solid: bool,
+}
+
+fn solid() -> bool {
+    true
+}
+
+pub fn default_presets() -> Vec<AnnotationPreset> {
+    vec![
+        AnnotationPreset {
+            name: String::from("Error Box"),
+            shape_type: ShapeType::Rectangle,
+            color: String::from("#ff0000"),
+            stroke_width: ShapeStroke::Broad,
+            is_filled: false,
+            is_solid: true,
+        },
+        AnnotationPreset {
+            name: String::from("Note"),
+            shape_type: ShapeType::Rectangle,
+            color: String::from("#ffff00"),
+            stroke_width: ShapeStroke::Medium,
+            is_filled: true,
+            is_solid: false,
+        },
+    ]
+}
@@ -0,0 +1,54 @@
+use iced::{Point, Rectangle};
+use serde::{Deserialize, Serialize};
+
+/// A named crop region, either relative to the monitor or to the window that was focused
+/// when the capture started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureProfile {
+    pub name: String,
+    #[serde(default)]
+    pub relative_to_active_window: bool,
+    pub x: RegionLength,
+    pub y: RegionLength,
+    pub width: RegionLength,
+    pub height: RegionLength,
+}
+
+/// Integers are pixels (negative values count from the far edge), floats are fractions
+/// of the reference size
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RegionLength {
+    Pixels(i32),
+    Fraction(f32),
+}
+
+impl RegionLength {
+    pub fn resolve(self, total: f32) -> f32 {
+        match self {
+            RegionLength::Pixels(pixels) if pixels < 0 => total + pixels as f32,
+            RegionLength::Pixels(pixels) => pixels as f32,
+            RegionLength::Fraction(fraction) => fraction * total,
+        }
+    }
+}
+
+impl CaptureProfile {
+    /// Resolves the region against `reference`, clamped to it
+    pub fn resolve(&self, reference: Rectangle) -> (Point, Point) {
+        let x = self.x.resolve(reference.width).clamp(0.0, reference.width);
+        let y = self
+            .y
+            .resolve(reference.height)
+            .clamp(0.0, reference.height);
+        let width = self.width.resolve(reference.width).min(reference.width - x);
+        let height = self
+            .height
+            .resolve(reference.height)
+            .min(reference.height - y);
+        (
+            Point::new(reference.x + x, reference.y + y),
+            Point::new(reference.x + x + width, reference.y + y + height),
+        )
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Look of images rendered from clipboard text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnippetStyle {
+    /// Path to a TTF/OTF font, the bundled monospace font is used when empty
+    pub font_path: String,
+    pub font_size: f32,
+    pub foreground: String,
+    pub background: String,
+    pub padding: u32,
+}
+
+impl Default for SnippetStyle {
+    fn default() -> Self {
+        Self {
+            font_path: String::new(),
+            font_size: 20.0,
+            foreground: String::from("#d4d4d4"),
+            background: String::from("#1e1e1e"),
+            padding: 32,
+        }
+    }
+}
+
+/// Parses `#rrggbb`, falling back to `fallback` when malformed
+pub fn parse_hex_color(value: &str, fallback: [u8; 3]) -> [u8; 3] {
+    hex_color(value).unwrap_or(fallback)
+}
+
+/// Parses `#rrggbb`, `None` when malformed
+pub fn hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value
+        .trim()
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
@@ -4,7 +4,59 @@ use std::{
     path::Path,
 };
 
-pub fn open_config() -> Result<File, std::io::Error> {
+use chrono::{DateTime, Local};
+
+/// File name, without extension, every capture gets unless the config sets another
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "Capture_%Y-%m-%d-%H-%M-%S";
+
+/// Templates can't name other directories, so captures stay in the configured one
+pub fn is_valid_filename_template(template: &str) -> bool {
+    !template.trim().is_empty()
+        && !template.contains(['/', '\\'])
+        && template != "."
+        && template != ".."
+}
+
+/// Replaces `%Y %m %d %H %M %S` with the parts of `time` and `%n` with `counter`, anything else
+/// is kept as written
+pub fn expand_filename_template(template: &str, time: &DateTime<Local>, counter: u32) -> String {
+    let mut name = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            name.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => name.push_str(&time.format("%Y").to_string()),
+            Some('m') => name.push_str(&time.format("%m").to_string()),
+            Some('d') => name.push_str(&time.format("%d").to_string()),
+            Some('H') => name.push_str(&time.format("%H").to_string()),
+            Some('M') => name.push_str(&time.format("%M").to_string()),
+            Some('S') => name.push_str(&time.format("%S").to_string()),
+            Some('n') => name.push_str(&counter.to_string()),
+            Some(other) => {
+                name.push('%');
+                name.push(other);
+            }
+            None => name.push('%'),
+        }
+    }
+    name
+}
+
+/// Literal start of `template` before its first placeholder, which every name it expands to
+/// shares. Invalid templates fall back to the default like saving does
+pub fn filename_template_prefix(template: &str) -> &str {
+    let template = if is_valid_filename_template(template) {
+        template
+    } else {
+        DEFAULT_FILENAME_TEMPLATE
+    };
+    template.split('%').next().unwrap_or_default()
+}
+
+pub fn config_path() -> String {
     #[cfg(target_os = "windows")]
     let path = format!(
         "{}{}\\.config\\capter.toml",
@@ -18,6 +70,11 @@ pub fn open_config() -> Result<File, std::io::Error> {
         var_os("HOME").unwrap().to_string_lossy()
     );
 
+    path
+}
+
+pub fn open_config() -> Result<File, std::io::Error> {
+    let path = config_path();
     if !Path::new(&path).exists() {
         DirBuilder::new()
             .recursive(true)
@@ -56,6 +113,22 @@ pub fn default_path() -> String {
     path
 }
 
+/// Creates `directory` and its parents if they are missing, failing when it can't be written to
+pub fn ensure_directory(directory: &str) -> Result<(), String> {
+    DirBuilder::new()
+        .recursive(true)
+        .create(directory)
+        .map_err(|error| format!("Could not create {}: {}", directory, error))?;
+    let readonly = std::fs::metadata(directory)
+        .map(|metadata| metadata.permissions().readonly())
+        .map_err(|error| format!("Could not read {}: {}", directory, error))?;
+    if readonly {
+        Err(format!("{} is read-only", directory))
+    } else {
+        Ok(())
+    }
+}
+
 pub fn shorten_path(path: String) -> String {
     #[cfg(target_os = "windows")]
     let home_path = format!(
@@ -75,3 +148,35 @@ pub fn shorten_path(path: String) -> String {
         replaced_path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filename_template_prefix_stops_at_the_first_placeholder() {
+        assert_eq!(
+            filename_template_prefix(DEFAULT_FILENAME_TEMPLATE),
+            "Capture_"
+        );
+        assert_eq!(filename_template_prefix("shot-%n"), "shot-");
+        assert_eq!(filename_template_prefix("%Y-%m-%d"), "");
+        assert_eq!(filename_template_prefix("../%n"), "Capture_");
+    }
+
+    #[test]
+    fn ensure_directory_creates_missing_parents() {
+        let root = std::env::temp_dir().join(format!("capter-ensure-{}", std::process::id()));
+        let nested = root.join("screenshots").join("2026");
+        let directory = nested.to_string_lossy();
+        // Left over by an earlier run that failed
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(ensure_directory(&directory), Ok(()));
+        assert!(nested.is_dir());
+        // Already there is fine too
+        assert_eq!(ensure_directory(&directory), Ok(()));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Logo composited onto every export, disabled while `path` is empty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watermark {
+    pub path: String,
+    pub corner: Corner,
+    pub opacity: f32,
+    /// Distance from the edges in pixels
+    pub margin: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            corner: Corner::default(),
+            opacity: 0.8,
+            margin: 16,
+        }
+    }
+}
+
+impl Corner {
+    pub fn next(&self) -> Self {
+        match self {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopLeft,
+        }
+    }
+}
+
+impl Display for Corner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TopLeft => write!(f, "Top Left"),
+            Self::TopRight => write!(f, "Top Right"),
+            Self::BottomLeft => write!(f, "Bottom Left"),
+            Self::BottomRight => write!(f, "Bottom Right"),
+        }
+    }
+}
@@ -1,9 +1,19 @@
 pub const APPNAME: &str = "Capter";
 
+pub const NO_DISPLAYS: &str = "No displays available; are you running headless?";
+
+pub const BLANK_CAPTURE: &str =
+    "Capture looks blank, protected content or a locked screen may have blocked it";
+
+pub const MIXED_SCALE: &str =
+    "All Monitors needs every monitor at the same display scale, capture them one at a time";
+
 pub const FONT_MEDIUM: &[u8] = include_bytes!("../assets/fonts/SpaceGrotesk-Medium.ttf");
 
 pub const FONT_BOLD: &[u8] = include_bytes!("../assets/fonts/SpaceGrotesk-Bold.ttf");
 
+pub const FONT_MONO: &[u8] = include_bytes!("../assets/fonts/FiraMono-Medium.ttf");
+
 pub const FONT_ICONS: &[u8] = include_bytes!("../assets/fonts/icons.ttf");
 
 pub const APPICON: &[u8] = include_bytes!("../assets/resources/icon.png");
@@ -1,25 +1,287 @@
+use std::{io::Write, path::PathBuf, time::Duration};
+
 use iced::{
-    futures::{SinkExt, Stream},
+    futures::{channel::mpsc::Sender, SinkExt, Stream},
     stream,
 };
 use interprocess::local_socket::{
-    traits::tokio::Listener, GenericNamespaced, ListenerOptions, ToNsName,
+    tokio::Stream as TokioStream,
+    traits::{tokio::Listener, Stream as _},
+    GenericNamespaced, ListenerOptions, ToNsName,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc,
 };
 
-use crate::{app::AppEvent, consts::APPNAME};
+use crate::{
+    app::AppEvent,
+    consts::APPNAME,
+    rpc::{RpcRequest, RpcResponse},
+};
 
-pub fn ipc_listener() -> impl Stream<Item = AppEvent> {
-    stream::channel(10, |mut output| async move {
-        let name = APPNAME.to_ns_name::<GenericNamespaced>().unwrap();
+/// What a second launch asks the running instance to do, picked from its arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceCommand {
+    /// Raises the open overlay or window, else opens the configure window
+    #[default]
+    Focus,
+    StartCapture,
+    OpenConfigureWindow,
+    /// Saves the primary monitor straight away
+    QuickCapture,
+}
 
-        let listner_opts = ListenerOptions::new().name(name);
+/// Line a second launch writes to the socket, told apart from RPC requests by its field
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Handoff {
+    command: InstanceCommand,
+}
 
-        let listener = listner_opts.create_tokio().unwrap();
+impl InstanceCommand {
+    /// `--capture` starts a capture, `--configure` opens the configure window and `--fullscreen`
+    /// saves the primary monitor
+    pub fn from_args(arg: Option<&str>) -> Self {
+        match arg {
+            Some("--capture") => InstanceCommand::StartCapture,
+            Some("--configure") => InstanceCommand::OpenConfigureWindow,
+            Some("--fullscreen") => InstanceCommand::QuickCapture,
+            _ => InstanceCommand::Focus,
+        }
+    }
+
+    fn event(self) -> AppEvent {
+        match self {
+            InstanceCommand::Focus => AppEvent::FocusWindow,
+            InstanceCommand::StartCapture => AppEvent::OpenCaptureWindow,
+            InstanceCommand::OpenConfigureWindow => AppEvent::OpenConfigureWindow,
+            InstanceCommand::QuickCapture => AppEvent::QuickCapture,
+        }
+    }
+}
+
+/// Serves the local socket. When it can't be opened the app keeps running without remote
+/// control or single instance handoff
+pub fn ipc_listener() -> impl Stream<Item = AppEvent> {
+    stream::channel(10, |output| async move {
+        const ATTEMPTS: u32 = 3;
+        const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+        let mut listener = None;
+        for attempt in 1..=ATTEMPTS {
+            let name = match socket_name().to_ns_name::<GenericNamespaced>() {
+                Ok(name) => name,
+                Err(error) => {
+                    println!("Invalid socket name {}: {}", socket_name(), error);
+                    break;
+                }
+            };
+            match ListenerOptions::new().name(name).create_tokio() {
+                Ok(created) => {
+                    listener = Some(created);
+                    break;
+                }
+                Err(error) => {
+                    println!(
+                        "Could not open the socket (attempt {} of {}): {}",
+                        attempt, ATTEMPTS, error
+                    );
+                    if !remove_stale_socket() {
+                        tokio::time::sleep(RETRY_DELAY).await;
+                    }
+                }
+            }
+        }
+        let Some(listener) = listener else {
+            println!("Remote control is unavailable");
+            return std::future::pending().await;
+        };
 
         loop {
-            if let Ok(_stream) = listener.accept().await {
-                output.send(AppEvent::OpenConfigureWindow).await.unwrap();
+            match listener.accept().await {
+                Ok(stream) => {
+                    tokio::spawn(serve_connection(stream, output.clone()));
+                }
+                // Backs off so a listener that keeps failing doesn't spin
+                Err(error) => {
+                    println!("Could not accept a connection: {}", error);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
             }
         }
     })
 }
+
+/// Removes the socket file of an instance that is no longer running, returning whether one
+/// was removed
+fn remove_stale_socket() -> bool {
+    match socket_path() {
+        Some(path) if path.exists() && !is_instance_running() => {
+            match std::fs::remove_file(&path) {
+                Ok(_) => {
+                    println!("Removed stale socket {}", path.display());
+                    true
+                }
+                Err(error) => {
+                    println!("Could not remove {}: {}", path.display(), error);
+                    false
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Answers newline separated JSON requests until the client hangs up. A second instance
+/// sends an `InstanceCommand` instead. A client that sends nothing, like the probe of
+/// `is_instance_running`, is ignored
+async fn serve_connection(stream: TokioStream, mut output: Sender<AppEvent>) {
+    if !is_same_user(&stream) {
+        println!("Refused a connection from another user");
+        return;
+    }
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(handoff) = serde_json::from_str::<Handoff>(&line) {
+            output.send(handoff.command.event()).await.ok();
+            continue;
+        }
+        let response = match serde_json::from_str::<RpcRequest>(&line) {
+            Ok(request) => {
+                let id = request.id.clone();
+                let (reply, mut response) = mpsc::channel(1);
+                output.send(AppEvent::Rpc(request, reply)).await.ok();
+                response
+                    .recv()
+                    .await
+                    .unwrap_or_else(|| RpcResponse::error(id, String::from("No response")))
+            }
+            Err(error) => RpcResponse::error(Value::Null, error.to_string()),
+        };
+        let mut json = serde_json::to_string(&response).unwrap();
+        json.push('\n');
+        if writer.write_all(json.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Whether the client runs as the current user. Abstract sockets on Linux have no file
+/// permissions, so any user could connect without this check
+#[cfg(unix)]
+fn is_same_user(stream: &TokioStream) -> bool {
+    use interprocess::local_socket::traits::StreamCommon;
+
+    match stream.peer_creds() {
+        Ok(creds) => creds.euid() == Some(unsafe { libc::getuid() }),
+        Err(error) => {
+            println!("Could not read the client's credentials: {}", error);
+            false
+        }
+    }
+}
+
+/// Named pipes only let their owner write to them by default
+#[cfg(windows)]
+fn is_same_user(_stream: &TokioStream) -> bool {
+    true
+}
+
+/// Socket name, unique per user so instances of different users on one machine don't collide
+fn socket_name() -> String {
+    #[cfg(unix)]
+    let user = unsafe { libc::getuid() }.to_string();
+
+    #[cfg(windows)]
+    let user = std::env::var("USERNAME").unwrap_or_default();
+
+    format!("{}-{}", APPNAME, user)
+}
+
+/// Hands `command` to another instance of the current user, returning false when none answers
+pub fn send_to_running_instance(command: InstanceCommand) -> bool {
+    let Ok(name) = socket_name().to_ns_name::<GenericNamespaced>() else {
+        return false;
+    };
+    let Ok(mut stream) = interprocess::local_socket::Stream::connect(name) else {
+        return false;
+    };
+    let mut line = serde_json::to_string(&Handoff { command }).unwrap();
+    line.push('\n');
+    if let Err(error) = stream.write_all(line.as_bytes()) {
+        println!("Could not reach the running instance: {}", error);
+    }
+    true
+}
+
+/// Returns true if another instance of the current user answers on the socket
+pub fn is_instance_running() -> bool {
+    match socket_name().to_ns_name::<GenericNamespaced>() {
+        Ok(name) => interprocess::local_socket::Stream::connect(name).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Filesystem path backing the socket, on platforms without a socket namespace
+fn socket_path() -> Option<PathBuf> {
+    if cfg!(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "windows"
+    )) {
+        None
+    } else {
+        Some(PathBuf::from("/tmp").join(socket_name()))
+    }
+}
+
+/// Prints the socket state for `--ipc-status`, returning the process exit code
+pub fn ipc_status() -> i32 {
+    let running = is_instance_running();
+    let socket = match socket_path() {
+        Some(path) if path.exists() => "present",
+        Some(_) => "absent",
+        None => "namespaced",
+    };
+    println!("socket: {}", socket);
+    println!("instance: {}", if running { "running" } else { "stopped" });
+    if !running && socket == "present" {
+        println!("stale: yes");
+        1
+    } else {
+        0
+    }
+}
+
+/// Removes a socket left behind by a crashed instance for `--ipc-clean`
+pub fn ipc_clean() -> i32 {
+    if is_instance_running() {
+        println!("instance running, nothing removed");
+        return 1;
+    }
+    match socket_path() {
+        Some(path) if path.exists() => match std::fs::remove_file(&path) {
+            Ok(_) => {
+                println!("removed: {}", path.display());
+                0
+            }
+            Err(error) => {
+                println!("failed to remove {}: {}", path.display(), error);
+                1
+            }
+        },
+        _ => {
+            println!("no stale socket");
+            0
+        }
+    }
+}
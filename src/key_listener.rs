@@ -29,11 +29,26 @@ pub fn global_key_listener() -> impl Stream<Item = AppEvent> {
                     Key::Alt => alt_pressed = true,
                     Key::ShiftLeft | Key::ShiftRight => shift_pressed = true,
                     Key::KeyS if alt_pressed && shift_pressed => {
-                        output.send(AppEvent::OpenCaptureWindow).await.unwrap();
+                        output.send(AppEvent::PickMonitor).await.unwrap();
+                    }
+                    Key::KeyR if alt_pressed && shift_pressed => {
+                        output.send(AppEvent::SilentRecapture).await.unwrap()
+                    }
+                    Key::KeyV if alt_pressed && shift_pressed => {
+                        output.send(AppEvent::OpenLastCapture).await.unwrap()
                     }
                     Key::KeyO if alt_pressed && shift_pressed => {
                         output.send(AppEvent::OpenConfigureWindow).await.unwrap()
                     }
+                    Key::KeyL if alt_pressed && shift_pressed => {
+                        output.send(AppEvent::ToggleLiveOverlay).await.unwrap()
+                    }
+                    Key::KeyD if alt_pressed && shift_pressed => {
+                        output.send(AppEvent::ToggleLiveDrawing).await.unwrap()
+                    }
+                    Key::KeyC if alt_pressed && shift_pressed => {
+                        output.send(AppEvent::ClearLiveOverlay).await.unwrap()
+                    }
                     _ => (),
                 },
                 EventType::KeyRelease(key) => match key {
@@ -0,0 +1,18 @@
+//! Screenshot logic of Capter, usable from scripts without opening the GUI.
+//!
+//! `capture_region` grabs part of a monitor with the export settings applied, `crop_image`
+//! cuts a selection out of an image and `save_capture` writes it like a finished capture.
+//! The overlay goes through the same functions.
+
+pub mod app;
+pub mod config;
+pub mod consts;
+pub mod ipc;
+pub mod key_listener;
+pub mod rpc;
+pub mod theme;
+pub mod tray_icon;
+pub mod windows;
+
+pub use config::{CaptureRegion, Config};
+pub use windows::capture_window::capture::{capture_region, crop_image, save_capture};
@@ -1,27 +1,43 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use app::App;
-use consts::{APPNAME, FONT_BOLD, FONT_ICONS, FONT_MEDIUM, MEDIUM};
+use capter::{
+    app::App,
+    config::Config,
+    consts::{FONT_BOLD, FONT_ICONS, FONT_MEDIUM, MEDIUM, NO_DISPLAYS},
+    ipc::{ipc_clean, ipc_status, send_to_running_instance, InstanceCommand},
+    tray_icon::create_tray_icon,
+    windows::capture_window::capture::quick_capture,
+};
 use iced::daemon;
-use interprocess::local_socket::{traits::Stream, GenericNamespaced, ToNsName};
-use tray_icon::create_tray_icon;
-
-mod app;
-mod config;
-mod consts;
-mod ipc;
-mod key_listener;
-mod theme;
-mod tray_icon;
-mod windows;
 
 fn main() -> Result<(), iced::Error> {
-    let name = APPNAME.to_ns_name::<GenericNamespaced>().unwrap();
+    let arg = std::env::args().nth(1);
+    match arg.as_deref() {
+        Some("--ipc-status") => std::process::exit(ipc_status()),
+        Some("--ipc-clean") => std::process::exit(ipc_clean()),
+        _ => (),
+    }
 
-    if interprocess::local_socket::Stream::connect(name).is_ok() {
+    let command = InstanceCommand::from_args(arg.as_deref());
+    if send_to_running_instance(command) {
         return Ok(());
     };
 
+    if xcap::Monitor::all().map_or(true, |monitors| monitors.is_empty()) {
+        println!("{}", NO_DISPLAYS);
+        std::process::exit(1);
+    }
+
+    // Without a running instance the capture is saved and the process exits
+    if command == InstanceCommand::QuickCapture {
+        let (config, _) = Config::new();
+        std::process::exit(if quick_capture(&config).is_some() {
+            0
+        } else {
+            1
+        });
+    }
+
     #[cfg(not(target_os = "linux"))]
     let _tray_icon = create_tray_icon();
 
@@ -37,7 +53,6 @@ fn main() -> Result<(), iced::Error> {
         .font(FONT_BOLD)
         .font(FONT_ICONS)
         .default_font(MEDIUM)
-        .scale_factor(App::scale_factor)
         .style(App::style)
         .theme(App::theme)
         .subscription(App::subscription)
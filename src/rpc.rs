@@ -0,0 +1,203 @@
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use iced::Task;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::Sender;
+use xcap::{image::ImageFormat, Monitor};
+
+use crate::{
+    app::{App, AppEvent},
+    config::{
+        utils::{ensure_directory, shorten_path},
+        CaptureRegion, Config,
+    },
+    windows::{
+        capture_window::capture::{capture_region, write_capture},
+        AppWindow,
+    },
+};
+
+/// One line of JSON sent over the IPC socket
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Reply to a request, carrying either `result` or `error`
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcResponse {
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RpcResponse {
+    pub fn error(id: Value, error: String) -> Self {
+        Self {
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+
+    pub fn new(id: Value, result: Result<Value, String>) -> Self {
+        match result {
+            Ok(result) => Self {
+                id,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Self::error(id, error),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionParams {
+    /// Monitor id, the primary monitor when missing
+    monitor: Option<u32>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    /// Returns the PNG inline instead of saving it
+    #[serde(default)]
+    base64: bool,
+}
+
+impl App {
+    /// Answers `request` on `reply`. Captures are taken and written off the UI thread, and
+    /// answered once they are done
+    pub fn handle_rpc(
+        &mut self,
+        request: RpcRequest,
+        reply: Sender<RpcResponse>,
+    ) -> Task<AppEvent> {
+        let result = match request.method.as_str() {
+            "capture_region" => match self.rpc_capture_region(request.params) {
+                Ok((region, base64)) => {
+                    let config = self.config.clone();
+                    let capture = async move {
+                        tokio::task::spawn_blocking(move || {
+                            capture_for_rpc(region, &config, base64)
+                        })
+                        .await
+                        .unwrap_or_else(|error| Err(error.to_string()))
+                    };
+                    return Task::perform(capture, move |result| {
+                        AppEvent::RpcCaptured(request.id.clone(), result, reply.clone())
+                    });
+                }
+                Err(error) => Err(error),
+            },
+            "list_windows" => rpc_list_windows(),
+            "get_config" => serde_json::to_value(&self.config).map_err(|error| error.to_string()),
+            "set_config" => self.rpc_set_config(request.params),
+            method => Err(format!("Unknown method {}", method)),
+        };
+        reply.try_send(RpcResponse::new(request.id, result)).ok();
+        Task::none()
+    }
+
+    /// Region and whether it is returned inline, from the params of `capture_region`
+    fn rpc_capture_region(&self, params: Value) -> Result<(CaptureRegion, bool), String> {
+        let params: RegionParams =
+            serde_json::from_value(params).map_err(|error| error.to_string())?;
+        let monitor = match params.monitor {
+            Some(id) => id,
+            None => Monitor::all()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|monitor| monitor.is_primary())
+                .map(|monitor| monitor.id())
+                .ok_or_else(|| String::from("No monitor found"))?,
+        };
+        let region = CaptureRegion {
+            monitor,
+            x: params.x,
+            y: params.y,
+            width: params.width,
+            height: params.height,
+        };
+        Ok((region, params.base64))
+    }
+
+    /// Merges the given fields into the config, the rest keep their values
+    fn rpc_set_config(&mut self, params: Value) -> Result<Value, String> {
+        let Value::Object(fields) = params else {
+            return Err(String::from("Expected an object of config fields"));
+        };
+        let config = self.config.with_fields(fields)?;
+        config.check(&self.config)?;
+
+        for window in self.windows.values_mut() {
+            if let AppWindow::Configure(config_window) = window {
+                config_window.config = config.clone();
+                config_window.opened = config.clone();
+                config_window.path = shorten_path(config.directory.clone());
+            }
+        }
+        self.config = config;
+        self.config.update_config();
+        Ok(Value::Bool(true))
+    }
+}
+
+/// Captures `region` and returns it inline as PNG or the path it was saved to
+fn capture_for_rpc(region: CaptureRegion, config: &Config, base64: bool) -> Result<Value, String> {
+    let image = capture_region(region, config)?;
+
+    if base64 {
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|error| error.to_string())?;
+        Ok(json!({
+            "width": image.width(),
+            "height": image.height(),
+            "image": STANDARD.encode(png.into_inner()),
+        }))
+    } else {
+        ensure_directory(&config.directory)?;
+        let path = write_capture(
+            &image,
+            &config.directory,
+            None,
+            &config.filename_template,
+            config.format,
+        )?;
+        Ok(json!({
+            "width": image.width(),
+            "height": image.height(),
+            "path": path,
+        }))
+    }
+}
+
+fn rpc_list_windows() -> Result<Value, String> {
+    let windows = xcap::Window::all().map_err(|error| error.to_string())?;
+    Ok(windows
+        .into_iter()
+        .filter(|window| !window.is_minimized() && window.width() != 0 && window.height() != 0)
+        .map(|window| {
+            json!({
+                "id": window.id(),
+                "title": window.title(),
+                "app_name": window.app_name(),
+                "monitor": window.current_monitor().id(),
+                "x": window.x(),
+                "y": window.y(),
+                "width": window.width(),
+                "height": window.height(),
+            })
+        })
+        .collect())
+}
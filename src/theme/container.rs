@@ -1,13 +1,15 @@
 use iced::{
     border::Radius,
     widget::container::{self, Style},
-    Background, Border,
+    Background, Border, Color, Shadow, Vector,
 };
 
 use super::Theme;
 
 pub enum ContainerClass {
     Default,
+    /// Square frame with a soft drop shadow, for captures pinned to the screen
+    Pinned,
 }
 
 impl container::Catalog for Theme {
@@ -17,16 +19,32 @@ impl container::Catalog for Theme {
         ContainerClass::Default
     }
 
-    fn style(&self, _class: &Self::Class<'_>) -> Style {
+    fn style(&self, class: &Self::Class<'_>) -> Style {
         let palette = self.palette();
-        Style {
-            background: Some(Background::Color(palette.surface)),
-            border: Border {
-                color: palette.secondary,
-                width: 0.5,
-                radius: Radius::new(8),
+        match class {
+            ContainerClass::Default => Style {
+                background: Some(Background::Color(palette.surface)),
+                border: Border {
+                    color: palette.secondary,
+                    width: 0.5,
+                    radius: Radius::new(8),
+                },
+                ..Default::default()
+            },
+            ContainerClass::Pinned => Style {
+                background: Some(Background::Color(palette.surface)),
+                border: Border {
+                    color: palette.secondary,
+                    width: 0.5,
+                    radius: Radius::new(0),
+                },
+                shadow: Shadow {
+                    color: Color::from_rgba(0.0, 0.0, 0.0, 0.4),
+                    offset: Vector::new(0.0, 2.0),
+                    blur_radius: 8.0,
+                },
+                ..Default::default()
             },
-            ..Default::default()
         }
     }
 }
@@ -10,7 +10,10 @@ use serde::{Deserialize, Serialize};
 
 pub mod button;
 pub mod container;
+pub mod scrollable;
+pub mod slider;
 pub mod text;
+pub mod text_input;
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum Theme {
@@ -21,6 +24,14 @@ pub enum Theme {
     Custom(Palette),
 }
 
+/// Whether the theme is picked by hand or follows the OS light / dark preference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ThemeMode {
+    #[default]
+    Manual,
+    Auto,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Animate)]
 pub struct Palette {
     pub background: Color,
@@ -77,6 +88,14 @@ impl Theme {
     }
 }
 
+/// Theme matching the OS appearance, light when it has no preference
+pub fn system_theme() -> Theme {
+    match dark_light::detect() {
+        dark_light::Mode::Dark => Theme::Dark,
+        _ => Theme::Light,
+    }
+}
+
 impl Display for Theme {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,48 @@
+use iced::{
+    border::Radius,
+    widget::{
+        container,
+        scrollable::{Catalog, Rail, Scroller, Status, Style},
+    },
+    Background, Border,
+};
+
+use super::Theme;
+
+pub enum ScrollableClass {
+    Default,
+}
+
+impl Catalog for Theme {
+    type Class<'a> = ScrollableClass;
+
+    fn default<'a>() -> Self::Class<'a> {
+        ScrollableClass::Default
+    }
+
+    fn style(&self, _class: &Self::Class<'_>, status: Status) -> Style {
+        let palette = self.palette();
+        let scroller_color = match status {
+            Status::Active => palette.primary,
+            Status::Hovered { .. } | Status::Dragged { .. } => palette.secondary,
+        };
+        let rail = Rail {
+            background: None,
+            border: Border::default(),
+            scroller: Scroller {
+                color: scroller_color,
+                border: Border {
+                    color: palette.secondary,
+                    width: 0.0,
+                    radius: Radius::new(8),
+                },
+            },
+        };
+        Style {
+            container: container::Style::default(),
+            vertical_rail: rail,
+            horizontal_rail: rail,
+            gap: Some(Background::Color(palette.background)),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use iced::{
+    widget::slider::{Catalog, Handle, HandleShape, Rail, Status, Style},
+    Background, Border, Color,
+};
+
+use super::Theme;
+
+pub enum SliderClass {
+    Default,
+}
+
+impl Catalog for Theme {
+    type Class<'a> = SliderClass;
+
+    fn default<'a>() -> Self::Class<'a> {
+        SliderClass::Default
+    }
+
+    fn style(&self, _class: &Self::Class<'_>, status: Status) -> Style {
+        let palette = self.palette();
+        Style {
+            rail: Rail {
+                backgrounds: (
+                    Background::Color(palette.active_primary),
+                    Background::Color(palette.secondary),
+                ),
+                width: 4.0,
+                border: Border {
+                    radius: 2.0.into(),
+                    ..Default::default()
+                },
+            },
+            handle: Handle {
+                shape: HandleShape::Circle { radius: 8.0 },
+                background: Background::Color(match status {
+                    Status::Active => palette.active_primary,
+                    Status::Hovered | Status::Dragged => palette.active_secondary,
+                }),
+                border_width: 0.0,
+                border_color: Color::TRANSPARENT,
+            },
+        }
+    }
+}
@@ -50,6 +50,36 @@ pub fn create_tray_icon() -> TrayIcon {
                 Code::KeyS,
             )),
         ),
+        &MenuItem::with_id(
+            "recapture",
+            "Recapture Last Region",
+            true,
+            Some(Accelerator::new(
+                Some(Modifiers::SHIFT.union(Modifiers::ALT)),
+                Code::KeyR,
+            )),
+        ),
+        &MenuItem::with_id(
+            "last",
+            "Open Last Capture",
+            true,
+            Some(Accelerator::new(
+                Some(Modifiers::SHIFT.union(Modifiers::ALT)),
+                Code::KeyV,
+            )),
+        ),
+        &MenuItem::with_id("sheet", "Contact Sheet of Recent Captures", true, None),
+        &MenuItem::with_id("snippet", "Capture Clipboard Text", true, None),
+        &MenuItem::with_id("clipboard", "Annotate Clipboard Image", true, None),
+        &MenuItem::with_id(
+            "live",
+            "Live Annotation",
+            true,
+            Some(Accelerator::new(
+                Some(Modifiers::SHIFT.union(Modifiers::ALT)),
+                Code::KeyL,
+            )),
+        ),
         &PredefinedMenuItem::separator(),
         &MenuItem::with_id("exit", "Exit", true, None),
     ])
@@ -107,6 +137,12 @@ pub fn tray_menu_listener() -> impl Stream<Item = AppEvent> {
                         sleep(Duration::from_secs(1));
                         output.send(AppEvent::OpenCaptureWindow).await.unwrap()
                     }
+                    "recapture" => output.send(AppEvent::SilentRecapture).await.unwrap(),
+                    "last" => output.send(AppEvent::OpenLastCapture).await.unwrap(),
+                    "sheet" => output.send(AppEvent::CreateContactSheet).await.unwrap(),
+                    "live" => output.send(AppEvent::ToggleLiveOverlay).await.unwrap(),
+                    "snippet" => output.send(AppEvent::OpenSnippetWindow).await.unwrap(),
+                    "clipboard" => output.send(AppEvent::OpenClipboardImage).await.unwrap(),
                     "exit" => output.send(AppEvent::ExitApp).await.unwrap(),
                     _ => (),
                 }
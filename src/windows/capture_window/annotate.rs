@@ -1,20 +1,26 @@
 use iced::{
     event::Status,
-    mouse::{Button, Cursor, Interaction},
+    keyboard::{
+        self,
+        key::{Key, Named},
+    },
+    mouse::{Button, Cursor, Interaction, ScrollDelta},
     widget::canvas::{
-        path::{arc::Elliptical, Builder},
-        Event, Fill, Frame, Geometry, LineCap, LineDash, Path, Program, Stroke, Style,
+        Event, Fill, Frame, Geometry, LineCap, LineDash, Path, Program, Stroke, Style, Text,
     },
-    Color, Point, Radians, Rectangle, Renderer, Size, Vector,
+    Color, Pixels, Point, Rectangle, Renderer, Size,
 };
+use xcap::image::Rgba;
 
 use crate::{
+    config::ReadoutPlacement,
     theme::Theme,
     windows::capture_window::models::{CropMode, Mode},
 };
 
 use super::{
-    models::{Shape, ShapeType}, utils::resolve_arrow_points, CaptureEvent, CaptureWindow
+    models::{RgbaColor, SelectionHandle, Shape, ShapeType},
+    CaptureEvent, CaptureWindow,
 };
 
 impl Program<CaptureEvent, Theme> for CaptureWindow {
@@ -29,8 +35,16 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
         _cursor: Cursor,
     ) -> Vec<Geometry<Renderer>> {
         let shapes_frame = self.cache.draw(renderer, bounds.size(), |frame| {
+            let mut merged_colors = Vec::new();
             for shape in self.shapes.iter() {
-                draw_shape(frame, shape);
+                if self.config.merge_highlights && shape.is_highlight() {
+                    if !merged_colors.contains(&shape.fill()) {
+                        merged_colors.push(shape.fill());
+                        draw_merged_highlights(frame, &self.shapes, shape.fill());
+                    }
+                } else {
+                    draw_shape(frame, shape);
+                }
             }
         });
 
@@ -38,7 +52,49 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
 
         match self.mode {
             Mode::Draw => {
-                draw_shape(&mut frame, &self.shape);
+                if self.is_typing() {
+                    let mut shape = self.shape.clone();
+                    shape.text.push('|');
+                    draw_shape(&mut frame, &shape);
+                } else {
+                    draw_shape(&mut frame, &self.shape);
+                }
+
+                let selected = self
+                    .selected_index
+                    .and_then(|index| self.shapes.get(index))
+                    .and_then(|shape| shape.bounds());
+                for bounds in [selected, self.measure_from].into_iter().flatten() {
+                    const PADDING: f32 = 6.0;
+                    let outline = Path::rectangle(
+                        Point::new(bounds.x - PADDING, bounds.y - PADDING),
+                        Size::new(bounds.width + 2.0 * PADDING, bounds.height + 2.0 * PADDING),
+                    );
+                    let selection_stroke = Stroke {
+                        style: Style::Solid(Color::WHITE),
+                        width: 2.0,
+                        line_dash: LineDash {
+                            segments: &[6.0, 4.0],
+                            offset: 0,
+                        },
+                        ..Default::default()
+                    };
+                    frame.stroke(&outline, selection_stroke);
+                }
+
+                // Handles on the endpoints that can be dragged to reshape the selection
+                if let Some(endpoints) = self
+                    .selected_index
+                    .and_then(|index| self.shapes.get(index))
+                    .filter(|shape| shape.shape_type.is_resizable())
+                    .and_then(|shape| shape.endpoints)
+                {
+                    const HANDLE: f32 = 8.0;
+                    for point in [endpoints.initial_pt, endpoints.final_pt] {
+                        let top_left = Point::new(point.x - HANDLE / 2.0, point.y - HANDLE / 2.0);
+                        frame.fill_rectangle(top_left, Size::new(HANDLE, HANDLE), Color::WHITE);
+                    }
+                }
             }
             Mode::Crop => {
                 if !matches!(self.crop_mode, CropMode::FullScreen) {
@@ -121,6 +177,65 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
                     };
 
                     frame.stroke(&selection, dashed_stroke);
+
+                    // Handles in the middle of each edge, the corners are marked above
+                    if matches!(self.crop_mode, CropMode::ManualSelection) {
+                        const HANDLE: f32 = 8.0;
+                        let middle = Point::new(
+                            (top_left.x + bottom_right.x) / 2.0,
+                            (top_left.y + bottom_right.y) / 2.0,
+                        );
+                        for point in [
+                            Point::new(middle.x, top_left.y),
+                            Point::new(middle.x, bottom_right.y),
+                            Point::new(top_left.x, middle.y),
+                            Point::new(bottom_right.x, middle.y),
+                        ] {
+                            frame.fill_rectangle(
+                                Point::new(point.x - HANDLE / 2.0, point.y - HANDLE / 2.0),
+                                Size::new(HANDLE, HANDLE),
+                                Color::WHITE,
+                            );
+                        }
+                    }
+
+                    if self.config.readout == ReadoutPlacement::Selection {
+                        draw_readout(
+                            &mut frame,
+                            self.selection_rect(),
+                            bounds,
+                            top_left,
+                            bottom_right,
+                        );
+                    }
+                }
+
+                let (top_left, bottom_right) = if matches!(self.crop_mode, CropMode::FullScreen) {
+                    (Point::ORIGIN, Point::new(bounds.width, bounds.height))
+                } else {
+                    self.endpoints.normalize()
+                };
+                if let Some((origin, size)) = self.safe_area.fit(top_left, bottom_right) {
+                    let guide_stroke = Stroke {
+                        style: Style::Solid(Color::from_rgba8(255, 255, 0, 0.8)),
+                        width: 1.0,
+                        line_dash: LineDash {
+                            segments: &[6.0, 4.0],
+                            offset: 0,
+                        },
+                        ..Default::default()
+                    };
+                    frame.stroke(&Path::rectangle(origin, size), guide_stroke);
+                }
+
+                if self.config.magnifier {
+                    draw_magnifier(
+                        &mut frame,
+                        |x, y| self.redacted_pixel(x, y),
+                        self.to_image_point(self.cursor_position),
+                        self.cursor_position,
+                        bounds,
+                    );
                 }
             }
         }
@@ -132,8 +247,8 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
         &self,
         _state: &mut Self::State,
         event: Event,
-        _bounds: Rectangle,
-        _cursor: Cursor,
+        bounds: Rectangle,
+        cursor: Cursor,
     ) -> (Status, Option<CaptureEvent>) {
         match event {
             iced::widget::canvas::Event::Mouse(event) => match event {
@@ -142,12 +257,23 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
                     Some(CaptureEvent::UpdateCurrentPosition(position)),
                 ),
                 iced::mouse::Event::ButtonPressed(button) => {
-                    if button == Button::Left {
+                    // The cursor is unavailable while it is over the toolbar
+                    if button == Button::Left && cursor.is_over(bounds) {
                         (Status::Captured, Some(CaptureEvent::SetInitialPoint))
+                    } else if button == Button::Right && cursor.is_over(bounds) {
+                        (Status::Captured, Some(CaptureEvent::PickShape))
                     } else {
                         (Status::Ignored, None)
                     }
                 }
+                iced::mouse::Event::WheelScrolled { delta } if matches!(self.mode, Mode::Draw) => {
+                    let (ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. }) = delta;
+                    if y == 0.0 {
+                        (Status::Ignored, None)
+                    } else {
+                        (Status::Captured, Some(CaptureEvent::ScrollTool(y > 0.0)))
+                    }
+                }
                 iced::mouse::Event::ButtonReleased(button) => {
                     if button == Button::Left {
                         (Status::Captured, Some(CaptureEvent::SetFinalPoint))
@@ -157,6 +283,36 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
                 }
                 _ => (Status::Ignored, None),
             },
+            // Captured keys don't reach the app-wide shortcuts
+            iced::widget::canvas::Event::Keyboard(keyboard::Event::KeyPressed {
+                key,
+                modifiers,
+                text,
+                ..
+            }) if self.is_typing() => match key {
+                Key::Named(Named::Escape) => (Status::Captured, Some(CaptureEvent::CancelText)),
+                Key::Named(Named::Enter) => (Status::Captured, Some(CaptureEvent::CommitText)),
+                Key::Named(Named::Backspace) => {
+                    (Status::Captured, Some(CaptureEvent::EraseTextChar))
+                }
+                // AltGr arrives as Ctrl + Alt on Windows
+                _ if (modifiers.control() && !modifiers.alt()) || modifiers.logo() => {
+                    (Status::Ignored, None)
+                }
+                _ => match text
+                    .and_then(|text| text.chars().next())
+                    .filter(|c| !c.is_control())
+                {
+                    Some(c) => (Status::Captured, Some(CaptureEvent::InsertTextChar(c))),
+                    None => (Status::Ignored, None),
+                },
+            },
+            iced::widget::canvas::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                (
+                    Status::Ignored,
+                    Some(CaptureEvent::ModifiersChanged(modifiers)),
+                )
+            }
             _ => (Status::Ignored, None),
         }
     }
@@ -167,7 +323,41 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
         bounds: Rectangle,
         cursor: Cursor,
     ) -> Interaction {
-        if cursor.is_over(bounds) {
+        let handle = self
+            .adjusting
+            .or_else(|| self.selection_handle_at(self.cursor_position))
+            .filter(|_| matches!(self.mode, Mode::Crop) && cursor.is_over(bounds));
+        if self.dragging.is_some() || matches!(self.adjusting, Some(SelectionHandle::Body(_))) {
+            Interaction::Grabbing
+        } else if let Some(handle) = handle {
+            match handle {
+                SelectionHandle::Body(_) => Interaction::Grab,
+                SelectionHandle::Edge {
+                    left,
+                    right,
+                    top,
+                    bottom,
+                } => match (left || right, top || bottom) {
+                    (true, false) => Interaction::ResizingHorizontally,
+                    (false, true) => Interaction::ResizingVertically,
+                    _ => Interaction::Crosshair,
+                },
+            }
+        } else if cursor.is_over(bounds)
+            && matches!(self.mode, Mode::Draw)
+            && !self.sampling
+            && self.shape.endpoints.is_none()
+            && self.shape.shape_type != ShapeType::Measure
+            && self.grab_at(self.cursor_position).is_some()
+        {
+            Interaction::Grab
+        } else if cursor.is_over(bounds)
+            && matches!(self.mode, Mode::Draw)
+            && !self.sampling
+            && self.shape.shape_type == ShapeType::Text
+        {
+            Interaction::Text
+        } else if cursor.is_over(bounds) {
             Interaction::Crosshair
         } else {
             Interaction::default()
@@ -175,62 +365,182 @@ impl Program<CaptureEvent, Theme> for CaptureWindow {
     }
 }
 
-fn draw_shape(frame: &mut Frame, shape: &Shape) {
+pub fn draw_shape(frame: &mut Frame, shape: &Shape) {
     if let Some(endpoints) = shape.endpoints {
-        let shape_type = shape.shape_type;
-        let color = shape.color.into_iced_color(shape.is_solid);
-        let stroke = Stroke::default()
-            .with_width(shape.stroke_width.f32())
-            .with_color(color);
-        match shape_type {
-            ShapeType::Rectangle => {
+        shape.shape_type.drawable().draw(shape, endpoints, frame);
+    }
+}
+
+/// Size and origin of the selection in image pixels, below its bottom left corner or above
+/// its top when the screen edge is in the way
+fn draw_readout(
+    frame: &mut Frame,
+    (x, y, width, height): (u32, u32, u32, u32),
+    bounds: Rectangle,
+    top_left: Point,
+    bottom_right: Point,
+) {
+    const SIZE: f32 = 16.0;
+    const PADDING: f32 = 4.0;
+    const GAP: f32 = 6.0;
+
+    let content = format!("{} x {} at {}, {}", width, height, x, y);
+    // The canvas can't measure text, digits are a bit over half the font size wide
+    let size = Size::new(
+        content.len() as f32 * SIZE * 0.6 + 2.0 * PADDING,
+        SIZE + 2.0 * PADDING,
+    );
+    let left = top_left.x.clamp(0.0, (bounds.width - size.width).max(0.0));
+    let top = if bottom_right.y + GAP + size.height <= bounds.height {
+        bottom_right.y + GAP
+    } else if top_left.y - GAP - size.height >= 0.0 {
+        top_left.y - GAP - size.height
+    } else {
+        bottom_right.y - GAP - size.height
+    };
+
+    frame.fill_rectangle(
+        Point::new(left, top),
+        size,
+        Fill::from(Color::from_rgba(0.0, 0.0, 0.0, 0.7)),
+    );
+    frame.fill_text(Text {
+        content,
+        position: Point::new(left + PADDING, top + PADDING),
+        color: Color::WHITE,
+        size: Pixels(SIZE),
+        ..Default::default()
+    });
+}
+
+/// Zoomed square of the image pixels around `pixel` as `pixel_at` reads them, with the pixel
+/// under the cursor outlined and its coordinates below. It sits below and right of the cursor,
+/// flipping to the other side of it near the screen edges
+fn draw_magnifier(
+    frame: &mut Frame,
+    pixel_at: impl Fn(i64, i64) -> Option<Rgba<u8>>,
+    pixel: Point,
+    cursor: Point,
+    bounds: Rectangle,
+) {
+    /// Pixels shown on each side of the one under the cursor
+    const REACH: i64 = 7;
+    const CELL: f32 = 8.0;
+    const GAP: f32 = 24.0;
+    const SIZE: f32 = 14.0;
+    const PADDING: f32 = 4.0;
+
+    let side = (2 * REACH + 1) as f32 * CELL;
+    let label_height = SIZE + 2.0 * PADDING;
+    let left = if cursor.x + GAP + side <= bounds.width {
+        cursor.x + GAP
+    } else {
+        cursor.x - GAP - side
+    };
+    let top = if cursor.y + GAP + side + label_height <= bounds.height {
+        cursor.y + GAP
+    } else {
+        cursor.y - GAP - side - label_height
+    };
+
+    let (px, py) = (pixel.x.floor() as i64, pixel.y.floor() as i64);
+    for dy in -REACH..=REACH {
+        for dx in -REACH..=REACH {
+            let (x, y) = (px + dx, py + dy);
+            let color = match pixel_at(x, y) {
+                Some(Rgba([r, g, b, _])) => Color::from_rgb8(r, g, b),
+                None => Color::BLACK,
+            };
+            frame.fill_rectangle(
+                Point::new(
+                    left + (dx + REACH) as f32 * CELL,
+                    top + (dy + REACH) as f32 * CELL,
+                ),
+                Size::new(CELL, CELL),
+                color,
+            );
+        }
+    }
+
+    let center = REACH as f32 * CELL;
+    let guide = Stroke {
+        style: Style::Solid(Color::from_rgba(1.0, 1.0, 1.0, 0.4)),
+        width: 1.0,
+        ..Default::default()
+    };
+    for (from, to) in [
+        (
+            Point::new(left, top + center + CELL / 2.0),
+            Point::new(left + side, top + center + CELL / 2.0),
+        ),
+        (
+            Point::new(left + center + CELL / 2.0, top),
+            Point::new(left + center + CELL / 2.0, top + side),
+        ),
+    ] {
+        frame.stroke(&Path::line(from, to), guide);
+    }
+    let target = Path::rectangle(
+        Point::new(left + center, top + center),
+        Size::new(CELL, CELL),
+    );
+    frame.stroke(
+        &target,
+        Stroke {
+            style: Style::Solid(Color::BLACK),
+            width: 3.0,
+            ..Default::default()
+        },
+    );
+    frame.stroke(
+        &target,
+        Stroke {
+            style: Style::Solid(Color::WHITE),
+            width: 1.0,
+            ..Default::default()
+        },
+    );
+    frame.stroke(
+        &Path::rectangle(Point::new(left, top), Size::new(side, side)),
+        Stroke {
+            style: Style::Solid(Color::WHITE),
+            width: 2.0,
+            ..Default::default()
+        },
+    );
+
+    frame.fill_rectangle(
+        Point::new(left, top + side),
+        Size::new(side, label_height),
+        Fill::from(Color::from_rgba(0.0, 0.0, 0.0, 0.7)),
+    );
+    frame.fill_text(Text {
+        content: format!("{}, {}", px, py),
+        position: Point::new(left + PADDING, top + side + PADDING),
+        color: Color::WHITE,
+        size: Pixels(SIZE),
+        ..Default::default()
+    });
+}
+
+/// Fills every highlight of `color` as a single union so overlaps don't darken
+fn draw_merged_highlights(frame: &mut Frame, shapes: &[Shape], color: RgbaColor) {
+    let path = Path::new(|builder| {
+        for shape in shapes
+            .iter()
+            .filter(|shape| shape.is_highlight() && shape.fill() == color)
+        {
+            if let Some(endpoints) = shape.endpoints {
                 let (top_left, bottom_right) = endpoints.normalize();
                 let size = (bottom_right - top_left).into();
-                let path = Path::rectangle(top_left, size);
-                if shape.is_filled {
-                    let fill = Fill::from(color);
-                    frame.fill(&path, fill);
+                let radius = shape.fitted_corner_radius(endpoints);
+                if radius > 0.0 {
+                    builder.rounded_rectangle(top_left, size, radius.into());
                 } else {
-                    frame.stroke(&path, stroke);
+                    builder.rectangle(top_left, size);
                 }
             }
-            ShapeType::Ellipse => {
-                let (top_left, bottom_right) = endpoints.normalize();
-                let size = bottom_right - top_left;
-                let radii = Vector::new(size.x / 2.0, size.y / 2.0);
-                let center = Point::new(top_left.x + radii.x, top_left.y + radii.y);
-                let arc = Elliptical {
-                    center,
-                    radii,
-                    rotation: Radians(0.0),
-                    start_angle: Radians(0.0),
-                    end_angle: Radians(360.0),
-                };
-                let mut builder = Builder::new();
-                builder.ellipse(arc);
-                let path = builder.build();
-                if shape.is_filled {
-                    let fill = Fill::from(color);
-                    frame.fill(&path, fill);
-                } else {
-                    frame.stroke(&path, stroke);
-                };
-            }
-            ShapeType::Line => {
-                let path = Path::line(endpoints.initial_pt, endpoints.final_pt);
-                frame.stroke(&path, stroke);
-            }
-            ShapeType::Arrow => {
-                let (right_pt, left_pt) = resolve_arrow_points(endpoints.initial_pt, endpoints.final_pt);
-                let mut builder = Builder::new();
-                builder.move_to(endpoints.initial_pt);
-                builder.line_to(endpoints.final_pt);
-                builder.move_to(right_pt);
-                builder.line_to(endpoints.final_pt);
-                builder.line_to(left_pt);
-                let path = builder.build();
-                frame.stroke(&path, stroke);
-            }
         }
-    }
+    });
+    frame.fill(&path, Fill::from(color.into_iced()));
 }
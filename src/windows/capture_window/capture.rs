@@ -1,194 +1,2826 @@
-use arboard::{Clipboard, ImageData};
-use iced::{widget::canvas::Cache, Point};
+use std::{collections::HashMap, fs::File, path::Path, sync::Mutex, time::Instant};
+
+use active_win_pos_rs::get_active_window;
+use arboard::Clipboard;
+#[cfg(not(target_os = "windows"))]
+use arboard::ImageData;
+use iced::{keyboard::Modifiers, widget::canvas::Cache, Point, Rectangle, Size, Vector};
 use indexmap::IndexMap;
-use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Transform};
 use xcap::{
-    image::{imageops::overlay, DynamicImage, ImageFormat, RgbaImage},
+    image::{
+        codecs::{
+            gif::{GifEncoder, Repeat},
+            jpeg::JpegEncoder,
+        },
+        imageops::{
+            blur, crop_imm, flip_horizontal_in_place, flip_vertical_in_place, overlay, resize,
+            FilterType,
+        },
+        Delay, Frame, ImageError, ImageFormat, Pixel, Rgb, RgbImage, Rgba, RgbaImage,
+    },
     Monitor,
 };
 
+use crate::{
+    config::{
+        palette::load_palette,
+        snippet::parse_hex_color,
+        utils::{
+            ensure_directory, expand_filename_template, is_valid_filename_template,
+            DEFAULT_FILENAME_TEMPLATE,
+        },
+        watermark::{Corner, Watermark},
+        CaptureRegion, Config, DoneAction, ImageFlip, ImageFormat as OutputFormat, RevealFormat,
+        TileGrid, VectorFormat,
+    },
+    consts::{BLANK_CAPTURE, MIXED_SCALE, NO_DISPLAYS},
+};
+
 use super::{
-    models::{CapturedWindow, CropMode, Endpoints, Mode, Shape, ShapeType}, utils::resolve_arrow_points, CaptureWindow
+    drawable::push_rounded_rect,
+    models::{
+        AspectLock, CapturedWindow, CropMode, Endpoints, ExportCanvas, Mode, Redaction, RgbaColor,
+        SafeArea, SelectionHandle, Shape, ShapeColor, ShapeDrag, ShapeMove, ShapeStroke, ShapeType,
+        STEP_MARKER_RADIUS,
+    },
+    pdf::export_pdf,
+    snippet::draw_timestamp,
+    svg::{export_svg, is_vector},
+    utils::{nearest_edges, snap_angle, snap_square},
+    CaptureWindow,
 };
 
 impl CaptureWindow {
-    pub fn new(monitor: Monitor) -> Self {
-        let id = monitor.id();
-        let image = monitor.capture_image().unwrap();
-        let windows = get_windows(id);
-        let scale_factor = monitor.scale_factor();
-        CaptureWindow {
+    /// Captures `monitor`, `None` if it can't be read
+    pub fn new(monitor: Monitor, config: &Config) -> Option<Self> {
+        // Relative to the monitor like the windows from `get_windows`
+        let active_window = get_active_window().ok().map(|window| Rectangle {
+            x: window.position.x as f32 - monitor.x() as f32,
+            y: window.position.y as f32 - monitor.y() as f32,
+            width: window.position.width as f32,
+            height: window.position.height as f32,
+        });
+        let image = capture_monitor(&monitor)?;
+        let windows = get_windows(&monitor);
+        let mut capture_window = CaptureWindow::with_capture(
+            image,
+            windows,
+            active_window,
+            monitor.scale_factor(),
+            config,
+        );
+        capture_window.monitor_id = Some(monitor.id());
+        Some(capture_window)
+    }
+
+    /// Opens the overlay on an `image` that was captured along with `windows` and the
+    /// `active_window`, both in its pixels
+    pub fn with_capture(
+        mut image: RgbaImage,
+        mut windows: IndexMap<u32, CapturedWindow>,
+        active_window: Option<Rectangle>,
+        scale_factor: f32,
+        config: &Config,
+    ) -> Self {
+        let blank_capture = is_blank(&image, config.blank_threshold);
+        if blank_capture {
+            println!("{}", BLANK_CAPTURE);
+        }
+        mask_excluded_windows(&mut image, &mut windows, &config.exclude_windows);
+        let mut capture_window = CaptureWindow {
             scale_factor,
             cursor_position: Point::ORIGIN,
             crop_mode: CropMode::FullScreen,
             mode_desc: String::from("Fullscreen"),
             image,
+            canvas: None,
             windows,
             mode: Mode::default(),
-            shape: Shape::default(),
+            shape: Shape {
+                shape_type: config.default_tool,
+                stroke_width: config.default_stroke,
+                ..Default::default()
+            },
             endpoints: Endpoints::default(),
             shapes: Vec::new(),
             cache: Cache::new(),
+            version: 0,
+            config: config.clone(),
+            active_window,
+            safe_area: SafeArea::default(),
+            aspect_lock: AspectLock::default(),
+            last_input: Instant::now(),
+            discard: false,
+            selected_index: None,
+            toolbar_expanded: false,
+            toolbar_hidden: false,
+            watermark: load_watermark(&config.watermark),
+            monitor_id: None,
+            last_tools: HashMap::new(),
+            draw_origin: Point::ORIGIN,
+            modifiers: Modifiers::empty(),
+            crop_history: Vec::new(),
+            crop_redo: Vec::new(),
+            saved_path: None,
+            last_click: None,
+            redo_stack: Vec::new(),
+            shape_moves: Vec::new(),
+            redo_moves: Vec::new(),
+            blank_capture,
+            measure_from: None,
+            step_counter: 0,
+            dragging: None,
+            adjusting: None,
+            palette: load_palette(&config.palette_path)
+                .into_iter()
+                .map(|[r, g, b]| ShapeColor::Custom(RgbaColor::new(r, g, b, 255)))
+                .collect(),
+            custom_color: None,
+            sampling: false,
+            countdown: None,
+        };
+        capture_window
+            .shape
+            .set_color(config.default_color.rgba(), config.highlight_opacity);
+        capture_window.flip_image(config.flip_on_open);
+        capture_window
+    }
+
+    /// Captures every monitor as one overlay spanning the virtual desktop, returned with the
+    /// desktop's top left corner to place it at. One window has a single scale factor, so
+    /// monitors at different scales are refused, as is a desktop where none can be read
+    pub fn desktop(config: &Config) -> Result<(Self, (i32, i32)), String> {
+        let monitors = Monitor::all().unwrap_or_default();
+        let scale_factor = monitors
+            .first()
+            .map(|monitor| monitor.scale_factor())
+            .ok_or(NO_DISPLAYS)?;
+        if monitors
+            .iter()
+            .any(|monitor| monitor.scale_factor() != scale_factor)
+        {
+            return Err(String::from(MIXED_SCALE));
+        }
+        let (image, (origin_x, origin_y)) =
+            capture_desktop(&monitors).ok_or("Could not capture any monitor")?;
+        let windows = get_desktop_windows((origin_x, origin_y));
+        let active_window = get_active_window().ok().map(|window| Rectangle {
+            x: window.position.x as f32 - origin_x as f32,
+            y: window.position.y as f32 - origin_y as f32,
+            width: window.position.width as f32,
+            height: window.position.height as f32,
+        });
+        let mut capture_window =
+            Self::with_capture(image, windows, active_window, scale_factor, config);
+        capture_window.mode_desc = String::from("All Monitors");
+        Ok((capture_window, (origin_x, origin_y)))
+    }
+
+    /// Captures the monitor again in place of the image, flipped as it was on open and keeping
+    /// the selection and shapes. `false` if the image isn't a screen capture or the monitor
+    /// can't be read
+    pub fn recapture(&mut self) -> bool {
+        let Some(monitor) = self.monitor_id.and_then(|id| {
+            Monitor::all()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|monitor| monitor.id() == id)
+        }) else {
+            return false;
+        };
+        let Some(mut image) = capture_monitor(&monitor) else {
+            return false;
+        };
+        if image.dimensions() != self.image.dimensions() {
+            println!(
+                "Monitor {} changed size, keeping the first capture",
+                monitor.id()
+            );
+            return false;
         }
+        self.blank_capture = is_blank(&image, self.config.blank_threshold);
+        mask_excluded_windows(
+            &mut image,
+            &mut get_windows(&monitor),
+            &self.config.exclude_windows,
+        );
+        match self.config.flip_on_open {
+            ImageFlip::Off => (),
+            ImageFlip::Horizontal => flip_horizontal_in_place(&mut image),
+            ImageFlip::Vertical => flip_vertical_in_place(&mut image),
+        }
+        self.image = image;
+        true
     }
 
-    pub fn take_screenshot(self, directory: String) {
-        let (img_width, img_height) = self.image.dimensions();
-        let top = draw_shapes(&self.image, self.shapes);
+    /// Seconds left before a timed capture grabs the screen, rounded up
+    pub fn countdown_remaining(&self) -> Option<u64> {
+        self.countdown.map(|deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs_f32()
+                .ceil() as u64
+        })
+    }
 
-        let final_image = match self.crop_mode {
-            CropMode::FullScreen => {
-                let mut base = self.image;
-                overlay(&mut base, &top, 0, 0);
-                base
+    /// Mirrors the capture, moving shapes, the selection and window bounds along with it
+    pub fn flip_image(&mut self, flip: ImageFlip) {
+        let (width, height) = self.image.dimensions();
+        match flip {
+            ImageFlip::Off => return,
+            ImageFlip::Horizontal => flip_horizontal_in_place(&mut self.image),
+            ImageFlip::Vertical => flip_vertical_in_place(&mut self.image),
+        }
+
+        let (overlay_width, overlay_height) = (
+            width as f32 / self.scale_factor,
+            height as f32 / self.scale_factor,
+        );
+        let flip_point = move |point: Point| match flip {
+            ImageFlip::Horizontal => Point::new(overlay_width - point.x, point.y),
+            _ => Point::new(point.x, overlay_height - point.y),
+        };
+        let flip_endpoints = move |endpoints: &mut Endpoints| {
+            endpoints.initial_pt = flip_point(endpoints.initial_pt);
+            endpoints.final_pt = flip_point(endpoints.final_pt);
+        };
+        let moved = self
+            .shape_moves
+            .iter_mut()
+            .chain(self.redo_moves.iter_mut())
+            .map(|shape_move| &mut shape_move.shape);
+        for shape in self
+            .shapes
+            .iter_mut()
+            .chain(self.redo_stack.iter_mut())
+            .chain(moved)
+        {
+            shape.map_points(flip_point);
+            if let Some(ref mut redaction) = shape.redaction {
+                let (patch_width, patch_height) = redaction.pixels.dimensions();
+                match flip {
+                    ImageFlip::Horizontal => {
+                        redaction.x = width as i64 - redaction.x - patch_width as i64;
+                        flip_horizontal_in_place(&mut redaction.pixels);
+                    }
+                    _ => {
+                        redaction.y = height as i64 - redaction.y - patch_height as i64;
+                        flip_vertical_in_place(&mut redaction.pixels);
+                    }
+                }
             }
-            CropMode::SpecificWindow(id) => {
-                let window = self.windows.get(&id).unwrap();
+        }
+        flip_endpoints(&mut self.endpoints);
+        if let Some((ref mut x, ref mut y, canvas_width, canvas_height)) = self.canvas {
+            match flip {
+                ImageFlip::Horizontal => *x = width - *x - canvas_width,
+                _ => *y = height - *y - canvas_height,
+            }
+        }
+        self.crop_history
+            .iter_mut()
+            .chain(self.crop_redo.iter_mut())
+            .for_each(|(_, endpoints)| flip_endpoints(endpoints));
 
-                let x = window.x;
-                let y = window.y;
-                let width = window.width;
-                let height = window.height;
-                let window_img = &window.image;
-
-                let mut base = RgbaImage::new(img_width, img_height);
-                overlay(&mut base, window_img, x as i64, y as i64);
-                overlay(&mut base, &top, 0, 0);
-                let x = if x < 0 { 0u32 } else { x as u32 };
-                let y = if y < 0 { 0u32 } else { y as u32 };
-                DynamicImage::from(base)
-                    .crop_imm(x, y, width, height)
-                    .into_rgba8()
-            }
-            CropMode::ManualSelection | CropMode::SelectionInProgress => {
-                let (top_left, bottom_right) = self.endpoints.normalize();
-                let x = top_left.x;
-                let y = top_left.y;
-                let size = bottom_right - top_left;
-                let width = size.x;
-                let height = size.y;
-                let mut base = self.image;
-                overlay(&mut base, &top, 0, 0);
-                DynamicImage::from(base)
-                    .crop_imm(x as u32, y as u32, width as u32, height as u32)
-                    .into_rgba8()
+        for window in self.windows.values_mut() {
+            match flip {
+                ImageFlip::Horizontal => {
+                    window.x = width as i32 - window.x - window.width as i32;
+                    flip_horizontal_in_place(&mut window.image);
+                }
+                _ => {
+                    window.y = height as i32 - window.y - window.height as i32;
+                    flip_vertical_in_place(&mut window.image);
+                }
+            }
+        }
+        if let Some(ref mut window) = self.active_window {
+            match flip {
+                ImageFlip::Horizontal => window.x = width as f32 - window.x - window.width,
+                _ => window.y = height as f32 - window.y - window.height,
+            }
+        }
+
+        self.cache.clear();
+    }
+
+    pub fn selected_shape_mut(&mut self) -> Option<&mut Shape> {
+        self.shapes.get_mut(self.selected_index?)
+    }
+
+    /// Whether a text box is open and takes keyboard input
+    pub fn is_typing(&self) -> bool {
+        matches!(self.mode, Mode::Draw)
+            && self.shape.shape_type == ShapeType::Text
+            && self.shape.endpoints.is_some()
+    }
+
+    /// Places `shape` on top of the others, which forgets everything that could be redone
+    pub fn push_shape(&mut self, shape: Shape) {
+        self.selected_index = None;
+        self.shapes.push(shape);
+        self.redo_stack.clear();
+        self.redo_moves.clear();
+        self.cache.clear();
+    }
+
+    /// Adds the open text box as a shape unless nothing was typed, then closes it
+    pub fn commit_text(&mut self) {
+        if !self.is_typing() {
+            return;
+        }
+        if !self.shape.text.trim().is_empty() {
+            self.push_shape(self.shape.clone());
+        }
+        self.shape.endpoints = None;
+        self.shape.text.clear();
+    }
+
+    /// Stretches the shape being drawn to the cursor, mirrored around where it was started
+    /// while Alt is held. Shift keeps lines at multiples of 45° and boxes square
+    pub fn update_drawn_shape(&mut self) {
+        match self.shape.shape_type {
+            ShapeType::Text | ShapeType::StepMarker => return,
+            ShapeType::Freehand => {
+                if self.shape.endpoints.is_some() {
+                    self.shape.push_point(self.cursor_position);
+                }
+                return;
             }
+            ShapeType::Measure => return self.update_measurement(),
+            _ => (),
+        }
+        let (origin, cursor) = (self.draw_origin, self.cursor_position);
+        let cursor = match self.shape.shape_type {
+            _ if !self.modifiers.shift() => cursor,
+            ShapeType::Line | ShapeType::Arrow => snap_angle(origin, cursor),
+            _ => snap_square(origin, cursor),
         };
+        let centered = self.modifiers.alt() && self.shape.shape_type != ShapeType::Spotlight;
+        if let Some(ref mut endpoints) = self.shape.endpoints {
+            endpoints.initial_pt = if centered {
+                origin - (cursor - origin)
+            } else {
+                origin
+            };
+            endpoints.final_pt = cursor;
+        }
+    }
 
-        save_image(final_image, directory);
+    /// Topmost shape within the pick tolerance of `point`
+    pub fn shape_at(&self, point: Point) -> Option<usize> {
+        self.shapes
+            .iter()
+            .rposition(|shape| shape.distance_to(point) <= self.config.pick_tolerance)
     }
-}
 
-pub fn draw_shapes(image: &RgbaImage, shapes: Vec<Shape>) -> RgbaImage {
-    let (width, height) = image.dimensions();
-    let mut pixmap = Pixmap::new(width, height).unwrap();
-    let transform = Transform::identity();
-    for shape in shapes.iter() {
-        let endpoints = shape.endpoints.unwrap();
-        let mut paint = Paint::default();
-        let color = shape.color.into_paint(shape.is_solid);
-        paint.set_color(color);
-        let mut stroke = Stroke::default();
-        if !shape.is_filled {
-            stroke.width = shape.stroke_width.f32()
-        }
-        match shape.shape_type {
-            ShapeType::Rectangle | ShapeType::Ellipse => {
-                let (top_left, bottom_right) = endpoints.normalize();
-                let (x, y) = (top_left.x, top_left.y);
-                let size = bottom_right - top_left;
-                let w = size.x;
-                let h = size.y;
-                let rect = Rect::from_xywh(x, y, w, h).unwrap();
-                if matches!(shape.shape_type, ShapeType::Rectangle) {
-                    let path = PathBuilder::from_rect(rect);
-                    if shape.is_filled {
-                        pixmap.fill_path(&path, &paint, FillRule::EvenOdd, transform, None);
-                    } else {
-                        pixmap.stroke_path(&path, &paint, &stroke, transform, None);
-                    }
+    /// Part of a shape under `point` that can be dragged: an endpoint of any resizable shape,
+    /// else the body of any shape but a blur, topmost first
+    pub fn grab_at(&self, point: Point) -> Option<(usize, ShapeDrag)> {
+        let tolerance = self.config.pick_tolerance;
+        let handle = self
+            .shapes
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, shape)| shape.shape_type.is_resizable())
+            .find_map(|(index, shape)| {
+                let endpoints = shape.endpoints?;
+                if endpoints.initial_pt.distance(point) <= tolerance {
+                    Some((index, ShapeDrag::InitialPoint))
+                } else if endpoints.final_pt.distance(point) <= tolerance {
+                    Some((index, ShapeDrag::FinalPoint))
                 } else {
-                    let path = PathBuilder::from_oval(rect).unwrap();
-                    if shape.is_filled {
-                        pixmap.fill_path(&path, &paint, FillRule::EvenOdd, transform, None);
+                    None
+                }
+            });
+        handle.or_else(|| {
+            let index = self.shapes.iter().rposition(|shape| {
+                shape.redaction.is_none() && shape.distance_to(point) <= tolerance
+            })?;
+            Some((index, ShapeDrag::Body(point)))
+        })
+    }
+
+    /// Selects the shape under the cursor and starts dragging the part of it that was grabbed
+    pub fn start_shape_drag(&mut self) {
+        self.commit_text();
+        if let Some((index, drag)) = self.grab_at(self.cursor_position) {
+            self.shape_moves.push(ShapeMove {
+                index,
+                shape: self.shapes[index].clone(),
+                count: self.shapes.len(),
+            });
+            self.redo_stack.clear();
+            self.redo_moves.clear();
+            self.selected_index = Some(index);
+            self.dragging = Some(drag);
+            self.cache.clear();
+        }
+    }
+
+    /// Ends the shape drag, forgetting its undo entry if the shape was only clicked
+    pub fn finish_shape_drag(&mut self) {
+        self.dragging = None;
+        let unmoved = self.shape_moves.last().is_some_and(|shape_move| {
+            self.shapes
+                .get(shape_move.index)
+                .is_some_and(|shape| shape.bounds() == shape_move.shape.bounds())
+        });
+        if unmoved {
+            self.shape_moves.pop();
+        }
+    }
+
+    /// Steps back the latest drag if it was made at the current shape count, else the latest
+    /// shape
+    pub fn undo_annotation(&mut self) {
+        let count = self.shapes.len();
+        if self
+            .shape_moves
+            .last()
+            .is_some_and(|shape_move| shape_move.count == count)
+        {
+            let Some(mut shape_move) = self.shape_moves.pop() else {
+                return;
+            };
+            std::mem::swap(&mut self.shapes[shape_move.index], &mut shape_move.shape);
+            self.redo_moves.push(shape_move);
+        } else if let Some(shape) = self.shapes.pop() {
+            self.redo_stack.push(shape);
+        } else {
+            return;
+        }
+        self.recount_steps();
+        self.selected_index = None;
+        self.cache.clear();
+    }
+
+    /// Reapplies what `undo_annotation` last stepped back
+    pub fn redo_annotation(&mut self) {
+        let count = self.shapes.len();
+        if self
+            .redo_moves
+            .last()
+            .is_some_and(|shape_move| shape_move.count == count)
+        {
+            let Some(mut shape_move) = self.redo_moves.pop() else {
+                return;
+            };
+            std::mem::swap(&mut self.shapes[shape_move.index], &mut shape_move.shape);
+            self.shape_moves.push(shape_move);
+        } else if let Some(shape) = self.redo_stack.pop() {
+            self.shapes.push(shape);
+        } else {
+            return;
+        }
+        self.recount_steps();
+        self.selected_index = None;
+        self.cache.clear();
+    }
+
+    /// Moves the dragged endpoint, or the whole selected shape, to the cursor
+    pub fn drag_selected_shape(&mut self) {
+        let (Some(index), Some(drag)) = (self.selected_index, self.dragging) else {
+            return;
+        };
+        let cursor = self.cursor_position;
+        let Some(shape) = self.shapes.get_mut(index) else {
+            return;
+        };
+        match drag {
+            ShapeDrag::InitialPoint | ShapeDrag::FinalPoint => {
+                if let Some(ref mut endpoints) = shape.endpoints {
+                    if matches!(drag, ShapeDrag::InitialPoint) {
+                        endpoints.initial_pt = cursor;
                     } else {
-                        pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+                        endpoints.final_pt = cursor;
                     }
                 }
             }
-            ShapeType::Line => {
-                let mut builder = PathBuilder::new();
-                builder.move_to(endpoints.initial_pt.x, endpoints.initial_pt.y);
-                builder.line_to(endpoints.final_pt.x, endpoints.final_pt.y);
-                let path = builder.finish().unwrap();
-                pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+            ShapeDrag::Body(from) => {
+                let offset = cursor - from;
+                shape.map_points(|point| point + offset);
+                self.dragging = Some(ShapeDrag::Body(cursor));
             }
-            ShapeType::Arrow => {
-                let (right_pt, left_pt) = resolve_arrow_points(endpoints.initial_pt, endpoints.final_pt);
-                let mut builder = PathBuilder::new();
-                builder.move_to(endpoints.initial_pt.x, endpoints.initial_pt.y);
-                builder.line_to(endpoints.final_pt.x, endpoints.final_pt.y);
-                builder.move_to(right_pt.x, right_pt.y);
-                builder.line_to(endpoints.final_pt.x, endpoints.final_pt.y);
-                builder.line_to(left_pt.x, left_pt.y);
-                let path = builder.finish().unwrap();
-                pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+        }
+        self.cache.clear();
+    }
+
+    /// What a measurement snaps to at `point`: the shape under it, the window under it while
+    /// Alt is held, or the point itself
+    pub fn measure_target(&self, point: Point) -> Rectangle {
+        if let Some(bounds) = self
+            .shape_at(point)
+            .and_then(|index| self.shapes[index].bounds())
+        {
+            return bounds;
+        }
+        if self.modifiers.alt() {
+            if let Some((_, _, endpoints)) = self.window_at(point) {
+                let (top_left, bottom_right) = endpoints.normalize();
+                return Rectangle::new(top_left, (bottom_right - top_left).into());
             }
         }
+        Rectangle::new(point, Size::ZERO)
     }
-    RgbaImage::from_vec(width, height, pixmap.take()).unwrap()
-}
-pub fn get_windows(monitor_id: u32) -> IndexMap<u32, CapturedWindow> {
-    let all_windows = xcap::Window::all().unwrap();
 
-    let valid_windows = all_windows
-        .into_iter()
-        .filter_map(|window| {
-            if window.current_monitor().id() == monitor_id
-                && !window.is_minimized()
-                && window.width() != 0
-                && window.height() != 0
-                && window.title() != ""
+    /// Stretches the measurement from the first target to the one under the cursor,
+    /// labelled with the gap in image pixels
+    fn update_measurement(&mut self) {
+        let Some(from) = self.measure_from else {
+            return;
+        };
+        let endpoints = nearest_edges(from, self.measure_target(self.cursor_position));
+        let gap = endpoints.initial_pt.distance(endpoints.final_pt) * self.scale_factor;
+        self.shape.endpoints = Some(endpoints);
+        self.shape.text = format!("{} px", gap.round());
+    }
+
+    /// Pixelates the capture under `endpoints` with blocks sized by `stroke`, `None` when the
+    /// region is empty
+    pub fn redact(&self, endpoints: Endpoints, stroke: ShapeStroke) -> Option<Redaction> {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let (top_left, bottom_right) = (
+            self.to_image_point(top_left),
+            self.to_image_point(bottom_right),
+        );
+        let (width, height) = self.image.dimensions();
+        let (left, top) = (
+            (top_left.x.max(0.0).round() as u32).min(width),
+            (top_left.y.max(0.0).round() as u32).min(height),
+        );
+        let (right, bottom) = (
+            (bottom_right.x.max(0.0).round() as u32).min(width),
+            (bottom_right.y.max(0.0).round() as u32).min(height),
+        );
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        let region = crop_imm(
+            &self.redacted_image(),
+            left,
+            top,
+            right - left,
+            bottom - top,
+        )
+        .to_image();
+        let block = (stroke.blur_block() * self.scale_factor).max(1.0);
+        let small = resize(
+            &region,
+            (region.width() as f32 / block).ceil() as u32,
+            (region.height() as f32 / block).ceil() as u32,
+            FilterType::Triangle,
+        );
+        Some(Redaction {
+            x: left as i64,
+            y: top as i64,
+            pixels: resize(&small, region.width(), region.height(), FilterType::Nearest),
+        })
+    }
+
+    /// Pastes every placed blur onto `image`, in drawing order
+    pub fn apply_redactions(&self, image: &mut RgbaImage) {
+        for redaction in self
+            .shapes
+            .iter()
+            .filter_map(|shape| shape.redaction.as_ref())
+        {
+            overlay(image, &redaction.pixels, redaction.x, redaction.y);
+        }
+    }
+
+    /// The capture as the overlay shows it, with every placed blur
+    pub fn redacted_image(&self) -> RgbaImage {
+        let mut image = self.image.clone();
+        self.apply_redactions(&mut image);
+        image
+    }
+
+    /// Adds a step marker numbered after the previous one under the cursor
+    pub fn place_step_marker(&mut self) {
+        self.commit_text();
+        self.step_counter += 1;
+        let radius = self.shape.stroke_width.font_size() * STEP_MARKER_RADIUS;
+        let offset = Vector::new(radius, radius);
+        self.push_shape(Shape {
+            endpoints: Some(Endpoints {
+                initial_pt: self.cursor_position - offset,
+                final_pt: self.cursor_position + offset,
+            }),
+            step: self.step_counter,
+            text: String::new(),
+            points: Vec::new(),
+            ..self.shape.clone()
+        });
+    }
+
+    /// Continues numbering after the highest marker left, so removing the latest ones keeps
+    /// the sequence contiguous
+    pub fn recount_steps(&mut self) {
+        self.step_counter = self
+            .shapes
+            .iter()
+            .filter(|shape| shape.shape_type == ShapeType::StepMarker)
+            .map(|shape| shape.step)
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// Picks the first target of a measurement, or the second one which adds it as a shape
+    pub fn place_measure_target(&mut self) {
+        if self.measure_from.is_none() {
+            self.measure_from = Some(self.measure_target(self.cursor_position));
+            self.update_measurement();
+            return;
+        }
+        self.update_measurement();
+        if let Some(endpoints) = self.shape.endpoints {
+            if endpoints.initial_pt != endpoints.final_pt {
+                self.push_shape(self.shape.clone());
+            }
+        }
+        self.measure_from = None;
+        self.shape.endpoints = None;
+        self.shape.text.clear();
+    }
+
+    /// Selects the element under the cursor found by `detect_element`, false when there is
+    /// none
+    pub fn suggest_element(&mut self) -> bool {
+        let seed = self.to_image_point(self.cursor_position);
+        let Some((x, y, width, height)) =
+            detect_element(&self.image, (seed.x as u32, seed.y as u32))
+        else {
+            return false;
+        };
+        self.endpoints = Endpoints {
+            initial_pt: self.to_overlay_point(Point::new(x as f32, y as f32)),
+            final_pt: self.to_overlay_point(Point::new((x + width) as f32, (y + height) as f32)),
+        };
+        self.crop_mode = CropMode::ManualSelection;
+        self.mode_desc = format!("Detected {} x {}", width, height);
+        true
+    }
+
+    /// Opens `image` centered on `monitor` to crop and annotate, `description` names where it
+    /// came from. Hovering the image selects all of it like a captured window.
+    pub fn from_image(
+        monitor: Monitor,
+        image: RgbaImage,
+        description: &str,
+        config: &Config,
+    ) -> Option<Self> {
+        let mut capture_window = CaptureWindow::new(monitor, config)?;
+        capture_window.windows.clear();
+        capture_window.monitor_id = None;
+        capture_window.blank_capture = false;
+
+        let (screen_width, screen_height) = capture_window.image.dimensions();
+        let (max_width, max_height) = (screen_width * 9 / 10, screen_height * 9 / 10);
+        let image = if image.width() > max_width || image.height() > max_height {
+            let ratio = (max_width as f32 / image.width() as f32)
+                .min(max_height as f32 / image.height() as f32);
+            resize(
+                &image,
+                (image.width() as f32 * ratio) as u32,
+                (image.height() as f32 * ratio) as u32,
+                FilterType::Lanczos3,
+            )
+        } else {
+            image
+        };
+
+        let top_left = capture_window.center_image(&image);
+        let Point { x, y } = capture_window.to_image_point(top_left);
+        capture_window.windows.insert(
+            0,
+            CapturedWindow {
+                name: description.to_string(),
+                title: String::new(),
+                x: x.round() as i32,
+                y: y.round() as i32,
+                width: image.width(),
+                height: image.height(),
+                image,
+            },
+        );
+        capture_window.mode_desc = description.to_string();
+        Some(capture_window)
+    }
+
+    /// Draws `image` centered over the current background and selects it, returning its
+    /// top left corner in overlay coordinates
+    fn center_image(&mut self, image: &RgbaImage) -> Point {
+        let x = self.image.width().saturating_sub(image.width()) / 2;
+        let y = self.image.height().saturating_sub(image.height()) / 2;
+        overlay(&mut self.image, image, x as i64, y as i64);
+        self.crop_history.clear();
+        self.crop_redo.clear();
+
+        self.endpoints = Endpoints {
+            initial_pt: self.to_overlay_point(Point::new(x as f32, y as f32)),
+            final_pt: self.to_overlay_point(Point::new(
+                (x + image.width()) as f32,
+                (y + image.height()) as f32,
+            )),
+        };
+        self.crop_mode = CropMode::ManualSelection;
+        self.endpoints.initial_pt
+    }
+
+    /// Makes the selection the whole canvas and keeps editing on it, moving shapes along and
+    /// dropping the ones that reach outside of it
+    pub fn crop_to_selection(&mut self) {
+        if matches!(self.crop_mode, CropMode::FullScreen) {
+            return;
+        }
+        let (x, y, width, height) = self.selection_rect();
+        let cropped = self.selection_base();
+        let old_origin = self.to_overlay_point(Point::new(x as f32, y as f32));
+        let selection = Rectangle::new(
+            old_origin,
+            Size::new(
+                width as f32 / self.scale_factor,
+                height as f32 / self.scale_factor,
+            ),
+        );
+        // Placed blurs are baked into the cropped image
+        let inside = |shape: &Shape| {
+            shape.redaction.is_none()
+                && shape.bounds().is_some_and(|bounds| {
+                    bounds.x >= selection.x
+                        && bounds.y >= selection.y
+                        && bounds.x + bounds.width <= selection.x + selection.width
+                        && bounds.y + bounds.height <= selection.y + selection.height
+                })
+        };
+        self.shapes.retain(inside);
+        self.redo_stack.retain(inside);
+        self.shape_moves.clear();
+        self.redo_moves.clear();
+
+        // Transparent around the canvas, as nothing there is part of the capture anymore
+        self.image = RgbaImage::new(self.image.width(), self.image.height());
+        self.windows.clear();
+        self.monitor_id = None;
+        self.canvas = None;
+        let offset = self.center_image(&cropped) - old_origin;
+        self.canvas = Some(self.selection_rect());
+        self.translate_shapes(offset);
+        for shape in self.redo_stack.iter_mut() {
+            shape.map_points(|point| point + offset);
+        }
+
+        self.selected_index = None;
+        self.mode_desc = format!("{} x {}", cropped.width(), cropped.height());
+    }
+
+    /// Moves every shape by `offset` in overlay coordinates
+    pub fn translate_shapes(&mut self, offset: Vector) {
+        // Blurs belong to the pixels under them
+        for shape in self
+            .shapes
+            .iter_mut()
+            .filter(|shape| shape.redaction.is_none())
+        {
+            shape.map_points(|point| point + offset);
+        }
+        for shape_move in self
+            .shape_moves
+            .iter_mut()
+            .chain(self.redo_moves.iter_mut())
+            .filter(|shape_move| shape_move.shape.redaction.is_none())
+        {
+            shape_move.shape.map_points(|point| point + offset);
+        }
+        self.cache.clear();
+    }
+
+    /// Seconds left before an idle overlay cancels itself, `None` while the timeout is
+    /// disabled or the user is working on a selection or annotations
+    pub fn timeout_remaining(&self) -> Option<u64> {
+        let timeout = self.config.overlay_timeout_secs;
+        if timeout == 0
+            || matches!(self.mode, Mode::Draw)
+            || !self.shapes.is_empty()
+            || matches!(self.crop_mode, CropMode::SelectionInProgress)
+            || self.countdown.is_some()
+        {
+            return None;
+        }
+        Some(timeout.saturating_sub(self.last_input.elapsed().as_secs()))
+    }
+
+    /// Converts a point from overlay (logical) coordinates into pixel coordinates of `self.image`
+    pub fn to_image_point(&self, point: Point) -> Point {
+        Point::new(point.x * self.scale_factor, point.y * self.scale_factor)
+    }
+
+    /// Opaque color of the image pixel under an overlay point, `None` outside the image
+    pub fn pixel_at(&self, point: Point) -> Option<RgbaColor> {
+        self.pixel_rgba_at(point).map(|color| color.with_alpha(255))
+    }
+
+    /// Color of the image pixel under an overlay point including its alpha, `None` outside
+    /// the image
+    pub fn pixel_rgba_at(&self, point: Point) -> Option<RgbaColor> {
+        let pixel = self.to_image_point(point);
+        if pixel.x < 0.0 || pixel.y < 0.0 {
+            return None;
+        }
+        let [r, g, b, a] = self.redacted_pixel(pixel.x as i64, pixel.y as i64)?.0;
+        Some(RgbaColor::new(r, g, b, a))
+    }
+
+    /// Pixel of the capture at `(x, y)` with every placed blur over it, so nothing redacted
+    /// can be read back off the overlay. `None` outside the image
+    pub fn redacted_pixel(&self, x: i64, y: i64) -> Option<Rgba<u8>> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let mut pixel = *self.image.get_pixel_checked(x as u32, y as u32)?;
+        for redaction in self
+            .shapes
+            .iter()
+            .filter_map(|shape| shape.redaction.as_ref())
+        {
+            let (patch_x, patch_y) = (x - redaction.x, y - redaction.y);
+            if patch_x < 0 || patch_y < 0 {
+                continue;
+            }
+            if let Some(patch) = redaction
+                .pixels
+                .get_pixel_checked(patch_x as u32, patch_y as u32)
+            {
+                pixel.blend(patch);
+            }
+        }
+        Some(pixel)
+    }
+
+    /// Converts a point from pixel coordinates of `self.image` into overlay (logical) coordinates
+    pub fn to_overlay_point(&self, point: Point) -> Point {
+        Point::new(point.x / self.scale_factor, point.y / self.scale_factor)
+    }
+
+    /// Resolves a profile into a manual selection, relative to either the image or the window
+    /// that was focused before the overlay opened
+    pub fn apply_profile(&mut self, index: usize) {
+        let Some(profile) = self.config.profiles.get(index) else {
+            return;
+        };
+        let reference = match (profile.relative_to_active_window, self.active_window) {
+            (true, Some(window)) => window,
+            (true, None) => {
+                println!("No active window found for profile {}", profile.name);
+                return;
+            }
+            (false, _) => Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: self.image.width() as f32,
+                height: self.image.height() as f32,
+            },
+        };
+        let (top_left, bottom_right) = profile.resolve(reference);
+        self.mode_desc = profile.name.clone();
+        self.remember_selection();
+        self.endpoints = Endpoints {
+            initial_pt: self.to_overlay_point(top_left),
+            final_pt: self.to_overlay_point(bottom_right),
+        };
+        self.crop_mode = CropMode::ManualSelection;
+    }
+
+    /// Switches to drawing with every setting of a configured preset
+    pub fn apply_preset(&mut self, index: usize) {
+        self.commit_text();
+        let Some(preset) = self.config.presets.get(index) else {
+            return;
+        };
+        let [r, g, b] = parse_hex_color(&preset.color, [255, 0, 0]);
+        self.shape.endpoints = None;
+        self.selected_index = None;
+        self.mode = Mode::Draw;
+        self.shape.shape_type = preset.shape_type;
+        self.shape.is_filled = preset.is_filled;
+        self.shape.is_solid = preset.is_solid;
+        self.shape.stroke_width = preset.stroke_width;
+        self.shape
+            .set_color(RgbaColor::new(r, g, b, 255), self.config.highlight_opacity);
+        self.mode_desc = preset.name.clone();
+    }
+
+    /// Keeps the current manual selection or hovered window so it can be restored by
+    /// `undo_crop`, a new selection follows so nothing is left to redo
+    pub fn remember_selection(&mut self) {
+        self.crop_redo.clear();
+        self.push_crop_history();
+    }
+
+    fn push_crop_history(&mut self) {
+        if matches!(
+            self.crop_mode,
+            CropMode::ManualSelection | CropMode::SpecificWindow(_)
+        ) {
+            self.crop_history.push((self.crop_mode, self.endpoints));
+        }
+    }
+
+    /// Selects the last finished manual selection again, clamped to the image in case the
+    /// resolution changed since
+    pub fn restore_selection(&mut self) {
+        let Some((x, y, width, height)) = self.config.last_selection else {
+            return;
+        };
+        let (image_width, image_height) = self.image.dimensions();
+        let (x, y) = (x.min(image_width), y.min(image_height));
+        let (width, height) = (width.min(image_width - x), height.min(image_height - y));
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.remember_selection();
+        self.endpoints = Endpoints {
+            initial_pt: self.to_overlay_point(Point::new(x as f32, y as f32)),
+            final_pt: self.to_overlay_point(Point::new((x + width) as f32, (y + height) as f32)),
+        };
+        self.crop_mode = CropMode::ManualSelection;
+        self.mode_desc = format!("{} x {}", width, height);
+    }
+
+    /// Edge or corner of the manual selection near `point`, or its body when `point` is inside
+    pub fn selection_handle_at(&self, point: Point) -> Option<SelectionHandle> {
+        const TOLERANCE: f32 = 8.0;
+        if !matches!(self.crop_mode, CropMode::ManualSelection) {
+            return None;
+        }
+        let (top_left, bottom_right) = self.endpoints.normalize();
+        let within = |value: f32, start: f32, end: f32| {
+            value >= start - TOLERANCE && value <= end + TOLERANCE
+        };
+        if !within(point.x, top_left.x, bottom_right.x)
+            || !within(point.y, top_left.y, bottom_right.y)
+        {
+            return None;
+        }
+        let left = (point.x - top_left.x).abs() <= TOLERANCE;
+        let right = !left && (point.x - bottom_right.x).abs() <= TOLERANCE;
+        let top = (point.y - top_left.y).abs() <= TOLERANCE;
+        let bottom = !top && (point.y - bottom_right.y).abs() <= TOLERANCE;
+        if left || right || top || bottom {
+            Some(SelectionHandle::Edge {
+                left,
+                right,
+                top,
+                bottom,
+            })
+        } else {
+            Some(SelectionHandle::Body(point))
+        }
+    }
+
+    /// Starts moving or resizing the manual selection from the handle under the cursor, the
+    /// previous selection can be restored with `undo_crop`
+    pub fn start_selection_adjust(&mut self) {
+        if let Some(handle) = self.selection_handle_at(self.cursor_position) {
+            self.remember_selection();
+            let (top_left, bottom_right) = self.endpoints.normalize();
+            self.endpoints = Endpoints {
+                initial_pt: top_left,
+                final_pt: bottom_right,
+            };
+            self.adjusting = Some(handle);
+        }
+    }
+
+    /// Moves the grabbed sides of the selection, or all of it kept on screen, to the cursor. A
+    /// locked aspect ratio is kept from the corner across from the grabbed handle
+    pub fn adjust_selection(&mut self) {
+        let Some(handle) = self.adjusting else {
+            return;
+        };
+        let cursor = self.snap_to_grid(self.cursor_position);
+        match handle {
+            SelectionHandle::Body(from) => {
+                let limit = self.to_overlay_point(Point::new(
+                    self.image.width() as f32,
+                    self.image.height() as f32,
+                ));
+                let size = self.endpoints.final_pt - self.endpoints.initial_pt;
+                let offset = self.cursor_position - from;
+                let initial_pt = self.snap_to_grid(Point::new(
+                    (self.endpoints.initial_pt.x + offset.x)
+                        .min(limit.x - size.x)
+                        .max(0.0),
+                    (self.endpoints.initial_pt.y + offset.y)
+                        .min(limit.y - size.y)
+                        .max(0.0),
+                ));
+                let moved = initial_pt - self.endpoints.initial_pt;
+                self.endpoints = Endpoints {
+                    initial_pt,
+                    final_pt: initial_pt + size,
+                };
+                self.adjusting = Some(SelectionHandle::Body(from + moved));
+            }
+            SelectionHandle::Edge {
+                left,
+                right,
+                top,
+                bottom,
+            } => {
+                let Endpoints {
+                    initial_pt,
+                    final_pt,
+                } = self.endpoints;
+                // The corner across from the handle stays put
+                let anchor = Point::new(
+                    if left { final_pt.x } else { initial_pt.x },
+                    if top { final_pt.y } else { initial_pt.y },
+                );
+                let mut corner = Point::new(
+                    if left { initial_pt.x } else { final_pt.x },
+                    if top { initial_pt.y } else { final_pt.y },
+                );
+                if left || right {
+                    corner.x = cursor.x;
+                }
+                if top || bottom {
+                    corner.y = cursor.y;
+                }
+                if let Some(ratio) = self.aspect_lock.ratio() {
+                    // A lone edge sets the size, the side next to it follows
+                    if !(top || bottom) {
+                        corner.y = anchor.y
+                            + ((corner.x - anchor.x).abs() / ratio).copysign(corner.y - anchor.y);
+                    } else if !(left || right) {
+                        corner.x = anchor.x
+                            + ((corner.y - anchor.y).abs() * ratio).copysign(corner.x - anchor.x);
+                    }
+                    corner = self.aspect_lock.constrain(anchor, corner);
+                }
+                if left {
+                    self.endpoints.initial_pt.x = corner.x;
+                } else {
+                    self.endpoints.final_pt.x = corner.x;
+                }
+                if top {
+                    self.endpoints.initial_pt.y = corner.y;
+                } else {
+                    self.endpoints.final_pt.y = corner.y;
+                }
+            }
+        }
+        let (_, _, width, height) = self.selection_rect();
+        self.mode_desc = format!("{} x {}", width, height);
+    }
+
+    /// Centers a selection of the configured fixed size on the cursor, kept inside the image
+    pub fn place_fixed_selection(&mut self) {
+        let (image_width, image_height) = self.image.dimensions();
+        let width = self.config.fixed_size.width.clamp(1, image_width);
+        let height = self.config.fixed_size.height.clamp(1, image_height);
+        let center = self.to_image_point(self.cursor_position);
+        let x = (center.x - width as f32 / 2.0)
+            .round()
+            .clamp(0.0, (image_width - width) as f32);
+        let y = (center.y - height as f32 / 2.0)
+            .round()
+            .clamp(0.0, (image_height - height) as f32);
+        self.endpoints = Endpoints {
+            initial_pt: self.to_overlay_point(Point::new(x, y)),
+            final_pt: self.to_overlay_point(Point::new(x + width as f32, y + height as f32)),
+        };
+        self.mode_desc = format!("{} x {}", width, height);
+    }
+
+    /// Steps back to the previous manual selection or window, or fullscreen once there is none
+    pub fn undo_crop(&mut self) {
+        let current = (self.crop_mode, self.endpoints);
+        while let Some((crop_mode, endpoints)) = self.crop_history.pop() {
+            // Windows that can no longer be selected are skipped
+            if let CropMode::SpecificWindow(id) = crop_mode {
+                if !self.windows.contains_key(&id) {
+                    continue;
+                }
+            }
+            self.crop_redo.push(current);
+            self.select_crop(crop_mode, endpoints);
+            return;
+        }
+        if !matches!(self.crop_mode, CropMode::FullScreen) {
+            self.crop_redo.push(current);
+        }
+        self.select_crop(CropMode::FullScreen, self.endpoints);
+    }
+
+    /// Selects again what the latest `undo_crop` stepped back from
+    pub fn redo_crop(&mut self) {
+        while let Some((crop_mode, endpoints)) = self.crop_redo.pop() {
+            if let CropMode::SpecificWindow(id) = crop_mode {
+                if !self.windows.contains_key(&id) {
+                    continue;
+                }
+            }
+            self.push_crop_history();
+            self.select_crop(crop_mode, endpoints);
+            return;
+        }
+    }
+
+    fn select_crop(&mut self, crop_mode: CropMode, endpoints: Endpoints) {
+        self.crop_mode = crop_mode;
+        self.endpoints = endpoints;
+        self.mode_desc = match crop_mode {
+            CropMode::FullScreen => String::from("FullScreen"),
+            CropMode::SpecificWindow(id) => self.windows[&id].name.clone(),
+            _ => {
+                let (_, _, width, height) = self.selection_rect();
+                format!("{} x {}", width, height)
+            }
+        };
+    }
+
+    /// Moves each coordinate of `point` onto the nearest edge of a captured window beside it,
+    /// if one is within the configured distance. Of equally near edges the window listed
+    /// first wins
+    pub fn snap_to_windows(&self, point: Point) -> Point {
+        if !self.config.window_snap {
+            return point;
+        }
+        let threshold = self.config.window_snap_distance * self.scale_factor;
+        let Point { x, y } = self.to_image_point(point);
+        let nearest = |value: f32, edges: Vec<f32>| {
+            edges
+                .into_iter()
+                .map(|edge| ((edge - value).abs(), edge))
+                .filter(|(distance, _)| *distance <= threshold)
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map_or(value, |(_, edge)| edge)
+        };
+        // Only edges running past the point count, within the same distance
+        let beside = |value: f32, start: i32, length: u32| {
+            value >= start as f32 - threshold && value <= (start + length as i32) as f32 + threshold
+        };
+        let vertical_edges = self
+            .windows
+            .values()
+            .filter(|window| beside(y, window.y, window.height))
+            .flat_map(|window| [window.x as f32, (window.x + window.width as i32) as f32])
+            .collect();
+        let horizontal_edges = self
+            .windows
+            .values()
+            .filter(|window| beside(x, window.x, window.width))
+            .flat_map(|window| [window.y as f32, (window.y + window.height as i32) as f32])
+            .collect();
+        self.to_overlay_point(Point::new(
+            nearest(x, vertical_edges),
+            nearest(y, horizontal_edges),
+        ))
+    }
+
+    /// Rounds a point to the configured crop grid, measured in image pixels
+    pub fn snap_to_grid(&self, point: Point) -> Point {
+        let step = self.config.crop_snap_step as f32;
+        if step == 0.0 {
+            return point;
+        }
+        let Point { x, y } = self.to_image_point(point);
+        self.to_overlay_point(Point::new(
+            (x / step).round() * step,
+            (y / step).round() * step,
+        ))
+    }
+
+    /// Finds the captured window under `point`, returning its bounds in overlay coordinates
+    pub fn window_at(&self, point: Point) -> Option<(u32, String, Endpoints)> {
+        let Point { x, y } = self.to_image_point(point);
+        self.windows.iter().find_map(|(id, window)| {
+            let top_left = Point::new(window.x as f32, window.y as f32);
+            let bottom_right = Point::new(
+                top_left.x + window.width as f32,
+                top_left.y + window.height as f32,
+            );
+            if (top_left.x..bottom_right.x).contains(&x)
+                && (top_left.y..bottom_right.y).contains(&y)
             {
                 Some((
-                    window.id(),
-                    CapturedWindow {
-                        name: window.app_name().to_string(),
-                        x: window.x(),
-                        y: window.y(),
-                        width: window.width(),
-                        height: window.height(),
-                        image: window.capture_image().unwrap(),
+                    *id,
+                    window.name.clone(),
+                    Endpoints {
+                        initial_pt: self.to_overlay_point(top_left),
+                        final_pt: self.to_overlay_point(bottom_right),
                     },
                 ))
             } else {
                 None
             }
         })
-        .collect();
+    }
 
-    valid_windows
-}
+    pub fn final_image(&self) -> RgbaImage {
+        self.final_image_with(&self.shapes)
+    }
 
-fn save_image(image: RgbaImage, directory: String) {
-    let date = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S");
+    /// Selected part of the capture, without annotations
+    pub fn selection_base(&self) -> RgbaImage {
+        let (x, y, width, height) = self.selection_rect();
+        let base = match self.crop_mode {
+            CropMode::SpecificWindow(id) => {
+                let window = self.windows.get(&id).unwrap();
+                let mut base = RgbaImage::new(self.image.width(), self.image.height());
+                overlay(&mut base, &window.image, window.x as i64, window.y as i64);
+                self.apply_redactions(&mut base);
+                base
+            }
+            _ => self.redacted_image(),
+        };
+        crop_image(&base, (x, y, width, height))
+    }
 
-    #[cfg(target_os = "windows")]
-    let image_path = format!("{}\\Capture_{}.png", directory, date);
+    /// Crops the capture with only `shapes` baked in, upscaled by the configured export scale
+    pub fn final_image_with(&self, shapes: &[Shape]) -> RgbaImage {
+        self.export_canvas().render(shapes)
+    }
 
-    #[cfg(not(target_os = "windows"))]
-    let image_path = format!("{}/Capture_{}.png", directory, date);
+    /// The selection ready for the shapes of an export to be drawn over it
+    pub fn export_canvas(&self) -> ExportCanvas {
+        let (x, y, _, _) = self.selection_rect();
+        self.export_canvas_from(self.selection_base(), (x, y))
+    }
 
-    Clipboard::new()
-        .unwrap()
-        .set_image(ImageData {
-            width: image.width() as usize,
-            height: image.height() as usize,
-            bytes: std::borrow::Cow::Borrowed(image.as_raw()),
-        })
-        .unwrap();
+    /// `base` cut out of the capture at `(x, y)` ready for the shapes of an export
+    fn export_canvas_from(&self, base: RgbaImage, (x, y): (u32, u32)) -> ExportCanvas {
+        // Only the background is resampled, shapes are rasterized at the target size
+        let export_scale = self.config.resample_scale();
+        let base = if export_scale != 1.0 {
+            resize(
+                &base,
+                (base.width() as f32 * export_scale).round() as u32,
+                (base.height() as f32 * export_scale).round() as u32,
+                self.config.export_filter.filter_type(),
+            )
+        } else {
+            base
+        };
+        let scale = self.scale_factor * export_scale;
+        let transform = Transform::from_row(
+            scale,
+            0.0,
+            0.0,
+            scale,
+            -(x as f32) * export_scale,
+            -(y as f32) * export_scale,
+        );
+        ExportCanvas {
+            base,
+            transform,
+            watermark: self.watermark.clone(),
+            config: self.config.clone(),
+        }
+    }
 
-    image
-        .save_with_format(image_path, ImageFormat::Png)
-        .unwrap();
+    /// Part of the image left to export, `(x, y, width, height)` in its pixels
+    pub fn canvas_rect(&self) -> (u32, u32, u32, u32) {
+        self.canvas
+            .unwrap_or((0, 0, self.image.width(), self.image.height()))
+    }
+
+    /// Current selection as `(x, y, width, height)` in image pixels
+    pub fn selection_rect(&self) -> (u32, u32, u32, u32) {
+        let canvas = self.canvas_rect();
+        let (left, top, right, bottom) = match self.crop_mode {
+            CropMode::FullScreen => return canvas,
+            CropMode::SpecificWindow(id) => {
+                let window = self.windows.get(&id).unwrap();
+                (
+                    window.x as i64,
+                    window.y as i64,
+                    window.x as i64 + window.width as i64,
+                    window.y as i64 + window.height as i64,
+                )
+            }
+            CropMode::ManualSelection
+            | CropMode::SelectionInProgress
+            | CropMode::FixedPlacement => {
+                let (top_left, bottom_right) = self.endpoints.normalize();
+                // Both corners snap to the pixel grid so fractional scale factors can't shift
+                // the crop by a pixel
+                let (top_left, bottom_right) = (
+                    self.to_image_point(top_left),
+                    self.to_image_point(bottom_right),
+                );
+                (
+                    top_left.x.round() as i64,
+                    top_left.y.round() as i64,
+                    bottom_right.x.round() as i64,
+                    bottom_right.y.round() as i64,
+                )
+            }
+        };
+        // Only the part on the canvas, like a window overhanging the monitor's edges
+        let (x, y, width, height) = canvas;
+        let clip_x = |value: i64| value.clamp(x as i64, (x + width) as i64) as u32;
+        let clip_y = |value: i64| value.clamp(y as i64, (y + height) as i64) as u32;
+        let (left, top) = (clip_x(left), clip_y(top));
+        (left, top, clip_x(right) - left, clip_y(bottom) - top)
+    }
+
+    /// The whole canvas with everything outside the selection blurred
+    pub fn focus_image(&self) -> RgbaImage {
+        let (canvas_x, canvas_y, canvas_width, canvas_height) = self.canvas_rect();
+        let canvas = self.export_canvas_from(
+            crop_image(
+                &self.redacted_image(),
+                (canvas_x, canvas_y, canvas_width, canvas_height),
+            ),
+            (canvas_x, canvas_y),
+        );
+        let drawn = canvas.draw(&self.shapes);
+
+        // The selection in pixels of the resampled canvas
+        let export_scale = self.config.resample_scale();
+        let scaled = |value: u32| (value as f32 * export_scale).round() as u32;
+        let (x, y, width, height) = self.selection_rect();
+        let (x, y) = (scaled(x - canvas_x), scaled(y - canvas_y));
+        let sharp = crop_image(&drawn, (x, y, scaled(width), scaled(height)));
+        let mut focused = blur(&drawn, self.config.focus_blur_radius * export_scale);
+        overlay(&mut focused, &sharp, x as i64, y as i64);
+        canvas.finish(focused)
+    }
+
+    /// Saves the focus export as a new version while keeping the window open
+    pub fn save_focus_version(&mut self) {
+        self.version += 1;
+        let image = self.focus_image();
+        let (width, height) = image.dimensions();
+        let path = save_capture(
+            image,
+            &self.config.directory,
+            Some(self.version),
+            &self.config,
+        );
+        self.saved_path = path.or(self.saved_path.take());
+        self.mode_desc = format!(
+            "{} v{}: {} x {}",
+            self.saved_label(),
+            self.version,
+            width,
+            height
+        );
+    }
+
+    /// Capture with the shapes that can't be written as vectors baked in
+    fn vector_background(&self) -> RgbaImage {
+        let raster: Vec<Shape> = self
+            .shapes
+            .iter()
+            .filter(|shape| !is_vector(shape))
+            .cloned()
+            .collect();
+        self.final_image_with(&raster)
+    }
+
+    /// Saves the annotations as vectors over the capture in the configured format
+    pub fn save_vector(&mut self) {
+        let background = self.vector_background();
+        let (document, extension) = match self.config.vector_format {
+            VectorFormat::Svg => (
+                export_svg(
+                    &background,
+                    &self.shapes,
+                    self.selection_rect(),
+                    self.scale_factor,
+                )
+                .into_bytes(),
+                "svg",
+            ),
+            VectorFormat::Pdf => (
+                export_pdf(
+                    &background,
+                    &self.shapes,
+                    self.selection_rect(),
+                    self.scale_factor,
+                    &self.config.pdf_title,
+                ),
+                "pdf",
+            ),
+        };
+
+        let directory = &self.config.directory;
+        let stem = capture_file_stem(directory, &self.config.filename_template, "", extension);
+        let path = file_path(directory, &format!("{}.{}", stem, extension));
+        match std::fs::write(&path, document) {
+            Ok(()) => self.mode_desc = format!("Exported {}", path),
+            Err(error) => println!("Could not write {}: {}", path, error),
+        }
+    }
+
+    /// Starts over on the same frozen capture, later saves keep counting versions
+    pub fn new_pass(&mut self) {
+        self.shapes.clear();
+        self.step_counter = 0;
+        self.redo_stack.clear();
+        self.shape_moves.clear();
+        self.redo_moves.clear();
+        self.shape.endpoints = None;
+        self.selected_index = None;
+        self.cache.clear();
+        self.mode = Mode::Crop;
+        self.crop_mode = CropMode::FullScreen;
+        self.endpoints = Endpoints::default();
+        self.crop_history.clear();
+        self.crop_redo.clear();
+        self.mode_desc = String::from("FullScreen");
+    }
+
+    /// Saves the current state as a new version while keeping the window open
+    pub fn save_version(&mut self, directory: String) {
+        self.version += 1;
+        let image = self.final_image();
+        let (width, height) = image.dimensions();
+        let path = save_capture(image, &directory, Some(self.version), &self.config);
+        self.saved_path = path.or(self.saved_path.take());
+        self.mode_desc = format!(
+            "{} v{}: {} x {}",
+            self.saved_label(),
+            self.version,
+            width,
+            height
+        );
+    }
+
+    /// Saves the final capture, returning the written file or else the latest saved version
+    pub fn take_screenshot(self, directory: String) -> Option<String> {
+        let version = (self.version > 0).then_some(self.version + 1);
+        let path = save_capture(self.final_image(), &directory, version, &self.config);
+        path.or(self.saved_path)
+    }
+
+    /// Region of the monitor that gets saved, `None` if the image isn't a screen capture
+    pub fn capture_region(&self) -> Option<CaptureRegion> {
+        let (x, y, width, height) = self.selection_rect();
+        self.monitor_id.map(|monitor| CaptureRegion {
+            monitor,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    fn saved_label(&self) -> &'static str {
+        if self.config.on_done == DoneAction::CopyClipboard {
+            "Copied"
+        } else {
+            "Saved"
+        }
+    }
+}
+
+impl ExportCanvas {
+    /// The export with `shapes` drawn over the background, then watermarked and size limited
+    pub fn render(&self, shapes: &[Shape]) -> RgbaImage {
+        self.finish(self.draw(shapes))
+    }
+
+    /// The background with `shapes` drawn over it
+    pub fn draw(&self, shapes: &[Shape]) -> RgbaImage {
+        let mut image = self.base.clone();
+        let top = draw_shapes(
+            image.width(),
+            image.height(),
+            shapes,
+            self.transform,
+            self.config.merge_highlights,
+        );
+        overlay(&mut image, &top, 0, 0);
+        image
+    }
+
+    /// Watermarks and size limits `image` drawn from this canvas
+    pub fn finish(&self, mut image: RgbaImage) -> RgbaImage {
+        stamp_export(&mut image, self.watermark.as_ref(), &self.config);
+        limit_dimensions(image, self.config.size_limit())
+    }
+}
+
+/// Saves one frame per annotation, each adding the next shape in drawing order, returning the
+/// written file, the first frame when they are separate files. Slow on large captures, so it
+/// is meant to run off the UI thread
+pub fn save_reveal(canvas: &ExportCanvas, shapes: &[Shape]) -> Result<String, String> {
+    let directory = &canvas.config.directory;
+    let template = &canvas.config.filename_template;
+    ensure_directory(directory)?;
+    let frames = (0..=shapes.len()).map(|count| canvas.render(&shapes[..count]));
+
+    match canvas.config.reveal_format {
+        RevealFormat::Frames => {
+            let stem = capture_file_stem(directory, template, "-step0", "png");
+            for (index, frame) in frames.enumerate() {
+                let path = file_path(directory, &format!("{}-step{}.png", stem, index));
+                frame
+                    .save_with_format(&path, ImageFormat::Png)
+                    .map_err(|error| format!("Could not write {}: {}", path, error))?;
+            }
+            Ok(file_path(directory, &format!("{}-step0.png", stem)))
+        }
+        RevealFormat::Gif => {
+            let stem = capture_file_stem(directory, template, "-reveal", "gif");
+            let path = file_path(directory, &format!("{}-reveal.gif", stem));
+            let write_error = |error: ImageError| format!("Could not write {}: {}", path, error);
+            let file = File::create(&path)
+                .map_err(|error| format!("Could not create {}: {}", path, error))?;
+            let mut encoder = GifEncoder::new(file);
+            encoder.set_repeat(Repeat::Infinite).map_err(write_error)?;
+            encoder
+                .encode_frames(frames.map(|frame| {
+                    Frame::from_parts(frame, 0, 0, Delay::from_numer_denom_ms(1000, 1))
+                }))
+                .map_err(write_error)?;
+            Ok(path)
+        }
+    }
+}
+
+/// Screen grab of `monitor`, `None` when it fails or comes back empty as it does
+/// without an accessible display
+fn capture_monitor(monitor: &Monitor) -> Option<RgbaImage> {
+    match monitor.capture_image() {
+        Ok(image) if image.width() > 0 && image.height() > 0 => Some(image),
+        Ok(_) => {
+            println!("Monitor {} returned an empty image", monitor.id());
+            None
+        }
+        Err(error) => {
+            println!("Could not capture monitor {}: {}", monitor.id(), error);
+            None
+        }
+    }
+}
+
+/// Captures `region` straight from its monitor and saves it without opening the overlay,
+/// returning the written file
+pub fn silent_capture(region: CaptureRegion, config: &Config) -> Option<String> {
+    match capture_region(region, config) {
+        Ok(image) => save_capture(image, &config.directory, None, config),
+        Err(error) => {
+            println!("{}", error);
+            None
+        }
+    }
+}
+
+/// Part of `image` at `(x, y, width, height)` in its pixels, cut down to what lies inside it
+pub fn crop_image(image: &RgbaImage, (x, y, width, height): (u32, u32, u32, u32)) -> RgbaImage {
+    crop_imm(image, x, y, width, height).to_image()
+}
+
+/// Saves the whole primary monitor like a finished capture, without any overlay
+pub fn quick_capture(config: &Config) -> Option<String> {
+    let Some(monitor) = Monitor::all()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|monitor| monitor.is_primary())
+    else {
+        println!("No monitor found");
+        return None;
+    };
+    // Cropping clamps the size to the monitor
+    let region = CaptureRegion {
+        monitor: monitor.id(),
+        x: 0,
+        y: 0,
+        width: u32::MAX,
+        height: u32::MAX,
+    };
+    silent_capture(region, config)
+}
+
+/// Grabs `region` from its monitor with the export settings applied, without any overlay.
+/// Fails on a blank looking capture so nothing black is saved unnoticed
+pub fn capture_region(region: CaptureRegion, config: &Config) -> Result<RgbaImage, String> {
+    let monitor = Monitor::all()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|monitor| monitor.id() == region.monitor)
+        .ok_or_else(|| format!("Monitor {} is not connected", region.monitor))?;
+
+    let mut image = capture_monitor(&monitor)
+        .ok_or_else(|| format!("Could not capture monitor {}", region.monitor))?;
+    if is_blank(&image, config.blank_threshold) {
+        return Err(String::from(BLANK_CAPTURE));
+    }
+    if !config.exclude_windows.is_empty() {
+        let mut windows = get_windows(&monitor);
+        mask_excluded_windows(&mut image, &mut windows, &config.exclude_windows);
+    }
+    match config.flip_on_open {
+        ImageFlip::Off => (),
+        ImageFlip::Horizontal => flip_horizontal_in_place(&mut image),
+        ImageFlip::Vertical => flip_vertical_in_place(&mut image),
+    }
+    let mut image = crop_image(&image, (region.x, region.y, region.width, region.height));
+    if image.width() == 0 || image.height() == 0 {
+        return Err(String::from("Region is outside the monitor"));
+    }
+
+    let export_scale = config.resample_scale();
+    if export_scale != 1.0 {
+        image = resize(
+            &image,
+            (image.width() as f32 * export_scale).round() as u32,
+            (image.height() as f32 * export_scale).round() as u32,
+            config.export_filter.filter_type(),
+        );
+    }
+    stamp_export(
+        &mut image,
+        load_watermark(&config.watermark).as_ref(),
+        config,
+    );
+    Ok(limit_dimensions(image, config.size_limit()))
+}
+
+/// Adds the watermark and timestamp to an export, `exact_pixels` exports get neither
+fn stamp_export(image: &mut RgbaImage, logo: Option<&RgbaImage>, config: &Config) {
+    if config.exact_pixels {
+        return;
+    }
+    if let Some(logo) = logo {
+        apply_watermark(image, logo, &config.watermark);
+    }
+    if config.timestamp.enabled {
+        draw_timestamp(image, &config.timestamp);
+    }
+}
+
+/// Rasterizes `shapes` onto a transparent `width` x `height` layer, `transform` maps overlay
+/// coordinates to layer pixels
+pub fn draw_shapes(
+    width: u32,
+    height: u32,
+    shapes: &[Shape],
+    transform: Transform,
+    merge_highlights: bool,
+) -> RgbaImage {
+    let Some(mut pixmap) = Pixmap::new(width, height) else {
+        return RgbaImage::new(width, height);
+    };
+    let mut merged_colors: Vec<RgbaColor> = Vec::new();
+    for shape in shapes.iter() {
+        if merge_highlights && shape.is_highlight() {
+            // All highlights of a color are filled once as a union, at the first one's layer
+            if merged_colors.contains(&shape.fill()) {
+                continue;
+            }
+            merged_colors.push(shape.fill());
+            let mut builder = PathBuilder::new();
+            for highlight in shapes
+                .iter()
+                .filter(|other| other.is_highlight() && other.fill() == shape.fill())
+            {
+                let endpoints = highlight.endpoints.unwrap();
+                let (top_left, bottom_right) = endpoints.normalize();
+                if let Some(rect) =
+                    Rect::from_ltrb(top_left.x, top_left.y, bottom_right.x, bottom_right.y)
+                {
+                    push_rounded_rect(
+                        &mut builder,
+                        rect,
+                        highlight.fitted_corner_radius(endpoints),
+                    );
+                }
+            }
+            if let Some(path) = builder.finish() {
+                let mut paint = Paint::default();
+                paint.set_color(shape.fill().into_paint());
+                pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+            }
+            continue;
+        }
+        if let Some(endpoints) = shape.endpoints {
+            shape
+                .shape_type
+                .drawable()
+                .rasterize(shape, endpoints, &mut pixmap, transform);
+        }
+    }
+    RgbaImage::from_vec(width, height, pixmap.take()).unwrap()
+}
+/// Visible windows on `monitor`, positioned relative to its top left corner so they line up
+/// with its capture. Window positions are virtual desktop coordinates, which are negative on
+/// monitors left of or above the primary one
+pub fn get_windows(monitor: &Monitor) -> IndexMap<u32, CapturedWindow> {
+    let monitor_id = monitor.id();
+    relative_windows(
+        visible_windows(|window| window.current_monitor().id() == monitor_id),
+        (monitor.x(), monitor.y()),
+    )
+}
+
+/// Visible windows on every monitor, relative to the virtual desktop's top left corner
+/// `origin` so they line up with `capture_desktop`
+pub fn get_desktop_windows(origin: (i32, i32)) -> IndexMap<u32, CapturedWindow> {
+    relative_windows(visible_windows(|_| true), origin)
+}
+
+/// Windows that are shown and pass `filter`, at their virtual desktop coordinates
+fn visible_windows(filter: impl Fn(&xcap::Window) -> bool) -> IndexMap<u32, CapturedWindow> {
+    xcap::Window::all()
+        .unwrap()
+        .into_iter()
+        .filter(|window| {
+            !window.is_minimized()
+                && window.width() != 0
+                && window.height() != 0
+                && window.title() != ""
+                && filter(window)
+        })
+        .map(|window| {
+            (
+                window.id(),
+                CapturedWindow {
+                    name: window.app_name().to_string(),
+                    title: window.title().to_string(),
+                    x: window.x(),
+                    y: window.y(),
+                    width: window.width(),
+                    height: window.height(),
+                    image: window.capture_image().unwrap(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Moves `windows` from virtual desktop coordinates to ones relative to `origin`
+pub fn relative_windows(
+    mut windows: IndexMap<u32, CapturedWindow>,
+    (origin_x, origin_y): (i32, i32),
+) -> IndexMap<u32, CapturedWindow> {
+    for window in windows.values_mut() {
+        window.x -= origin_x;
+        window.y -= origin_y;
+    }
+    windows
+}
+
+/// Captures `monitors` into one image of the virtual desktop, returned with the desktop's
+/// top left corner. `None` if none of them can be read
+pub fn capture_desktop(monitors: &[Monitor]) -> Option<(RgbaImage, (i32, i32))> {
+    let captures: Vec<(i32, i32, RgbaImage)> = monitors
+        .iter()
+        .filter_map(|monitor| Some((monitor.x(), monitor.y(), capture_monitor(monitor)?)))
+        .collect();
+    compose_desktop(&captures)
+}
+
+/// Places each `(x, y, image)` monitor capture at its virtual desktop position in one image,
+/// returned with the top left corner it starts at. Gaps between monitors stay transparent
+pub fn compose_desktop(captures: &[(i32, i32, RgbaImage)]) -> Option<(RgbaImage, (i32, i32))> {
+    let left = captures.iter().map(|(x, _, _)| *x as i64).min()?;
+    let top = captures.iter().map(|(_, y, _)| *y as i64).min()?;
+    let right = captures
+        .iter()
+        .map(|(x, _, image)| *x as i64 + image.width() as i64)
+        .max()?;
+    let bottom = captures
+        .iter()
+        .map(|(_, y, image)| *y as i64 + image.height() as i64)
+        .max()?;
+    let mut desktop = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+    for (x, y, image) in captures {
+        overlay(&mut desktop, image, *x as i64 - left, *y as i64 - top);
+    }
+    Some((desktop, (left as i32, top as i32)))
+}
+
+/// Whether at least `threshold` of the image is a single color, as protected content and
+/// locked screens come back black. Only every few pixels are sampled
+fn is_blank(image: &RgbaImage, threshold: f32) -> bool {
+    const STRIDE: usize = 7;
+    const TOLERANCE: u8 = 8;
+
+    if threshold <= 0.0 {
+        return false;
+    }
+    let mut pixels = image.pixels().step_by(STRIDE);
+    let Some(first) = pixels.next() else {
+        return false;
+    };
+    let (mut total, mut matching) = (1usize, 1usize);
+    for pixel in pixels {
+        total += 1;
+        if (0..3).all(|channel| pixel.0[channel].abs_diff(first.0[channel]) <= TOLERANCE) {
+            matching += 1;
+        }
+    }
+    matching as f32 / total as f32 >= threshold
+}
+
+/// Bounds `(x, y, width, height)` of the area of similar color around `seed`, found by
+/// flood filling. `None` when the fill runs out of the box it is limited to, as it does on
+/// a backdrop, or when the area is too small to be an element
+fn detect_element(image: &RgbaImage, seed: (u32, u32)) -> Option<(u32, u32, u32, u32)> {
+    const TOLERANCE: u8 = 24;
+    const MAX_EXTENT: u32 = 600;
+    const MIN_SIZE: u32 = 4;
+
+    let (seed_x, seed_y) = seed;
+    let (width, height) = image.dimensions();
+    if seed_x >= width || seed_y >= height {
+        return None;
+    }
+    let left = seed_x.saturating_sub(MAX_EXTENT);
+    let top = seed_y.saturating_sub(MAX_EXTENT);
+    let right = (seed_x + MAX_EXTENT).min(width - 1);
+    let bottom = (seed_y + MAX_EXTENT).min(height - 1);
+    let box_width = (right - left + 1) as usize;
+    let mut visited = vec![false; box_width * (bottom - top + 1) as usize];
+    let index = |x: u32, y: u32| (y - top) as usize * box_width + (x - left) as usize;
+
+    let color = image.get_pixel(seed_x, seed_y).0;
+    let similar = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x, y).0;
+        (0..3).all(|channel| pixel[channel].abs_diff(color[channel]) <= TOLERANCE)
+    };
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (seed_x, seed_y, seed_x, seed_y);
+    let mut stack = vec![(seed_x, seed_y)];
+    visited[index(seed_x, seed_y)] = true;
+    while let Some((x, y)) = stack.pop() {
+        // Touching the limit means the area isn't enclosed, unless the screen edge is there
+        if (x == left && left > 0)
+            || (y == top && top > 0)
+            || (x == right && right < width - 1)
+            || (y == bottom && bottom < height - 1)
+        {
+            return None;
+        }
+        (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+        let neighbours = [
+            (x > left).then(|| (x - 1, y)),
+            (x < right).then(|| (x + 1, y)),
+            (y > top).then(|| (x, y - 1)),
+            (y < bottom).then(|| (x, y + 1)),
+        ];
+        for (nx, ny) in neighbours.into_iter().flatten() {
+            if !visited[index(nx, ny)] && similar(nx, ny) {
+                visited[index(nx, ny)] = true;
+                stack.push((nx, ny));
+            }
+        }
+    }
+
+    let (element_width, element_height) = (max_x - min_x + 1, max_y - min_y + 1);
+    let covers_screen = element_width == width && element_height == height;
+    (element_width >= MIN_SIZE && element_height >= MIN_SIZE && !covers_screen).then_some((
+        min_x,
+        min_y,
+        element_width,
+        element_height,
+    ))
+}
+
+/// Blanks the visible part of every window whose title matches one of `patterns` and drops
+/// it from `windows` so it can't be picked as a crop. `windows` is ordered top-most first,
+/// parts covered by a window above are left as they are.
+fn mask_excluded_windows(
+    image: &mut RgbaImage,
+    windows: &mut IndexMap<u32, CapturedWindow>,
+    patterns: &[String],
+) {
+    const MASK: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+    let patterns: Vec<String> = patterns
+        .iter()
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| pattern.to_lowercase())
+        .collect();
+    if patterns.is_empty() {
+        return;
+    }
+    let is_excluded = |window: &CapturedWindow| {
+        let title = window.title.to_lowercase();
+        patterns.iter().any(|pattern| title.contains(pattern))
+    };
+    let contains = |window: &CapturedWindow, x: i64, y: i64| {
+        (window.x as i64..window.x as i64 + window.width as i64).contains(&x)
+            && (window.y as i64..window.y as i64 + window.height as i64).contains(&y)
+    };
+
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let mut excluded = Vec::new();
+    for (index, (id, window)) in windows.iter().enumerate() {
+        if !is_excluded(window) {
+            continue;
+        }
+        excluded.push(*id);
+        let above: Vec<&CapturedWindow> = windows.values().take(index).collect();
+        let x_range = (window.x as i64).max(0)..(window.x as i64 + window.width as i64).min(width);
+        let y_range =
+            (window.y as i64).max(0)..(window.y as i64 + window.height as i64).min(height);
+        for y in y_range {
+            for x in x_range.clone() {
+                if !above.iter().any(|other| contains(other, x, y)) {
+                    image.put_pixel(x as u32, y as u32, MASK);
+                }
+            }
+        }
+    }
+    for id in excluded {
+        windows.shift_remove(&id);
+    }
+}
+
+fn load_watermark(watermark: &Watermark) -> Option<RgbaImage> {
+    if watermark.path.is_empty() {
+        return None;
+    }
+    match xcap::image::open(&watermark.path) {
+        Ok(image) => Some(image.into_rgba8()),
+        Err(error) => {
+            println!("Could not load watermark {}: {}", watermark.path, error);
+            None
+        }
+    }
+}
+
+/// Composites the logo into the configured corner, shrinking it to fit inside the margins
+fn apply_watermark(image: &mut RgbaImage, logo: &RgbaImage, watermark: &Watermark) {
+    let margin = watermark.margin;
+    let (width, height) = image.dimensions();
+    let (max_width, max_height) = (
+        width.saturating_sub(2 * margin),
+        height.saturating_sub(2 * margin),
+    );
+    if max_width == 0 || max_height == 0 {
+        return;
+    }
+
+    let mut logo = if logo.width() > max_width || logo.height() > max_height {
+        let ratio =
+            (max_width as f32 / logo.width() as f32).min(max_height as f32 / logo.height() as f32);
+        resize(
+            logo,
+            ((logo.width() as f32 * ratio) as u32).max(1),
+            ((logo.height() as f32 * ratio) as u32).max(1),
+            FilterType::Lanczos3,
+        )
+    } else {
+        logo.clone()
+    };
+
+    let opacity = watermark.opacity.clamp(0.0, 1.0);
+    if opacity < 1.0 {
+        for pixel in logo.pixels_mut() {
+            pixel.0[3] = (pixel.0[3] as f32 * opacity).round() as u8;
+        }
+    }
+
+    let x = match watermark.corner {
+        Corner::TopLeft | Corner::BottomLeft => margin,
+        Corner::TopRight | Corner::BottomRight => width - margin - logo.width(),
+    };
+    let y = match watermark.corner {
+        Corner::TopLeft | Corner::TopRight => margin,
+        Corner::BottomLeft | Corner::BottomRight => height - margin - logo.height(),
+    };
+    overlay(image, &logo, x as i64, y as i64);
+}
+
+/// Downscales `image` so its largest side is at most `max_dimension`
+fn limit_dimensions(image: RgbaImage, max_dimension: Option<u32>) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    match max_dimension {
+        Some(max) if max > 0 && width.max(height) > max => {
+            let ratio = max as f32 / width.max(height) as f32;
+            resize(
+                &image,
+                ((width as f32 * ratio).round() as u32).max(1),
+                ((height as f32 * ratio).round() as u32).max(1),
+                FilterType::Lanczos3,
+            )
+        }
+        _ => image,
+    }
+}
+
+/// Expands `template` into a file name, without extension, for which `directory` has no file
+/// with `suffix` and `extension` appended yet. `%n` counts up until the name is free, else `-1`,
+/// `-2` and so on are appended
+pub fn capture_file_stem(directory: &str, template: &str, suffix: &str, extension: &str) -> String {
+    let template = if is_valid_filename_template(template) {
+        template
+    } else {
+        println!("Invalid filename template {}, using the default", template);
+        DEFAULT_FILENAME_TEMPLATE
+    };
+    let now = chrono::Local::now();
+    let counted = template.contains("%n");
+    (0u32..)
+        .map(|attempt| {
+            let stem = expand_filename_template(template, &now, attempt + 1);
+            if counted || attempt == 0 {
+                stem
+            } else {
+                format!("{}-{}", stem, attempt)
+            }
+        })
+        .find(|stem| {
+            !Path::new(&file_path(
+                directory,
+                &format!("{}{}.{}", stem, suffix, extension),
+            ))
+            .exists()
+        })
+        .unwrap()
+}
+
+/// Writes `image` to `path` in `format`
+fn encode_capture(image: &RgbaImage, path: &str, format: OutputFormat) -> Result<(), String> {
+    match format.validated() {
+        OutputFormat::Png => image.save_with_format(path, ImageFormat::Png),
+        OutputFormat::WebP => image.save_with_format(path, ImageFormat::WebP),
+        OutputFormat::Jpeg { quality } => {
+            // JPEG has no alpha channel, transparent pixels are blended onto white
+            let flattened = RgbImage::from_fn(image.width(), image.height(), |x, y| {
+                let [r, g, b, a] = image.get_pixel(x, y).0;
+                let blend = |channel: u8| {
+                    ((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8
+                };
+                Rgb([blend(r), blend(g), blend(b)])
+            });
+            File::create(path)
+                .map_err(ImageError::IoError)
+                .and_then(|file| {
+                    JpegEncoder::new_with_quality(file, quality).encode_image(&flattened)
+                })
+        }
+    }
+    .map_err(|error| format!("Could not write {}: {}", path, error))
+}
+
+/// Writes the image in `format`, named by `template`, into `directory`, returning its path
+pub fn write_capture(
+    image: &RgbaImage,
+    directory: &str,
+    version: Option<u32>,
+    template: &str,
+    format: OutputFormat,
+) -> Result<String, String> {
+    let suffix = version.map_or(String::new(), |version| format!("-v{}", version));
+    let stem = capture_file_stem(directory, template, &suffix, format.extension());
+    let image_path = file_path(
+        directory,
+        &format!("{}{}.{}", stem, suffix, format.extension()),
+    );
+
+    encode_capture(image, &image_path, format)?;
+    println!(
+        "Saved {} ({} x {})",
+        image_path,
+        image.width(),
+        image.height()
+    );
+
+    Ok(image_path)
+}
+
+/// Splits the image into a grid of files named by row and column, returning the first one's
+/// path
+fn write_tiles(
+    image: &RgbaImage,
+    directory: &str,
+    version: Option<u32>,
+    grid: TileGrid,
+    template: &str,
+    format: OutputFormat,
+) -> Result<String, String> {
+    let suffix = version.map_or(String::new(), |version| format!("-v{}", version));
+    let stem = capture_file_stem(
+        directory,
+        template,
+        &format!("{}-r1c1", suffix),
+        format.extension(),
+    );
+    let (width, height) = image.dimensions();
+    let rows = grid.rows.clamp(1, height.max(1));
+    let columns = grid.columns.clamp(1, width.max(1));
+
+    let mut first_path = None;
+    for row in 0..rows {
+        let (top, bottom) = (height * row / rows, height * (row + 1) / rows);
+        for column in 0..columns {
+            let (left, right) = (width * column / columns, width * (column + 1) / columns);
+            let file_name = format!(
+                "{}{}-r{}c{}.{}",
+                stem,
+                suffix,
+                row + 1,
+                column + 1,
+                format.extension()
+            );
+            let tile_path = file_path(directory, &file_name);
+            let tile = crop_imm(image, left, top, right - left, bottom - top).to_image();
+            encode_capture(&tile, &tile_path, format)?;
+            first_path.get_or_insert(tile_path);
+        }
+    }
+    println!(
+        "Saved {} x {} tiles of {} x {} into {}",
+        rows, columns, width, height, directory
+    );
+
+    Ok(first_path.unwrap())
+}
+
+/// Saves `image` like a finished capture into `directory`, as `version` when it is set, with
+/// the configured name, format and Done action. Returns the written file, the first one when
+/// split into tiles, `None` when Done only copies to the clipboard or writing fails, which is
+/// printed
+pub fn save_capture(
+    image: RgbaImage,
+    directory: &str,
+    version: Option<u32>,
+    config: &Config,
+) -> Option<String> {
+    let action = config.on_done;
+    if matches!(action, DoneAction::CopyClipboard | DoneAction::Both) {
+        if let Err(error) = copy_to_clipboard(&image) {
+            println!("Could not copy the image: {}", error);
+        }
+    }
+    if action == DoneAction::CopyClipboard {
+        println!(
+            "Copied to clipboard ({} x {})",
+            image.width(),
+            image.height()
+        );
+        return None;
+    }
+
+    // The directory may have been removed since the overlay opened
+    if let Err(error) = ensure_directory(directory) {
+        println!("{}", error);
+    }
+    let written = if config.tile_grid.is_single() {
+        write_capture(
+            &image,
+            directory,
+            version,
+            &config.filename_template,
+            config.format,
+        )
+    } else {
+        write_tiles(
+            &image,
+            directory,
+            version,
+            config.tile_grid,
+            &config.filename_template,
+            config.format,
+        )
+    };
+    let image_path = match written {
+        Ok(path) => path,
+        Err(error) => {
+            println!("{}", error);
+            return None;
+        }
+    };
+
+    if action == DoneAction::SaveAndCopyPath {
+        if let Err(error) = with_clipboard(|clipboard| clipboard.set_text(&image_path)) {
+            println!("Could not copy the path: {}", error);
+        }
+    }
+
+    Some(image_path)
+}
+
+/// Windows apps disagree on which image format they paste, so the image is offered both as a
+/// DIB (Office, Paint) and as PNG (browsers, chat apps) under a single clipboard session
+#[cfg(target_os = "windows")]
+pub fn copy_to_clipboard(image: &RgbaImage) -> Result<(), String> {
+    use std::io::Cursor;
+
+    use clipboard_win::{formats::CF_DIB, raw, register_format};
+
+    let (width, height) = image.dimensions();
+
+    // BITMAPINFOHEADER followed by bottom-up BGRA rows
+    let mut dib = Vec::with_capacity(40 + image.as_raw().len());
+    dib.extend_from_slice(&40u32.to_le_bytes());
+    dib.extend_from_slice(&(width as i32).to_le_bytes());
+    dib.extend_from_slice(&(height as i32).to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes());
+    dib.extend_from_slice(&32u16.to_le_bytes());
+    dib.extend_from_slice(&0u32.to_le_bytes());
+    dib.extend_from_slice(&(width * height * 4).to_le_bytes());
+    dib.extend_from_slice(&[0; 16]);
+    for row in image.rows().rev() {
+        for pixel in row {
+            let [r, g, b, a] = pixel.0;
+            dib.extend_from_slice(&[b, g, r, a]);
+        }
+    }
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|error| error.to_string())?;
+
+    let _clipboard =
+        clipboard_win::Clipboard::new_attempts(10).map_err(|error| error.to_string())?;
+    raw::empty().map_err(|error| error.to_string())?;
+    raw::set_without_clear(CF_DIB, &dib).map_err(|error| error.to_string())?;
+    if let Some(format) = register_format("PNG") {
+        raw::set_without_clear(format.get(), &png).map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+/// arboard offers `image/png` on X11 and Wayland and a PNG/TIFF pasteboard image on macOS,
+/// which is what common apps on those platforms read
+#[cfg(not(target_os = "windows"))]
+pub fn copy_to_clipboard(image: &RgbaImage) -> Result<(), String> {
+    with_clipboard(|clipboard| {
+        clipboard.set_image(ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: std::borrow::Cow::Borrowed(image.as_raw()),
+        })
+    })
+}
+
+/// Runs `action` on the clipboard shared by the whole process. On X11 and Wayland copied data
+/// is served by the clipboard that set it, so it is kept open after the overlay closes
+pub fn with_clipboard<T>(
+    action: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+) -> Result<T, String> {
+    static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+    let mut shared = CLIPBOARD.lock().map_err(|error| error.to_string())?;
+    let clipboard = match shared.as_mut() {
+        Some(clipboard) => clipboard,
+        None => shared.insert(Clipboard::new().map_err(|error| error.to_string())?),
+    };
+    action(clipboard).map_err(|error| error.to_string())
+}
+
+pub fn file_path(directory: &str, file_name: &str) -> String {
+    #[cfg(target_os = "windows")]
+    let path = format!("{}\\{}", directory, file_name);
+
+    #[cfg(not(target_os = "windows"))]
+    let path = format!("{}/{}", directory, file_name);
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::window::Id;
+
+    use super::*;
+    use crate::{config::caption::TimestampCaption, windows::capture_window::CaptureEvent};
+
+    /// Overlay on a blank `width` x `height` capture with `(id, x, y, width, height)` windows
+    fn overlay_with(
+        width: u32,
+        height: u32,
+        windows: &[(u32, i32, i32, u32, u32)],
+        scale_factor: f32,
+    ) -> CaptureWindow {
+        let windows = windows
+            .iter()
+            .map(|&(id, x, y, width, height)| {
+                (
+                    id,
+                    CapturedWindow {
+                        name: format!("Window {}", id),
+                        title: String::new(),
+                        x,
+                        y,
+                        width,
+                        height,
+                        image: RgbaImage::new(width, height),
+                    },
+                )
+            })
+            .collect();
+        CaptureWindow::with_capture(
+            RgbaImage::new(width, height),
+            windows,
+            None,
+            scale_factor,
+            &Config::default(),
+        )
+    }
+
+    #[test]
+    fn desktop_places_windows_left_of_the_primary_monitor() {
+        let (desktop, origin) = compose_desktop(&[
+            (
+                -40,
+                0,
+                RgbaImage::from_pixel(40, 30, Rgba([0, 0, 255, 255])),
+            ),
+            (
+                0,
+                -10,
+                RgbaImage::from_pixel(60, 50, Rgba([0, 255, 0, 255])),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(origin, (-40, -10));
+        assert_eq!(desktop.dimensions(), (100, 50));
+        // Below the left monitor, which doesn't reach up as far
+        assert_eq!(desktop.get_pixel(0, 0).0, [0, 0, 0, 0]);
+
+        // Straddling both monitors from a negative origin
+        let windows = [(
+            3,
+            CapturedWindow {
+                name: String::from("Window 3"),
+                title: String::new(),
+                x: -30,
+                y: 5,
+                width: 50,
+                height: 20,
+                image: RgbaImage::new(50, 20),
+            },
+        )];
+        let windows = relative_windows(windows.into_iter().collect(), origin);
+        assert_eq!((windows[&3].x, windows[&3].y), (10, 15));
+
+        let mut capture_window =
+            CaptureWindow::with_capture(desktop, windows, None, 1.0, &Config::default());
+        let (id, _, endpoints) = capture_window.window_at(Point::new(15.0, 20.0)).unwrap();
+        assert_eq!(id, 3);
+        assert_eq!(endpoints.initial_pt, Point::new(10.0, 15.0));
+
+        capture_window.crop_mode = CropMode::SpecificWindow(3);
+        assert_eq!(capture_window.selection_rect(), (10, 15, 50, 20));
+    }
+
+    /// Opaque capture with a different color in every pixel
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+        })
+    }
+
+    #[test]
+    fn crop_image_cuts_out_the_rect() {
+        let image = gradient(40, 30);
+        let cropped = crop_image(&image, (5, 10, 20, 8));
+        assert_eq!(cropped.dimensions(), (20, 8));
+        assert_eq!(cropped.get_pixel(0, 0), image.get_pixel(5, 10));
+        assert_eq!(cropped.get_pixel(19, 7), image.get_pixel(24, 17));
+    }
+
+    #[test]
+    fn crop_image_clamps_to_the_image() {
+        let image = gradient(40, 30);
+        let cropped = crop_image(&image, (30, 25, 100, 100));
+        assert_eq!(cropped.dimensions(), (10, 5));
+        assert_eq!(cropped.get_pixel(9, 4), image.get_pixel(39, 29));
+
+        assert_eq!(crop_image(&image, (50, 50, 10, 10)).dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn focus_export_stays_inside_the_cropped_canvas() {
+        let image = gradient(200, 100);
+        let mut capture_window = CaptureWindow::with_capture(
+            image.clone(),
+            IndexMap::new(),
+            None,
+            1.0,
+            &Config::default(),
+        );
+        // Cropped into (10, 10) to (70, 50), nothing around it is part of the capture anymore
+        capture_window.image = RgbaImage::new(200, 100);
+        overlay(
+            &mut capture_window.image,
+            &crop_image(&image, (10, 10, 60, 40)),
+            10,
+            10,
+        );
+        capture_window.canvas = Some((10, 10, 60, 40));
+        capture_window.crop_mode = CropMode::ManualSelection;
+        capture_window.endpoints = Endpoints {
+            initial_pt: Point::new(20.0, 20.0),
+            final_pt: Point::new(40.0, 30.0),
+        };
+
+        let focused = capture_window.focus_image();
+        assert_eq!(focused.dimensions(), (60, 40));
+        assert_eq!(focused.get_pixel(10, 10), image.get_pixel(20, 20));
+        assert_eq!(focused.get_pixel(29, 19), image.get_pixel(39, 29));
+        // No transparent margin was blurred into the edges
+        assert!(focused.get_pixel(0, 0).0[3] > 240);
+        assert!(focused.get_pixel(59, 39).0[3] > 240);
+    }
+
+    #[test]
+    fn sampled_pixels_show_placed_blurs() {
+        let image = gradient(40, 30);
+        let mut capture_window = CaptureWindow::with_capture(
+            image.clone(),
+            IndexMap::new(),
+            None,
+            1.0,
+            &Config::default(),
+        );
+        let patch = Rgba([9, 9, 9, 255]);
+        capture_window.shapes.push(Shape {
+            shape_type: ShapeType::Blur,
+            redaction: Some(Redaction {
+                x: 10,
+                y: 5,
+                pixels: RgbaImage::from_pixel(8, 6, patch),
+            }),
+            ..rectangle(10.0, 5.0, 8.0, 6.0)
+        });
+
+        assert_eq!(capture_window.redacted_pixel(12, 7), Some(patch));
+        assert_eq!(
+            capture_window.pixel_at(Point::new(17.5, 10.5)),
+            Some(RgbaColor::new(9, 9, 9, 255))
+        );
+        assert_eq!(
+            capture_window.redacted_pixel(18, 7),
+            Some(*image.get_pixel(18, 7))
+        );
+        assert_eq!(capture_window.redacted_pixel(40, 7), None);
+    }
+
+    #[test]
+    fn exact_pixels_exports_are_a_copy_of_the_capture() {
+        let image = gradient(400, 200);
+        let config = Config {
+            exact_pixels: true,
+            export_scale: 2.0,
+            max_dimension: Some(100),
+            timestamp: TimestampCaption {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut capture_window =
+            CaptureWindow::with_capture(image.clone(), IndexMap::new(), None, 1.0, &config);
+        capture_window.watermark = Some(RgbaImage::from_pixel(16, 16, Rgba([255, 0, 0, 255])));
+
+        let exported = capture_window.final_image();
+        assert_eq!(exported.dimensions(), image.dimensions());
+        assert_eq!(exported.as_raw(), image.as_raw());
+    }
+
+    #[test]
+    fn stamped_exports_differ_from_the_capture() {
+        let image = gradient(400, 200);
+        let config = Config {
+            timestamp: TimestampCaption {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let capture_window =
+            CaptureWindow::with_capture(image.clone(), IndexMap::new(), None, 1.0, &config);
+
+        assert_ne!(capture_window.final_image().as_raw(), image.as_raw());
+    }
+
+    #[test]
+    fn window_at_scales_the_cursor_to_image_pixels() {
+        let capture_window = overlay_with(800, 600, &[(7, 100, 50, 200, 100)], 2.0);
+        let (id, name, endpoints) = capture_window.window_at(Point::new(60.0, 30.0)).unwrap();
+        assert_eq!(id, 7);
+        assert_eq!(name, "Window 7");
+        assert_eq!(endpoints.initial_pt, Point::new(50.0, 25.0));
+        assert_eq!(endpoints.final_pt, Point::new(150.0, 75.0));
+    }
+
+    #[test]
+    fn window_at_misses_outside_the_scaled_bounds() {
+        let capture_window = overlay_with(800, 600, &[(7, 100, 50, 200, 100)], 2.0);
+        // Inside the window's pixels when read unscaled
+        assert!(capture_window.window_at(Point::new(160.0, 70.0)).is_none());
+        assert!(capture_window.window_at(Point::new(40.0, 30.0)).is_none());
+    }
+
+    #[test]
+    fn window_at_picks_the_window_under_a_fractionally_scaled_cursor() {
+        let capture_window = overlay_with(
+            1200,
+            900,
+            &[(1, 0, 0, 300, 300), (2, 300, 150, 300, 150)],
+            1.5,
+        );
+        let window_id = |x, y| {
+            capture_window
+                .window_at(Point::new(x, y))
+                .map(|(id, ..)| id)
+        };
+        assert_eq!(window_id(250.0, 120.0), Some(2));
+        assert_eq!(window_id(150.0, 120.0), Some(1));
+        assert_eq!(window_id(410.0, 120.0), None);
+    }
+
+    fn rectangle(x: f32, y: f32, width: f32, height: f32) -> Shape {
+        Shape {
+            shape_type: ShapeType::Rectangle,
+            endpoints: Some(Endpoints {
+                initial_pt: Point::new(x, y),
+                final_pt: Point::new(x + width, y + height),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dragged_selection_reports_whole_image_pixels() {
+        let mut capture_window = overlay_with(400, 300, &[], 1.5);
+        capture_window.crop_mode = CropMode::SelectionInProgress;
+        capture_window.endpoints.initial_pt = Point::new(100.4, 70.2);
+        let _ = capture_window.update(
+            Id::unique(),
+            CaptureEvent::UpdateCurrentPosition(Point::new(20.2, 10.6)),
+        );
+
+        // Normalized to (20.2, 10.6) and (100.4, 70.2), which 1.5x rounds to (30, 16) and
+        // (151, 105)
+        assert_eq!(capture_window.selection_rect(), (30, 16, 121, 89));
+        assert_eq!(capture_window.mode_desc, "121 x 89");
+    }
+
+    #[test]
+    fn any_shape_can_be_grabbed_by_its_body() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        let filled = |x, y| Shape {
+            is_filled: true,
+            ..rectangle(x, y, 40.0, 40.0)
+        };
+        capture_window.shapes = vec![filled(10.0, 10.0), filled(30.0, 30.0)];
+
+        // Inside both, away from every endpoint, the topmost wins though neither is selected
+        let point = Point::new(45.0, 40.0);
+        assert!(matches!(
+            capture_window.grab_at(point),
+            Some((1, ShapeDrag::Body(_)))
+        ));
+        assert!(matches!(
+            capture_window.grab_at(Point::new(20.0, 25.0)),
+            Some((0, ShapeDrag::Body(_)))
+        ));
+        assert!(capture_window.grab_at(Point::new(150.0, 80.0)).is_none());
+    }
+
+    #[test]
+    fn undo_steps_back_a_drag_then_the_shapes() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        let moved = rectangle(60.0, 40.0, 40.0, 20.0).bounds();
+        capture_window.shapes = vec![rectangle(10.0, 10.0, 40.0, 20.0)];
+
+        capture_window.cursor_position = Point::new(30.0, 10.0);
+        capture_window.start_shape_drag();
+        capture_window.cursor_position = Point::new(80.0, 40.0);
+        capture_window.drag_selected_shape();
+        capture_window.finish_shape_drag();
+        assert_eq!(capture_window.shapes[0].bounds(), moved);
+
+        capture_window.undo_annotation();
+        assert_eq!(capture_window.shapes.len(), 1);
+        assert_eq!(
+            capture_window.shapes[0].bounds(),
+            rectangle(10.0, 10.0, 40.0, 20.0).bounds()
+        );
+        capture_window.redo_annotation();
+        assert_eq!(capture_window.shapes[0].bounds(), moved);
+
+        capture_window.undo_annotation();
+        capture_window.undo_annotation();
+        assert!(capture_window.shapes.is_empty());
+    }
+
+    #[test]
+    fn a_new_shape_forgets_undone_drags() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        let placed = rectangle(10.0, 10.0, 40.0, 20.0).bounds();
+        capture_window.shapes = vec![rectangle(10.0, 10.0, 40.0, 20.0)];
+        capture_window.cursor_position = Point::new(30.0, 10.0);
+        capture_window.start_shape_drag();
+        capture_window.cursor_position = Point::new(80.0, 40.0);
+        capture_window.drag_selected_shape();
+        capture_window.finish_shape_drag();
+        capture_window.undo_annotation();
+
+        capture_window.cursor_position = Point::new(150.0, 50.0);
+        capture_window.place_step_marker();
+        capture_window.undo_annotation();
+        capture_window.redo_annotation();
+        assert_eq!(capture_window.shapes.len(), 2);
+        assert_eq!(capture_window.shapes[1].shape_type, ShapeType::StepMarker);
+        assert_eq!(capture_window.shapes[0].bounds(), placed);
+    }
+
+    #[test]
+    fn clicking_a_shape_leaves_nothing_to_undo() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        capture_window.shapes = vec![rectangle(10.0, 10.0, 40.0, 20.0)];
+        capture_window.cursor_position = Point::new(30.0, 10.0);
+        capture_window.start_shape_drag();
+        capture_window.finish_shape_drag();
+        assert!(capture_window.shape_moves.is_empty());
+    }
+
+    /// Drags the `handle` of a (100, 100) to (200, 200) selection to `cursor` under `aspect_lock`
+    fn resize_selection(
+        aspect_lock: AspectLock,
+        handle: SelectionHandle,
+        cursor: Point,
+    ) -> (u32, u32, u32, u32) {
+        let mut capture_window = overlay_with(400, 300, &[], 1.0);
+        capture_window.crop_mode = CropMode::ManualSelection;
+        capture_window.endpoints = Endpoints {
+            initial_pt: Point::new(100.0, 100.0),
+            final_pt: Point::new(200.0, 200.0),
+        };
+        capture_window.aspect_lock = aspect_lock;
+        capture_window.adjusting = Some(handle);
+        capture_window.cursor_position = cursor;
+        capture_window.adjust_selection();
+        capture_window.selection_rect()
+    }
+
+    #[test]
+    fn resizing_the_selection_keeps_the_aspect_lock() {
+        let edge = |left, right, top, bottom| SelectionHandle::Edge {
+            left,
+            right,
+            top,
+            bottom,
+        };
+        // Free edges only move their own side
+        assert_eq!(
+            resize_selection(
+                AspectLock::Free,
+                edge(false, true, false, false),
+                Point::new(220.0, 150.0)
+            ),
+            (100, 100, 120, 100)
+        );
+        // The bottom right corner is held to the shorter side of the drag
+        assert_eq!(
+            resize_selection(
+                AspectLock::Square,
+                edge(false, true, false, true),
+                Point::new(260.0, 230.0)
+            ),
+            (100, 100, 130, 130)
+        );
+        // The right edge sets the width, the bottom follows at 4:3
+        assert_eq!(
+            resize_selection(
+                AspectLock::Standard,
+                edge(false, true, false, false),
+                Point::new(220.0, 150.0)
+            ),
+            (100, 100, 120, 90)
+        );
+        // The top edge grows away from the bottom left corner
+        assert_eq!(
+            resize_selection(
+                AspectLock::Square,
+                edge(false, false, true, false),
+                Point::new(150.0, 40.0)
+            ),
+            (100, 40, 160, 160)
+        );
+    }
+
+    /// Makes a manual selection from `(x, y)` to `(right, bottom)` like a finished drag
+    fn select(capture_window: &mut CaptureWindow, x: f32, y: f32, right: f32, bottom: f32) {
+        capture_window.remember_selection();
+        capture_window.crop_mode = CropMode::ManualSelection;
+        capture_window.endpoints = Endpoints {
+            initial_pt: Point::new(x, y),
+            final_pt: Point::new(right, bottom),
+        };
+    }
+
+    #[test]
+    fn redo_crop_returns_to_the_undone_selections() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        select(&mut capture_window, 10.0, 10.0, 60.0, 50.0);
+        select(&mut capture_window, 20.0, 20.0, 40.0, 30.0);
+
+        capture_window.undo_crop();
+        capture_window.undo_crop();
+        assert!(matches!(capture_window.crop_mode, CropMode::FullScreen));
+
+        capture_window.redo_crop();
+        assert_eq!(capture_window.selection_rect(), (10, 10, 50, 40));
+        capture_window.redo_crop();
+        assert_eq!(capture_window.selection_rect(), (20, 20, 20, 10));
+        assert_eq!(capture_window.mode_desc, "20 x 10");
+
+        // Undo still steps back through the redone selections
+        capture_window.undo_crop();
+        assert_eq!(capture_window.selection_rect(), (10, 10, 50, 40));
+    }
+
+    #[test]
+    fn a_new_selection_clears_the_crop_redo() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        select(&mut capture_window, 10.0, 10.0, 60.0, 50.0);
+        capture_window.undo_crop();
+        select(&mut capture_window, 30.0, 30.0, 90.0, 70.0);
+
+        capture_window.redo_crop();
+        assert_eq!(capture_window.selection_rect(), (30, 30, 60, 40));
+        assert!(capture_window.crop_redo.is_empty());
+    }
+
+    /// Red 200 x 100 capture cropped into (10, 10) to (60, 50), with one rectangle inside the
+    /// selection and one reaching out of it
+    fn cropped_overlay() -> CaptureWindow {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        capture_window.image = RgbaImage::from_pixel(200, 100, Rgba([255, 0, 0, 255]));
+        capture_window.crop_mode = CropMode::ManualSelection;
+        capture_window.endpoints = Endpoints {
+            initial_pt: Point::new(10.0, 10.0),
+            final_pt: Point::new(60.0, 50.0),
+        };
+        capture_window.shapes = vec![
+            rectangle(20.0, 20.0, 40.0, 10.0),
+            rectangle(50.0, 40.0, 30.0, 30.0),
+        ];
+        capture_window.crop_to_selection();
+        capture_window
+    }
+
+    #[test]
+    fn crop_to_selection_keeps_the_shapes_inside() {
+        let capture_window = cropped_overlay();
+        // Centered at ((200 - 50) / 2, (100 - 40) / 2)
+        assert_eq!(capture_window.canvas, Some((75, 30, 50, 40)));
+        assert_eq!(capture_window.shapes.len(), 1);
+        let endpoints = capture_window.shapes[0].endpoints.unwrap();
+        assert_eq!(endpoints.initial_pt, Point::new(85.0, 40.0));
+        assert_eq!(endpoints.final_pt, Point::new(125.0, 50.0));
+    }
+
+    #[test]
+    fn crop_to_selection_leaves_nothing_outside_the_canvas() {
+        let mut capture_window = cropped_overlay();
+        assert_eq!(capture_window.image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(capture_window.image.get_pixel(75, 30).0, [255, 0, 0, 255]);
+
+        capture_window.crop_mode = CropMode::FullScreen;
+        assert_eq!(capture_window.selection_rect(), (75, 30, 50, 40));
+
+        // A selection reaching past the canvas is cut down to it
+        capture_window.crop_mode = CropMode::ManualSelection;
+        capture_window.endpoints = Endpoints {
+            initial_pt: Point::new(0.0, 0.0),
+            final_pt: Point::new(100.0, 50.0),
+        };
+        assert_eq!(capture_window.selection_rect(), (75, 30, 25, 20));
+        assert!(capture_window
+            .selection_base()
+            .pixels()
+            .all(|pixel| pixel.0 == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn shape_at_picks_up_to_the_tolerance_outside_the_stroke() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        let mut line = rectangle(0.0, 50.0, 100.0, 0.0);
+        line.shape_type = ShapeType::Line;
+        capture_window.shapes = vec![line];
+        // Half the medium stroke plus the default tolerance of 6
+        assert_eq!(capture_window.shape_at(Point::new(50.0, 58.5)), Some(0));
+        assert_eq!(capture_window.shape_at(Point::new(50.0, 58.75)), None);
+        assert_eq!(capture_window.shape_at(Point::new(108.5, 50.0)), Some(0));
+        assert_eq!(capture_window.shape_at(Point::new(108.75, 50.0)), None);
+    }
+
+    #[test]
+    fn shape_at_prefers_the_top_most_shape() {
+        let mut capture_window = overlay_with(200, 100, &[], 1.0);
+        let mut filled = rectangle(0.0, 0.0, 100.0, 100.0);
+        filled.is_filled = true;
+        capture_window.shapes = vec![filled.clone(), filled];
+        assert_eq!(capture_window.shape_at(Point::new(50.0, 50.0)), Some(1));
+    }
 }
@@ -0,0 +1,134 @@
+use std::{fs::read_dir, path::PathBuf, time::SystemTime};
+
+use ab_glyph::{Font, PxScale, ScaleFont};
+use xcap::image::{imageops::overlay, ImageFormat, Rgba, RgbaImage};
+
+use crate::config::{contact_sheet::ContactSheet, utils::filename_template_prefix, Config};
+
+use super::{
+    capture::file_path,
+    snippet::{draw_text, line_width, load_font},
+};
+
+/// Start of a saved sheet's name, sheets are left off later ones even if the template matches
+const CONTACT_SHEET_PREFIX: &str = "ContactSheet_";
+
+const PADDING: u32 = 12;
+const LABEL_SIZE: f32 = 14.0;
+
+/// Lays out thumbnails of the latest captures in `directory`, those named by `template`,
+/// newest first. `None` if there are none
+pub fn render_contact_sheet(
+    directory: &str,
+    template: &str,
+    style: &ContactSheet,
+) -> Option<RgbaImage> {
+    let prefix = filename_template_prefix(template);
+    let mut captures: Vec<(SystemTime, PathBuf)> = read_dir(directory)
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix)
+                || name.starts_with(CONTACT_SHEET_PREFIX)
+                || ![".png", ".jpg", ".webp"]
+                    .iter()
+                    .any(|extension| name.ends_with(extension))
+            {
+                return None;
+            }
+            Some((entry.metadata().ok()?.modified().ok()?, entry.path()))
+        })
+        .collect();
+    captures.sort_by_key(|capture| std::cmp::Reverse(capture.0));
+    captures.truncate(style.count.max(1) as usize);
+
+    let size = style.thumbnail_size.max(16);
+    let thumbnails: Vec<(String, RgbaImage)> = captures
+        .into_iter()
+        .filter_map(|(_, path)| {
+            let image = match xcap::image::open(&path) {
+                Ok(image) => image.thumbnail(size, size).into_rgba8(),
+                Err(error) => {
+                    println!("Could not read {}: {}", path.display(), error);
+                    return None;
+                }
+            };
+            let label = path.file_stem()?.to_string_lossy();
+            Some((label.trim_start_matches(prefix).to_string(), image))
+        })
+        .collect();
+    if thumbnails.is_empty() {
+        return None;
+    }
+
+    let font = load_font("");
+    let scale = PxScale::from(LABEL_SIZE);
+    let scaled = font.as_scaled(scale);
+    let label_height = if style.labels {
+        (scaled.height() + PADDING as f32 / 2.0).ceil() as u32
+    } else {
+        0
+    };
+
+    let columns = style.columns.clamp(1, thumbnails.len() as u32);
+    let rows = (thumbnails.len() as u32).div_ceil(columns);
+    let (cell_width, cell_height) = (size + PADDING, size + label_height + PADDING);
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width + PADDING,
+        rows * cell_height + PADDING,
+        Rgba([30, 30, 30, 255]),
+    );
+
+    for (index, (label, thumbnail)) in thumbnails.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        let (left, top) = (PADDING + column * cell_width, PADDING + row * cell_height);
+        overlay(
+            &mut sheet,
+            thumbnail,
+            (left + (size - thumbnail.width()) / 2) as i64,
+            (top + (size - thumbnail.height()) / 2) as i64,
+        );
+
+        if style.labels {
+            let mut label = label.clone();
+            while !label.is_empty() && line_width(&font, scale, &label) > size as f32 {
+                label.pop();
+            }
+            let width = line_width(&font, scale, &label);
+            draw_text(
+                &mut sheet,
+                &font,
+                scale,
+                &label,
+                (
+                    left as f32 + (size as f32 - width) / 2.0,
+                    (top + size) as f32 + PADDING as f32 / 2.0 + scaled.ascent(),
+                ),
+                [212, 212, 212],
+            );
+        }
+    }
+
+    Some(sheet)
+}
+
+/// Writes the contact sheet next to the captures, returning its path
+pub fn save_contact_sheet(config: &Config) -> Result<String, String> {
+    let sheet = render_contact_sheet(
+        &config.directory,
+        &config.filename_template,
+        &config.contact_sheet,
+    )
+    .ok_or_else(|| format!("No captures found in {}", config.directory))?;
+    let date = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S");
+    let path = file_path(
+        &config.directory,
+        &format!("{}{}.png", CONTACT_SHEET_PREFIX, date),
+    );
+    sheet
+        .save_with_format(&path, ImageFormat::Png)
+        .map_err(|error| format!("Could not write {}: {}", path, error))?;
+    println!("Saved {} ({} x {})", path, sheet.width(), sheet.height());
+    Ok(path)
+}
@@ -0,0 +1,981 @@
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use iced::{
+    widget::{
+        canvas::{
+            fill::Rule,
+            path::{arc::Elliptical, Builder},
+            Fill, Frame, LineCap, LineDash, LineJoin, Path, Stroke, Style, Text,
+        },
+        text::LineHeight,
+    },
+    Color, Pixels, Point, Radians, Rectangle, Size, Vector,
+};
+use tiny_skia::{
+    FillRule, GradientStop, Paint, PathBuilder, Pixmap, PremultipliedColorU8, RadialGradient, Rect,
+    SpreadMode, StrokeDash, Transform,
+};
+
+use crate::consts::MEDIUM;
+
+/// Control point distance of a quarter circle drawn as a cubic Bézier curve
+pub const KAPPA: f32 = 0.552_284_8;
+
+use super::{
+    models::{
+        Endpoints, RgbaColor, Shape, ShapeType, SPOTLIGHT_ALPHA, SPOTLIGHT_FEATHER,
+        STEP_MARKER_RADIUS,
+    },
+    snippet::{line_width, load_font, trace_text},
+    utils::arrowhead_points,
+};
+
+/// Everything the overlay and the exporter need from one kind of shape.
+///
+/// Each `ShapeType` is backed by an implementation returned from `ShapeType::drawable`, so a
+/// new shape is a new variant, its implementation and one match arm there.
+pub trait DrawableShape {
+    /// Name shown in the history panel
+    fn name(&self, shape: &Shape) -> &'static str;
+
+    /// Draws onto the overlay canvas, in overlay coordinates
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame);
+
+    /// Rasterizes onto an export layer, `transform` maps overlay coordinates to its pixels
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    );
+
+    /// Distance from `point` to the outline, ignoring the stroke width
+    fn distance_to(&self, shape: &Shape, endpoints: Endpoints, point: Point) -> f32;
+
+    fn bounds(&self, endpoints: Endpoints) -> Rectangle {
+        let (top_left, bottom_right) = endpoints.normalize();
+        Rectangle::new(top_left, (bottom_right - top_left).into())
+    }
+
+    fn label(&self, shape: &Shape) -> String {
+        format!("{} {}", shape.stroke_color, self.name(shape))
+    }
+
+    /// Geometry written to SVG and PDF exports, `None` bakes the shape into the background
+    fn serialize(&self, _shape: &Shape, _endpoints: Endpoints) -> Option<VectorPath> {
+        None
+    }
+}
+
+/// What a shape is written as in vector exports, in overlay coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VectorPath {
+    Rectangle {
+        top_left: Point,
+        bottom_right: Point,
+        radius: f32,
+    },
+    Ellipse {
+        top_left: Point,
+        bottom_right: Point,
+    },
+    Line {
+        from: Point,
+        to: Point,
+    },
+    /// Shaft from `from` to `base`, then the filled head from `tip` over both corners
+    Arrow {
+        from: Point,
+        base: Point,
+        tip: Point,
+        corners: (Point, Point),
+    },
+}
+
+impl VectorPath {
+    /// Same path with every coordinate and the corner radius multiplied by `factor`
+    pub fn scaled(self, factor: f32) -> Self {
+        let scale = |point: Point| Point::new(point.x * factor, point.y * factor);
+        match self {
+            VectorPath::Rectangle {
+                top_left,
+                bottom_right,
+                radius,
+            } => VectorPath::Rectangle {
+                top_left: scale(top_left),
+                bottom_right: scale(bottom_right),
+                radius: radius * factor,
+            },
+            VectorPath::Ellipse {
+                top_left,
+                bottom_right,
+            } => VectorPath::Ellipse {
+                top_left: scale(top_left),
+                bottom_right: scale(bottom_right),
+            },
+            VectorPath::Line { from, to } => VectorPath::Line {
+                from: scale(from),
+                to: scale(to),
+            },
+            VectorPath::Arrow {
+                from,
+                base,
+                tip,
+                corners: (right, left),
+            } => VectorPath::Arrow {
+                from: scale(from),
+                base: scale(base),
+                tip: scale(tip),
+                corners: (scale(right), scale(left)),
+            },
+        }
+    }
+}
+
+pub struct RectangleShape;
+pub struct EllipseShape;
+pub struct LineShape;
+pub struct ArrowShape;
+pub struct SpotlightShape;
+pub struct TextShape;
+pub struct FreehandShape;
+pub struct MeasureShape;
+pub struct StepMarkerShape;
+pub struct BlurShape;
+
+impl ShapeType {
+    pub fn drawable(self) -> &'static dyn DrawableShape {
+        match self {
+            ShapeType::Rectangle => &RectangleShape,
+            ShapeType::Ellipse => &EllipseShape,
+            ShapeType::Line => &LineShape,
+            ShapeType::Arrow => &ArrowShape,
+            ShapeType::Spotlight => &SpotlightShape,
+            ShapeType::Text => &TextShape,
+            ShapeType::Freehand => &FreehandShape,
+            ShapeType::Measure => &MeasureShape,
+            ShapeType::StepMarker => &StepMarkerShape,
+            ShapeType::Blur => &BlurShape,
+        }
+    }
+}
+
+impl DrawableShape for RectangleShape {
+    fn name(&self, shape: &Shape) -> &'static str {
+        match (shape.is_filled, shape.is_solid) {
+            (true, false) => "Highlight",
+            (true, true) => "Filled Rectangle",
+            (false, _) => "Rectangle",
+        }
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let size = (bottom_right - top_left).into();
+        let radius = shape.fitted_corner_radius(endpoints);
+        let path = if radius > 0.0 {
+            Path::rounded_rectangle(top_left, size, radius.into())
+        } else {
+            Path::rectangle(top_left, size)
+        };
+        fill_or_stroke(shape, &path, frame);
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let mut builder = PathBuilder::new();
+        if let Some(rect) = skia_rect(endpoints) {
+            push_rounded_rect(&mut builder, rect, shape.fitted_corner_radius(endpoints));
+        }
+        if let Some(path) = builder.finish() {
+            fill_or_stroke_path(shape, &path, pixmap, transform);
+        }
+    }
+
+    fn distance_to(&self, shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let dx = (top_left.x - point.x).max(point.x - bottom_right.x);
+        let dy = (top_left.y - point.y).max(point.y - bottom_right.y);
+        if dx <= 0.0 && dy <= 0.0 {
+            if shape.is_filled {
+                0.0
+            } else {
+                -dx.max(dy)
+            }
+        } else {
+            dx.max(0.0).hypot(dy.max(0.0))
+        }
+    }
+
+    fn serialize(&self, shape: &Shape, endpoints: Endpoints) -> Option<VectorPath> {
+        let (top_left, bottom_right) = endpoints.normalize();
+        Some(VectorPath::Rectangle {
+            top_left,
+            bottom_right,
+            radius: shape.fitted_corner_radius(endpoints),
+        })
+    }
+}
+
+impl DrawableShape for EllipseShape {
+    fn name(&self, shape: &Shape) -> &'static str {
+        if shape.is_filled {
+            "Filled Ellipse"
+        } else {
+            "Ellipse"
+        }
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let size = bottom_right - top_left;
+        let radii = Vector::new(size.x / 2.0, size.y / 2.0);
+        let center = Point::new(top_left.x + radii.x, top_left.y + radii.y);
+        let arc = Elliptical {
+            center,
+            radii,
+            rotation: Radians(0.0),
+            start_angle: Radians(0.0),
+            end_angle: Radians(360.0),
+        };
+        let mut builder = Builder::new();
+        builder.ellipse(arc);
+        fill_or_stroke(shape, &builder.build(), frame);
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        if let Some(path) = skia_rect(endpoints).and_then(PathBuilder::from_oval) {
+            fill_or_stroke_path(shape, &path, pixmap, transform);
+        }
+    }
+
+    fn distance_to(&self, shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let (rx, ry) = (
+            (bottom_right.x - top_left.x) / 2.0,
+            (bottom_right.y - top_left.y) / 2.0,
+        );
+        let center = Point::new(top_left.x + rx, top_left.y + ry);
+        let (dx, dy) = (point.x - center.x, point.y - center.y);
+        if rx < f32::EPSILON || ry < f32::EPSILON {
+            return segment_distance(point, top_left, bottom_right);
+        }
+        // Measured along the ray from the center, close to the true distance for anything
+        // but very flat ellipses
+        let ratio = (dx / rx).hypot(dy / ry);
+        if ratio <= 1.0 && shape.is_filled {
+            0.0
+        } else if ratio < f32::EPSILON {
+            rx.min(ry)
+        } else {
+            dx.hypot(dy) * (1.0 - 1.0 / ratio).abs()
+        }
+    }
+
+    fn serialize(&self, _shape: &Shape, endpoints: Endpoints) -> Option<VectorPath> {
+        let (top_left, bottom_right) = endpoints.normalize();
+        Some(VectorPath::Ellipse {
+            top_left,
+            bottom_right,
+        })
+    }
+}
+
+impl DrawableShape for LineShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Line"
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let path = Path::line(endpoints.initial_pt, endpoints.final_pt);
+        frame.stroke(&path, canvas_stroke(shape));
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let mut builder = PathBuilder::new();
+        builder.move_to(endpoints.initial_pt.x, endpoints.initial_pt.y);
+        builder.line_to(endpoints.final_pt.x, endpoints.final_pt.y);
+        if let Some(path) = builder.finish() {
+            fill_or_stroke_path(shape, &path, pixmap, transform);
+        }
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        segment_distance(point, endpoints.initial_pt, endpoints.final_pt)
+    }
+
+    fn serialize(&self, _shape: &Shape, endpoints: Endpoints) -> Option<VectorPath> {
+        Some(VectorPath::Line {
+            from: endpoints.initial_pt,
+            to: endpoints.final_pt,
+        })
+    }
+}
+
+impl DrawableShape for ArrowShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Arrow"
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let (right_pt, left_pt, base) =
+            arrowhead_points(endpoints.initial_pt, endpoints.final_pt, shape.stroke_width);
+        frame.stroke(
+            &Path::line(endpoints.initial_pt, base),
+            canvas_stroke(shape),
+        );
+        let mut builder = Builder::new();
+        builder.move_to(endpoints.final_pt);
+        builder.line_to(right_pt);
+        builder.line_to(left_pt);
+        builder.close();
+        let head = builder.build();
+        frame.fill(&head, Fill::from(shape.stroke().into_iced()));
+        // The head stays solid so the tip keeps its shape
+        let head_stroke = Stroke {
+            line_dash: LineDash::default(),
+            ..canvas_stroke(shape)
+        };
+        frame.stroke(&head, head_stroke.with_line_join(LineJoin::Round));
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let (right_pt, left_pt, base) =
+            arrowhead_points(endpoints.initial_pt, endpoints.final_pt, shape.stroke_width);
+        let mut paint = Paint::default();
+        paint.set_color(shape.stroke().into_paint());
+        let mut stroke = skia_stroke(shape);
+
+        let mut builder = PathBuilder::new();
+        builder.move_to(endpoints.initial_pt.x, endpoints.initial_pt.y);
+        builder.line_to(base.x, base.y);
+        if let Some(path) = builder.finish() {
+            pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+        }
+
+        let mut builder = PathBuilder::new();
+        builder.move_to(endpoints.final_pt.x, endpoints.final_pt.y);
+        builder.line_to(right_pt.x, right_pt.y);
+        builder.line_to(left_pt.x, left_pt.y);
+        builder.close();
+        if let Some(path) = builder.finish() {
+            pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+            stroke.line_join = tiny_skia::LineJoin::Round;
+            stroke.dash = None;
+            pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+        }
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        segment_distance(point, endpoints.initial_pt, endpoints.final_pt)
+    }
+
+    fn serialize(&self, shape: &Shape, endpoints: Endpoints) -> Option<VectorPath> {
+        let (right_pt, left_pt, base) =
+            arrowhead_points(endpoints.initial_pt, endpoints.final_pt, shape.stroke_width);
+        Some(VectorPath::Arrow {
+            from: endpoints.initial_pt,
+            base,
+            tip: endpoints.final_pt,
+            corners: (right_pt, left_pt),
+        })
+    }
+}
+
+impl DrawableShape for SpotlightShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Spotlight"
+    }
+
+    fn draw(&self, _shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        // Canvas has no radial gradients, so the soft edge is built from stacked rings
+        const RINGS: usize = 8;
+        let radius = endpoints.length();
+        let alpha = 1.0 - (1.0 - SPOTLIGHT_ALPHA).powf(1.0 / RINGS as f32);
+        let size = frame.size();
+        for ring in 0..RINGS {
+            let ring_radius = radius * (1.0 + SPOTLIGHT_FEATHER * ring as f32 / RINGS as f32);
+            let path = Path::new(|builder| {
+                builder.rectangle(Point::ORIGIN, size);
+                builder.circle(endpoints.initial_pt, ring_radius);
+            });
+            frame.fill(
+                &path,
+                Fill {
+                    style: Style::Solid(Color::from_rgba(0.0, 0.0, 0.0, alpha)),
+                    rule: Rule::EvenOdd,
+                },
+            );
+        }
+    }
+
+    fn rasterize(
+        &self,
+        _shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let radius = endpoints.length();
+        let center = tiny_skia::Point::from_xy(endpoints.initial_pt.x, endpoints.initial_pt.y);
+        let shader = RadialGradient::new(
+            center,
+            center,
+            radius * (1.0 + SPOTLIGHT_FEATHER),
+            vec![
+                GradientStop::new(0.0, tiny_skia::Color::TRANSPARENT),
+                GradientStop::new(
+                    1.0 / (1.0 + SPOTLIGHT_FEATHER),
+                    tiny_skia::Color::TRANSPARENT,
+                ),
+                GradientStop::new(
+                    1.0,
+                    tiny_skia::Color::from_rgba(0.0, 0.0, 0.0, SPOTLIGHT_ALPHA).unwrap(),
+                ),
+            ],
+            SpreadMode::Pad,
+            transform,
+        );
+        // The darkening covers the whole layer, not just the circle
+        let bounds = Rect::from_xywh(0.0, 0.0, pixmap.width() as f32, pixmap.height() as f32);
+        if let (Some(shader), Some(bounds)) = (shader, bounds) {
+            let paint = Paint {
+                shader,
+                ..Default::default()
+            };
+            pixmap.fill_rect(bounds, &paint, Transform::identity(), None);
+        }
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        let center = endpoints.initial_pt;
+        ((point.x - center.x).hypot(point.y - center.y) - endpoints.length()).max(0.0)
+    }
+
+    fn bounds(&self, endpoints: Endpoints) -> Rectangle {
+        let radius = endpoints.length();
+        Rectangle::new(
+            endpoints.initial_pt - Vector::new(radius, radius),
+            Size::new(2.0 * radius, 2.0 * radius),
+        )
+    }
+
+    fn label(&self, shape: &Shape) -> String {
+        String::from(self.name(shape))
+    }
+}
+
+impl DrawableShape for TextShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Text"
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let (top_left, _) = endpoints.normalize();
+        frame.fill_text(Text {
+            content: shape.text.clone(),
+            position: top_left,
+            color: shape.stroke().into_iced(),
+            size: Pixels(shape.stroke_width.font_size()),
+            line_height: LineHeight::Relative(1.0),
+            font: MEDIUM,
+            ..Default::default()
+        });
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let (top_left, _) = endpoints.normalize();
+        rasterize_text(
+            &shape.text,
+            shape.stroke(),
+            top_left,
+            shape.stroke_width.font_size(),
+            pixmap,
+            transform,
+        );
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let dx = (top_left.x - point.x).max(point.x - bottom_right.x);
+        let dy = (top_left.y - point.y).max(point.y - bottom_right.y);
+        dx.max(0.0).hypot(dy.max(0.0))
+    }
+
+    fn label(&self, shape: &Shape) -> String {
+        const PREVIEW: usize = 14;
+        let mut preview: String = shape.text.chars().take(PREVIEW).collect();
+        if shape.text.chars().count() > PREVIEW {
+            preview.push_str("...");
+        }
+        format!("Text \"{}\"", preview)
+    }
+}
+
+impl DrawableShape for FreehandShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Pen"
+    }
+
+    fn draw(&self, shape: &Shape, _endpoints: Endpoints, frame: &mut Frame) {
+        let Some((first, rest)) = shape.points.split_first() else {
+            return;
+        };
+        let mut builder = Builder::new();
+        builder.move_to(*first);
+        // A lone click still leaves a dot
+        builder.line_to(*first);
+        for point in rest {
+            builder.line_to(*point);
+        }
+        let stroke = Stroke {
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..canvas_stroke(shape)
+        };
+        frame.stroke(&builder.build(), stroke);
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        _endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let Some((first, rest)) = shape.points.split_first() else {
+            return;
+        };
+        let mut builder = PathBuilder::new();
+        builder.move_to(first.x, first.y);
+        builder.line_to(first.x, first.y);
+        for point in rest {
+            builder.line_to(point.x, point.y);
+        }
+        if let Some(path) = builder.finish() {
+            let mut paint = Paint::default();
+            paint.set_color(shape.stroke().into_paint());
+            let stroke = tiny_skia::Stroke {
+                width: shape.stroke_width.f32(),
+                line_cap: tiny_skia::LineCap::Round,
+                line_join: tiny_skia::LineJoin::Round,
+                ..Default::default()
+            };
+            pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+        }
+    }
+
+    fn distance_to(&self, shape: &Shape, _endpoints: Endpoints, point: Point) -> f32 {
+        match shape.points.as_slice() {
+            [] => f32::INFINITY,
+            [only] => only.distance(point),
+            points => points
+                .windows(2)
+                .map(|segment| segment_distance(point, segment[0], segment[1]))
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+}
+
+impl DrawableShape for MeasureShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Measurement"
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let mut builder = Builder::new();
+        for (start, end) in dimension_segments(shape, endpoints) {
+            builder.move_to(start);
+            builder.line_to(end);
+        }
+        frame.stroke(&builder.build(), canvas_stroke(shape));
+
+        let (position, size) = dimension_label(shape, endpoints);
+        frame.fill_rectangle(
+            position,
+            size,
+            Fill::from(Color::from_rgba(0.0, 0.0, 0.0, 0.6)),
+        );
+        frame.fill_text(Text {
+            content: shape.text.clone(),
+            position: position + Vector::new(MEASURE_PADDING, MEASURE_PADDING),
+            color: shape.stroke().into_iced(),
+            size: Pixels(MEASURE_FONT_SIZE),
+            line_height: LineHeight::Relative(1.0),
+            font: MEDIUM,
+            ..Default::default()
+        });
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let mut builder = PathBuilder::new();
+        for (start, end) in dimension_segments(shape, endpoints) {
+            builder.move_to(start.x, start.y);
+            builder.line_to(end.x, end.y);
+        }
+        if let Some(path) = builder.finish() {
+            fill_or_stroke_path(shape, &path, pixmap, transform);
+        }
+
+        let (position, size) = dimension_label(shape, endpoints);
+        if let Some(rect) = Rect::from_xywh(position.x, position.y, size.width, size.height) {
+            let mut paint = Paint::default();
+            paint.set_color_rgba8(0, 0, 0, 153);
+            pixmap.fill_rect(rect, &paint, transform, None);
+        }
+        rasterize_text(
+            &shape.text,
+            shape.stroke(),
+            position + Vector::new(MEASURE_PADDING, MEASURE_PADDING),
+            MEASURE_FONT_SIZE,
+            pixmap,
+            transform,
+        );
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        segment_distance(point, endpoints.initial_pt, endpoints.final_pt)
+    }
+
+    fn label(&self, shape: &Shape) -> String {
+        format!("Measurement {}", shape.text)
+    }
+}
+
+impl DrawableShape for StepMarkerShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Step"
+    }
+
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        let (center, radius) = marker_circle(endpoints);
+        frame.fill(
+            &Path::circle(center, radius),
+            Fill::from(shape.fill().into_iced()),
+        );
+        let (top_left, font_size) = marker_number(shape, center, radius);
+        frame.fill_text(Text {
+            content: shape.step.to_string(),
+            position: top_left,
+            color: contrasting(shape.fill()).faded(shape.alpha).into_iced(),
+            size: Pixels(font_size),
+            line_height: LineHeight::Relative(1.0),
+            font: MEDIUM,
+            ..Default::default()
+        });
+    }
+
+    fn rasterize(
+        &self,
+        shape: &Shape,
+        endpoints: Endpoints,
+        pixmap: &mut Pixmap,
+        transform: Transform,
+    ) {
+        let (center, radius) = marker_circle(endpoints);
+        if let Some(path) = PathBuilder::from_circle(center.x, center.y, radius) {
+            let mut paint = Paint::default();
+            paint.set_color(shape.fill().into_paint());
+            pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+        }
+        let (top_left, font_size) = marker_number(shape, center, radius);
+        rasterize_text(
+            &shape.step.to_string(),
+            contrasting(shape.fill()).faded(shape.alpha),
+            top_left,
+            font_size,
+            pixmap,
+            transform,
+        );
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        let (center, radius) = marker_circle(endpoints);
+        (center.distance(point) - radius).max(0.0)
+    }
+
+    fn label(&self, shape: &Shape) -> String {
+        format!("{} Step {}", shape.stroke_color, shape.step)
+    }
+}
+
+impl DrawableShape for BlurShape {
+    fn name(&self, _shape: &Shape) -> &'static str {
+        "Blur"
+    }
+
+    /// Placed blurs are part of the background, only the region being dragged is outlined
+    fn draw(&self, shape: &Shape, endpoints: Endpoints, frame: &mut Frame) {
+        if shape.redaction.is_none() {
+            let (top_left, bottom_right) = endpoints.normalize();
+            frame.stroke(
+                &Path::rectangle(top_left, (bottom_right - top_left).into()),
+                Stroke::default()
+                    .with_width(1.0)
+                    .with_color(shape.stroke().into_iced()),
+            );
+        }
+    }
+
+    fn rasterize(
+        &self,
+        _shape: &Shape,
+        _endpoints: Endpoints,
+        _pixmap: &mut Pixmap,
+        _transform: Transform,
+    ) {
+    }
+
+    fn distance_to(&self, _shape: &Shape, endpoints: Endpoints, point: Point) -> f32 {
+        let (top_left, bottom_right) = endpoints.normalize();
+        let dx = (top_left.x - point.x).max(point.x - bottom_right.x);
+        let dy = (top_left.y - point.y).max(point.y - bottom_right.y);
+        dx.max(0.0).hypot(dy.max(0.0))
+    }
+
+    fn label(&self, shape: &Shape) -> String {
+        format!("{:?} Blur", shape.stroke_width)
+    }
+}
+
+/// Center and radius of the circle a step marker's endpoints enclose
+fn marker_circle(endpoints: Endpoints) -> (Point, f32) {
+    let (top_left, bottom_right) = endpoints.normalize();
+    let radius = (bottom_right.x - top_left.x).min(bottom_right.y - top_left.y) / 2.0;
+    (Point::new(top_left.x + radius, top_left.y + radius), radius)
+}
+
+/// Top left corner and font size of a step marker's number, centered in its circle and
+/// shrunk for numbers with several digits
+fn marker_number(shape: &Shape, center: Point, radius: f32) -> (Point, f32) {
+    let label = shape.step.to_string();
+    let font_size = radius / STEP_MARKER_RADIUS / (label.len().max(2) as f32 / 2.0);
+    let size = text_size(&label, font_size);
+    (
+        Point::new(center.x - size.width / 2.0, center.y - size.height / 2.0),
+        font_size,
+    )
+}
+
+/// Black or white, whichever reads better on `color`
+fn contrasting(color: RgbaColor) -> RgbaColor {
+    let luma = 0.299 * color.r as f32 + 0.587 * color.g as f32 + 0.114 * color.b as f32;
+    if luma > 150.0 {
+        RgbaColor::new(0, 0, 0, 255)
+    } else {
+        RgbaColor::new(255, 255, 255, 255)
+    }
+}
+
+const MEASURE_FONT_SIZE: f32 = 14.0;
+const MEASURE_PADDING: f32 = 3.0;
+
+/// The dimension line followed by a tick across each of its ends
+fn dimension_segments(shape: &Shape, endpoints: Endpoints) -> [(Point, Point); 3] {
+    let Endpoints {
+        initial_pt,
+        final_pt,
+    } = endpoints;
+    let line = final_pt - initial_pt;
+    let length = initial_pt.distance(final_pt).max(f32::EPSILON);
+    let half_tick = 4.0 + shape.stroke_width.f32() * 2.0;
+    let normal = Vector::new(-line.y / length * half_tick, line.x / length * half_tick);
+    [
+        (initial_pt, final_pt),
+        (initial_pt - normal, initial_pt + normal),
+        (final_pt - normal, final_pt + normal),
+    ]
+}
+
+/// Top left corner and size of the gap label's backdrop, centered on the line
+fn dimension_label(shape: &Shape, endpoints: Endpoints) -> (Point, Size) {
+    let text = text_size(&shape.text, MEASURE_FONT_SIZE);
+    let size = Size::new(
+        text.width + 2.0 * MEASURE_PADDING,
+        text.height + 2.0 * MEASURE_PADDING,
+    );
+    let center = Point::new(
+        (endpoints.initial_pt.x + endpoints.final_pt.x) / 2.0,
+        (endpoints.initial_pt.y + endpoints.final_pt.y) / 2.0,
+    );
+    (
+        Point::new(center.x - size.width / 2.0, center.y - size.height / 2.0),
+        size,
+    )
+}
+
+/// Draws one line of `text` with its line box at `top_left`, as the canvas lays it out
+fn rasterize_text(
+    text: &str,
+    color: RgbaColor,
+    top_left: Point,
+    font_size: f32,
+    pixmap: &mut Pixmap,
+    transform: Transform,
+) {
+    let mut origin = [tiny_skia::Point::from_xy(top_left.x, top_left.y)];
+    transform.map_points(&mut origin);
+    let font = load_font("");
+    let font_size = font_size * transform.sy;
+    let scale = em_scale(&font, font_size);
+    let scaled = font.as_scaled(scale);
+    // Centered in a line as tall as the font size, as the canvas lays it out
+    let baseline = origin[0].y + (font_size - scaled.height()) / 2.0 + scaled.ascent();
+
+    let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+    let pixels = pixmap.pixels_mut();
+    trace_text(
+        &font,
+        scale,
+        text,
+        (origin[0].x, baseline),
+        |x, y, coverage| {
+            if x < 0 || y < 0 || x >= width || y >= height {
+                return;
+            }
+            let pixel = &mut pixels[(y * width + x) as usize];
+            let alpha = color.a as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+            let over = |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1.0 - alpha)).round();
+            let a = over(255, pixel.alpha()) as u8;
+            let [r, g, b] = [
+                (color.r, pixel.red()),
+                (color.g, pixel.green()),
+                (color.b, pixel.blue()),
+            ]
+            .map(|(src, dst)| (over(src, dst) as u8).min(a));
+            if let Some(blended) = PremultipliedColorU8::from_rgba(r, g, b, a) {
+                *pixel = blended;
+            }
+        },
+    );
+}
+
+/// Size of `text` laid out on one line at `font_size`, matching the canvas
+pub fn text_size(text: &str, font_size: f32) -> Size {
+    let font = load_font("");
+    Size::new(
+        line_width(&font, em_scale(&font, font_size), text),
+        font_size,
+    )
+}
+
+/// `ab_glyph` scales by the font's height, the canvas by its em size
+fn em_scale(font: &FontArc, font_size: f32) -> PxScale {
+    PxScale::from(font_size * font.height_unscaled() / font.units_per_em().unwrap_or(1000.0))
+}
+
+fn canvas_stroke(shape: &Shape) -> Stroke<'static> {
+    Stroke {
+        line_dash: LineDash {
+            segments: shape.dash_pattern(),
+            offset: 0,
+        },
+        ..Stroke::default()
+            .with_width(shape.stroke_width.f32())
+            .with_color(shape.stroke().into_iced())
+    }
+}
+
+fn skia_stroke(shape: &Shape) -> tiny_skia::Stroke {
+    tiny_skia::Stroke {
+        width: shape.stroke_width.f32(),
+        dash: StrokeDash::new(shape.dash_pattern().to_vec(), 0.0),
+        ..Default::default()
+    }
+}
+
+fn fill_or_stroke(shape: &Shape, path: &Path, frame: &mut Frame) {
+    if shape.is_filled {
+        frame.fill(path, Fill::from(shape.fill().into_iced()));
+    } else {
+        frame.stroke(path, canvas_stroke(shape));
+    }
+}
+
+/// Adds `rect` with its corners rounded by `radius` as a closed contour, quarter circles are
+/// approximated by cubic curves
+pub fn push_rounded_rect(builder: &mut PathBuilder, rect: Rect, radius: f32) {
+    if radius <= 0.0 {
+        builder.push_rect(rect);
+        return;
+    }
+    let k = radius * (1.0 - KAPPA);
+    let (left, top, right, bottom) = (rect.left(), rect.top(), rect.right(), rect.bottom());
+    builder.move_to(left + radius, top);
+    builder.line_to(right - radius, top);
+    builder.cubic_to(right - k, top, right, top + k, right, top + radius);
+    builder.line_to(right, bottom - radius);
+    builder.cubic_to(right, bottom - k, right - k, bottom, right - radius, bottom);
+    builder.line_to(left + radius, bottom);
+    builder.cubic_to(left + k, bottom, left, bottom - k, left, bottom - radius);
+    builder.line_to(left, top + radius);
+    builder.cubic_to(left, top + k, left + k, top, left + radius, top);
+    builder.close();
+}
+
+fn skia_rect(endpoints: Endpoints) -> Option<Rect> {
+    let (top_left, bottom_right) = endpoints.normalize();
+    Rect::from_ltrb(top_left.x, top_left.y, bottom_right.x, bottom_right.y)
+}
+
+fn fill_or_stroke_path(
+    shape: &Shape,
+    path: &tiny_skia::Path,
+    pixmap: &mut Pixmap,
+    transform: Transform,
+) {
+    let mut paint = Paint::default();
+    if shape.is_filled {
+        paint.set_color(shape.fill().into_paint());
+        pixmap.fill_path(path, &paint, FillRule::EvenOdd, transform, None);
+    } else {
+        paint.set_color(shape.stroke().into_paint());
+        pixmap.stroke_path(path, &paint, &skia_stroke(shape), transform, None);
+    }
+}
+
+fn segment_distance(point: Point, start: Point, end: Point) -> f32 {
+    let (sx, sy) = (end.x - start.x, end.y - start.y);
+    let length_squared = sx * sx + sy * sy;
+    let t = if length_squared < f32::EPSILON {
+        0.0
+    } else {
+        (((point.x - start.x) * sx + (point.y - start.y) * sy) / length_squared).clamp(0.0, 1.0)
+    };
+    (point.x - (start.x + t * sx)).hypot(point.y - (start.y + t * sy))
+}
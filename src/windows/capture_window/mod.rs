@@ -1,31 +1,51 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use capture::{copy_to_clipboard, save_reveal};
 use iced::{
+    keyboard::Modifiers,
+    mouse::Interaction,
     widget::{
-        button, canvas, canvas::Cache, column, container, horizontal_space, image::Handle, row,
-        stack, text, vertical_space, Image,
+        button, canvas, canvas::Cache, column, container, horizontal_space, image::Handle,
+        mouse_area, row, slider, stack, text, text_input, vertical_space, Column, Image, Row,
+    },
+    window::{
+        change_mode, disable_mouse_passthrough, enable_mouse_passthrough, gain_focus, Id,
+        Mode as WindowMode,
     },
-    window::Id,
     Alignment::Center,
     Length::Fill,
-    Point, Task,
+    Point, Rectangle, Task, Vector,
 };
 use indexmap::IndexMap;
 use models::{
-    CapturedWindow, CropMode, Endpoints, Mode, Shape, ShapeColor, ShapeStroke, ShapeType,
+    AspectLock, CapturedWindow, CropMode, Endpoints, Mode, RgbaColor, SafeArea, SelectionHandle,
+    Shape, ShapeColor, ShapeDrag, ShapeMove, ShapeStroke, ShapeType, StrokeStyle, CORNER_RADII,
 };
+use rfd::FileDialog;
+use svg::import_svg;
 use xcap::image::RgbaImage;
 
 use crate::{
     app::AppEvent,
+    config::{Config, DoneAction, EmptyDoneAction, ImageFlip, ReadoutPlacement},
     consts::{
-        ARROW, ELLIPSE_FILLED, ELLIPSE_STROKE, HIGHLIGHT, ICON, LINE, RECT_FILLED, RECT_STROKE,
-        STROKE_BROAD, STROKE_MEDIUM, STROKE_THIN,
+        ARROW, BLANK_CAPTURE, ELLIPSE_FILLED, ELLIPSE_STROKE, HIGHLIGHT, ICON, LINE, RECT_FILLED,
+        RECT_STROKE, STROKE_BROAD, STROKE_MEDIUM, STROKE_THIN,
     },
     theme::{button::ButtonClass, text::TextClass, Element},
 };
 
 pub mod annotate;
 pub mod capture;
+pub mod contact_sheet;
+pub mod drawable;
 pub mod models;
+pub mod pdf;
+pub mod snippet;
+pub mod svg;
 pub mod utils;
 
 pub struct CaptureWindow {
@@ -33,6 +53,9 @@ pub struct CaptureWindow {
     pub crop_mode: CropMode,
     pub mode_desc: String,
     pub image: RgbaImage,
+    /// Part of the image left after cropping into a selection, in its pixels. Selections and
+    /// exports stay inside it, `None` while all of the image is the canvas
+    pub canvas: Option<(u32, u32, u32, u32)>,
     pub windows: IndexMap<u32, CapturedWindow>,
     pub cursor_position: Point,
     pub mode: Mode,
@@ -40,131 +63,677 @@ pub struct CaptureWindow {
     pub shape: Shape,
     pub shapes: Vec<Shape>,
     pub cache: Cache,
+    pub version: u32,
+    pub config: Config,
+    pub active_window: Option<Rectangle>,
+    pub safe_area: SafeArea,
+    pub aspect_lock: AspectLock,
+    pub last_input: Instant,
+    /// Set when the capture is cancelled so closing the window doesn't save it
+    pub discard: bool,
+    pub selected_index: Option<usize>,
+    pub toolbar_expanded: bool,
+    /// Set while the peek key is held, so shapes can be drawn under the toolbar
+    pub toolbar_hidden: bool,
+    /// Logo from the watermark config, loaded once when the overlay opens
+    pub watermark: Option<RgbaImage>,
+    /// Monitor the image was captured from, `None` when it isn't a screen capture
+    pub monitor_id: Option<u32>,
+    /// Color and stroke each tool was last used with, keyed by shape type and fill flags
+    pub last_tools: HashMap<(ShapeType, bool, bool), (RgbaColor, ShapeStroke)>,
+    /// Where the shape being drawn was started
+    pub draw_origin: Point,
+    pub modifiers: Modifiers,
+    /// Earlier manual or window selections, most recent last
+    pub crop_history: Vec<(CropMode, Endpoints)>,
+    /// Selections stepped back from with `undo_crop`, cleared by a new selection
+    pub crop_redo: Vec<(CropMode, Endpoints)>,
+    /// File written by the latest version saved from this window
+    pub saved_path: Option<String>,
+    /// Time and position of the last press in crop mode, to detect double clicks
+    pub last_click: Option<(Instant, Point)>,
+    /// Undone shapes, the most recently undone last
+    pub redo_stack: Vec<Shape>,
+    /// Drags of placed shapes that can be undone, the latest last
+    pub shape_moves: Vec<ShapeMove>,
+    /// Undone drags, redone before shapes undone at a lower count
+    pub redo_moves: Vec<ShapeMove>,
+    /// The capture came back as one color, likely blocked by the system
+    pub blank_capture: bool,
+    /// Bounds of the first target picked with the Measure tool
+    pub measure_from: Option<Rectangle>,
+    /// Number of the latest step marker, the next one gets the one after it
+    pub step_counter: u32,
+    /// Part of the selected shape being dragged
+    pub dragging: Option<ShapeDrag>,
+    /// Part of the manual selection being dragged
+    pub adjusting: Option<SelectionHandle>,
+    /// Swatches from the configured palette file, shown after the presets
+    pub palette: Vec<ShapeColor>,
+    /// Hex code being typed into the color picker, `None` while it is closed
+    pub custom_color: Option<String>,
+    /// Set while the eyedropper waits for a click to pick the color under the cursor
+    pub sampling: bool,
+    /// When a timed capture grabs the screen again, clicks pass through the overlay until then
+    pub countdown: Option<Instant>,
 }
 
+/// Countdown of the Timer button when no capture delay is configured
+const TIMED_CAPTURE_SECS: u32 = 3;
+
 #[derive(Debug, Clone)]
 pub enum CaptureEvent {
-    Undo,
+    UndoAnnotation,
+    Redo,
+    UndoCrop,
+    RedoCrop,
+    RestoreSelection,
     Done,
+    SaveAndContinue,
+    /// Copies the selection with its annotations and closes without saving a file
+    CopyToClipboard,
+    /// Pins the selection with its annotations on top of the screen and closes without saving
+    /// a file
+    PinToScreen,
+    NewPassSameImage,
+    ImportSvg,
+    ExportFocus,
+    ExportReveal,
+    /// The reveal export written off the UI thread, or why it couldn't be
+    RevealExported(Result<String, String>),
+    ExportVector,
+    /// Sent by `Esc`. Drops a shape that is still being drawn, or closes the eyedropper or color
+    /// picker, first. Otherwise draw mode discards every shape and goes back to crop mode, and
+    /// crop mode closes the window without saving
     Cancel,
     ChooseShapeType(ShapeType, bool, bool),
+    /// Switches between crop and draw mode, keeping the selection and every shape
+    ToggleMode,
+    ToggleToolbar,
+    HideToolbar(bool),
+    SelectShape(bool),
+    PickShape,
+    DeleteSelectedShape,
+    /// Moves every shape by the offset, in overlay pixels
+    TranslateAllShapes {
+        dx: f32,
+        dy: f32,
+    },
     ChangeStroke(ShapeStroke),
+    ChangeStrokeStyle(StrokeStyle),
+    ChangeCornerRadius(f32),
+    ChangeOpacity(f32),
+    InsertTextChar(char),
+    EraseTextChar,
+    CommitText,
+    CancelText,
     ChangeColor(ShapeColor),
+    ToggleColorPicker,
+    ToggleEyedropper,
+    EditCustomColor(String),
+    /// Picks the color typed into the color picker, if it is a valid hex code
+    ApplyCustomColor,
+    JumpToHistory(usize),
+    ApplyProfile(usize),
+    ApplyPreset(usize),
+    ChangeSafeArea(SafeArea),
+    ChangeAspectLock(AspectLock),
+    FlipImage(ImageFlip),
+    CropToSelection,
+    /// Attaches a selection of the configured fixed size to the cursor
+    StartFixedPlacement,
     SetInitialPoint,
     UpdateCurrentPosition(Point),
+    ModifiersChanged(Modifiers),
+    /// Wheel up or down over the canvas, steps the stroke or the color with Ctrl
+    ScrollTool(bool),
     SetFinalPoint,
+    CheckTimeout,
+    /// Counts down `secs` over the live screen, then captures it again and saves the selection
+    /// from the new image
+    StartTimedCapture(u32),
+    CountdownTick,
+    /// Sent once the overlay is hidden for the timed capture
+    FinishTimedCapture,
 }
 
 impl CaptureWindow {
     pub fn update(&mut self, id: Id, message: CaptureEvent) -> Task<AppEvent> {
+        if !matches!(message, CaptureEvent::CheckTimeout) {
+            self.last_input = Instant::now();
+        }
+        // Shortcuts still reach the overlay during a timed capture, only cancelling it is handled
+        if self.countdown.is_some()
+            && !matches!(
+                message,
+                CaptureEvent::Cancel
+                    | CaptureEvent::CountdownTick
+                    | CaptureEvent::CheckTimeout
+                    | CaptureEvent::RevealExported(_)
+            )
+        {
+            return Task::none();
+        }
         match message {
-            CaptureEvent::Undo => {
-                if matches!(self.mode, Mode::Draw) {
-                    self.shapes.pop();
-                    self.cache.clear();
-                } else {
-                    self.crop_mode = CropMode::FullScreen;
+            CaptureEvent::UndoAnnotation => {
+                if self.shape.endpoints.is_none() {
+                    self.undo_annotation();
+                }
+            }
+            CaptureEvent::Redo => {
+                if self.shape.endpoints.is_none() {
+                    self.redo_annotation();
+                }
+            }
+            CaptureEvent::UndoCrop => {
+                if matches!(self.mode, Mode::Crop)
+                    && !matches!(self.crop_mode, CropMode::SelectionInProgress)
+                {
+                    self.undo_crop();
+                }
+            }
+            CaptureEvent::RedoCrop => {
+                if matches!(self.mode, Mode::Crop)
+                    && !matches!(self.crop_mode, CropMode::SelectionInProgress)
+                {
+                    self.redo_crop();
+                }
+            }
+            CaptureEvent::RestoreSelection => {
+                if matches!(self.mode, Mode::Crop)
+                    && !matches!(self.crop_mode, CropMode::SelectionInProgress)
+                {
+                    self.restore_selection();
                 }
             }
             CaptureEvent::Done => {
                 if matches!(self.mode, Mode::Draw) {
-                    self.mode = Mode::Crop;
+                    self.shape.endpoints = None;
+                    self.selected_index = None;
+                    self.measure_from = None;
+                    if !self.shapes.is_empty() {
+                        self.mode = Mode::Crop;
+                    } else {
+                        match self.config.on_empty_done {
+                            EmptyDoneAction::ReturnToCrop => {
+                                self.mode = Mode::Crop;
+                                self.mode_desc = String::from("No annotations drawn");
+                            }
+                            EmptyDoneAction::FinishCapture => {
+                                return Task::done(AppEvent::RequestClose(id));
+                            }
+                        }
+                    }
                 } else {
                     return Task::done(AppEvent::RequestClose(id));
                 }
             }
+            CaptureEvent::SaveAndContinue => {
+                return Task::done(AppEvent::SaveCapture(id));
+            }
+            CaptureEvent::CopyToClipboard => {
+                if matches!(self.crop_mode, CropMode::SelectionInProgress) {
+                    return Task::none();
+                }
+                self.commit_text();
+                return match copy_to_clipboard(&self.final_image()) {
+                    Ok(()) => {
+                        self.discard = true;
+                        Task::done(AppEvent::RequestClose(id))
+                    }
+                    Err(error) => Task::done(AppEvent::ShowError(format!(
+                        "Could not copy the capture: {}",
+                        error
+                    ))),
+                };
+            }
+            CaptureEvent::PinToScreen => {
+                if matches!(self.crop_mode, CropMode::SelectionInProgress) {
+                    return Task::none();
+                }
+                self.commit_text();
+                self.discard = true;
+                return Task::done(AppEvent::PinImage(self.final_image(), self.scale_factor))
+                    .chain(Task::done(AppEvent::RequestClose(id)));
+            }
+            CaptureEvent::NewPassSameImage => self.new_pass(),
+            CaptureEvent::ExportFocus => self.save_focus_version(),
+            CaptureEvent::ExportReveal => {
+                self.commit_text();
+                let canvas = self.export_canvas();
+                let shapes = self.shapes.clone();
+                self.mode_desc = String::from("Exporting reveal...");
+                let export = async move {
+                    tokio::task::spawn_blocking(move || save_reveal(&canvas, &shapes))
+                        .await
+                        .unwrap_or_else(|error| Err(error.to_string()))
+                };
+                return Task::perform(export, move |result| {
+                    AppEvent::Capture(id, CaptureEvent::RevealExported(result))
+                });
+            }
+            CaptureEvent::RevealExported(result) => match result {
+                Ok(path) => self.mode_desc = format!("Exported {}", path),
+                Err(error) => {
+                    println!("{}", error);
+                    self.mode_desc = String::from("Could not export the reveal");
+                }
+            },
+            CaptureEvent::ExportVector => {
+                self.commit_text();
+                self.save_vector();
+            }
+            CaptureEvent::ImportSvg => {
+                if let Some(contents) = FileDialog::new()
+                    .add_filter("SVG", &["svg"])
+                    .pick_file()
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+                {
+                    for shape in import_svg(&contents, self.scale_factor) {
+                        self.push_shape(shape);
+                    }
+                }
+            }
+            CaptureEvent::Cancel if self.countdown.is_some() => {
+                self.countdown = None;
+                self.mode_desc = String::from("Timed capture cancelled");
+                return disable_mouse_passthrough(id).chain(gain_focus(id));
+            }
             CaptureEvent::Cancel => {
-                if matches!(self.mode, Mode::Draw) {
+                let in_progress = self.shape.endpoints.is_some()
+                    || self.measure_from.is_some()
+                    || self.sampling
+                    || self.custom_color.is_some();
+                if matches!(self.mode, Mode::Draw) && in_progress {
+                    self.shape.endpoints = None;
+                    self.shape.points.clear();
+                    self.measure_from = None;
+                    self.sampling = false;
+                    self.custom_color = None;
+                } else if matches!(self.mode, Mode::Draw) {
                     self.shapes.clear();
+                    self.redo_stack.clear();
+                    self.shape_moves.clear();
+                    self.redo_moves.clear();
+                    self.step_counter = 0;
+                    self.selected_index = None;
+                    self.measure_from = None;
+                    self.shape.endpoints = None;
                     self.cache.clear();
                     self.mode = Mode::Crop;
                 } else {
+                    self.discard = true;
+                    return Task::done(AppEvent::RequestClose(id));
+                }
+            }
+            CaptureEvent::CheckTimeout => {
+                if self.timeout_remaining() == Some(0) {
+                    return Task::done(AppEvent::Capture(id, CaptureEvent::Cancel));
+                }
+            }
+            CaptureEvent::StartTimedCapture(secs) => {
+                if matches!(self.mode, Mode::Crop)
+                    && matches!(self.crop_mode, CropMode::ManualSelection)
+                    && self.monitor_id.is_some()
+                {
+                    self.countdown = Some(Instant::now() + Duration::from_secs(secs as u64));
+                    return enable_mouse_passthrough(id);
+                }
+            }
+            CaptureEvent::CountdownTick => {
+                if self.countdown_remaining() == Some(0) {
+                    self.countdown = None;
+                    // Gives the compositor a moment to take the overlay off the screen
+                    return change_mode(id, WindowMode::Hidden).chain(Task::perform(
+                        tokio::time::sleep(Duration::from_millis(200)),
+                        move |_| AppEvent::Capture(id, CaptureEvent::FinishTimedCapture),
+                    ));
+                }
+            }
+            CaptureEvent::FinishTimedCapture => {
+                if self.recapture() {
                     return Task::done(AppEvent::RequestClose(id));
                 }
+                self.mode_desc = String::from("Could not capture the screen again");
+                return change_mode(id, WindowMode::Fullscreen)
+                    .chain(disable_mouse_passthrough(id))
+                    .chain(gain_focus(id));
             }
             CaptureEvent::ChooseShapeType(shape_type, is_filled, is_solid) => {
+                self.commit_text();
                 self.shape.endpoints = None;
+                self.measure_from = None;
                 self.mode = Mode::Draw;
+                self.last_tools.insert(
+                    (
+                        self.shape.shape_type,
+                        self.shape.is_filled,
+                        self.shape.is_solid,
+                    ),
+                    (self.shape.stroke_color, self.shape.stroke_width),
+                );
                 self.shape.shape_type = shape_type;
                 self.shape.is_filled = is_filled;
                 self.shape.is_solid = is_solid;
+                let (color, stroke_width) = self
+                    .last_tools
+                    .get(&(shape_type, is_filled, is_solid))
+                    .copied()
+                    .unwrap_or((self.shape.stroke_color, self.shape.stroke_width));
+                self.shape.stroke_width = stroke_width;
+                self.shape.set_color(color, self.config.highlight_opacity);
+            }
+            CaptureEvent::ToggleMode => {
+                // Left alone mid-gesture so nothing half drawn is lost
+                if self.shape.endpoints.is_none()
+                    && self.measure_from.is_none()
+                    && self.dragging.is_none()
+                    && !matches!(self.crop_mode, CropMode::SelectionInProgress)
+                {
+                    self.selected_index = None;
+                    self.mode = match self.mode {
+                        Mode::Crop => Mode::Draw,
+                        Mode::Draw => Mode::Crop,
+                    };
+                }
+            }
+            CaptureEvent::ToggleToolbar => self.toolbar_expanded = !self.toolbar_expanded,
+            CaptureEvent::HideToolbar(hidden) => self.toolbar_hidden = hidden,
+            CaptureEvent::SelectShape(forward) => {
+                if matches!(self.mode, Mode::Draw) && !self.shapes.is_empty() {
+                    let last = self.shapes.len() - 1;
+                    self.selected_index = Some(match (self.selected_index, forward) {
+                        (Some(index), true) if index < last => index + 1,
+                        (Some(index), false) if index > 0 && index <= last => index - 1,
+                        (_, true) => 0,
+                        (_, false) => last,
+                    });
+                }
+            }
+            CaptureEvent::PickShape => {
+                if matches!(self.mode, Mode::Draw) && self.shape.endpoints.is_none() {
+                    self.selected_index = self.shape_at(self.cursor_position);
+                    self.cache.clear();
+                }
+            }
+            CaptureEvent::DeleteSelectedShape => {
+                if let Some(index) = self.selected_index.take() {
+                    if index < self.shapes.len() {
+                        self.shapes.remove(index);
+                        // Later indices shifted down
+                        self.shape_moves.clear();
+                        self.redo_moves.clear();
+                        self.recount_steps();
+                        self.cache.clear();
+                    }
+                }
+            }
+            CaptureEvent::TranslateAllShapes { dx, dy } => {
+                self.translate_shapes(Vector::new(dx, dy))
             }
             CaptureEvent::ChangeStroke(stroke_width) => {
                 self.shape.stroke_width = stroke_width;
+                self.config.default_stroke = stroke_width;
+                self.shape.fit_text();
+                if let Some(shape) = self.selected_shape_mut() {
+                    shape.stroke_width = stroke_width;
+                    shape.fit_text();
+                    self.cache.clear();
+                }
+            }
+            CaptureEvent::ChangeStrokeStyle(stroke_style) => {
+                self.shape.stroke_style = stroke_style;
+                if let Some(shape) = self.selected_shape_mut() {
+                    shape.stroke_style = stroke_style;
+                    self.cache.clear();
+                }
+            }
+            CaptureEvent::ChangeCornerRadius(radius) => {
+                self.shape.corner_radius = radius;
+                if let Some(shape) = self.selected_shape_mut() {
+                    shape.corner_radius = radius;
+                    self.cache.clear();
+                }
+            }
+            CaptureEvent::ChangeOpacity(alpha) => {
+                self.shape.alpha = alpha.clamp(0.1, 1.0);
+                if let Some(shape) = self.selected_shape_mut() {
+                    shape.alpha = alpha.clamp(0.1, 1.0);
+                    self.cache.clear();
+                }
+            }
+            CaptureEvent::InsertTextChar(c) => {
+                if self.is_typing() {
+                    self.shape.text.push(c);
+                    self.shape.fit_text();
+                }
+            }
+            CaptureEvent::EraseTextChar => {
+                if self.is_typing() {
+                    self.shape.text.pop();
+                    self.shape.fit_text();
+                }
+            }
+            CaptureEvent::CommitText => self.commit_text(),
+            CaptureEvent::CancelText => {
+                if self.is_typing() {
+                    self.shape.endpoints = None;
+                    self.shape.text.clear();
+                }
             }
             CaptureEvent::ChangeColor(color) => {
-                self.shape.color = color;
+                let opacity = self.config.highlight_opacity;
+                self.shape.set_color(color.rgba(), opacity);
+                self.config.default_color = color;
+                if let Some(shape) = self.selected_shape_mut() {
+                    shape.set_color(color.rgba(), opacity);
+                    self.cache.clear();
+                }
+            }
+            CaptureEvent::ToggleColorPicker => {
+                self.custom_color = match self.custom_color {
+                    Some(_) => None,
+                    None => Some(self.shape.stroke_color.to_hex()),
+                };
+            }
+            CaptureEvent::ToggleEyedropper => self.sampling = !self.sampling,
+            CaptureEvent::EditCustomColor(hex) => self.custom_color = Some(hex),
+            CaptureEvent::ApplyCustomColor => {
+                if let Some(color) = self.custom_color.as_deref().and_then(RgbaColor::from_hex) {
+                    self.custom_color = None;
+                    return self.update(id, CaptureEvent::ChangeColor(ShapeColor::Custom(color)));
+                }
+            }
+            CaptureEvent::JumpToHistory(index) => {
+                self.selected_index = None;
+                if index < self.shapes.len() {
+                    self.redo_stack.extend(self.shapes.drain(index..).rev());
+                    self.shape_moves
+                        .retain(|shape_move| shape_move.count <= index);
+                    self.redo_moves.clear();
+                    self.recount_steps();
+                }
+                self.cache.clear();
+            }
+            CaptureEvent::ApplyProfile(index) => self.apply_profile(index),
+            CaptureEvent::ApplyPreset(index) => self.apply_preset(index),
+            CaptureEvent::ChangeSafeArea(safe_area) => self.safe_area = safe_area,
+            CaptureEvent::ChangeAspectLock(aspect_lock) => self.aspect_lock = aspect_lock,
+            CaptureEvent::FlipImage(flip) => self.flip_image(flip),
+            CaptureEvent::CropToSelection => self.crop_to_selection(),
+            CaptureEvent::StartFixedPlacement => {
+                if matches!(self.mode, Mode::Crop) {
+                    self.remember_selection();
+                    self.crop_mode = CropMode::FixedPlacement;
+                    self.place_fixed_selection();
+                }
             }
             CaptureEvent::SetInitialPoint => match self.mode {
+                // The tool is left as it was, so the next press draws with the picked color
+                Mode::Draw if self.sampling => {
+                    self.sampling = false;
+                    if let Some(color) = self.pixel_at(self.cursor_position) {
+                        return self
+                            .update(id, CaptureEvent::ChangeColor(ShapeColor::Custom(color)));
+                    }
+                }
+                Mode::Draw if self.shape.shape_type == ShapeType::Measure => {
+                    self.place_measure_target()
+                }
+                // Measuring picks shapes as targets instead of editing them
+                Mode::Draw
+                    if self.shape.shape_type != ShapeType::Measure
+                        && self.grab_at(self.cursor_position).is_some() =>
+                {
+                    self.start_shape_drag()
+                }
+                Mode::Draw if self.shape.shape_type == ShapeType::StepMarker => {
+                    self.selected_index = None;
+                    self.place_step_marker()
+                }
                 Mode::Draw => {
+                    self.selected_index = None;
+                    self.commit_text();
+                    self.shape.text.clear();
+                    self.shape.points.clear();
+                    self.draw_origin = self.cursor_position;
                     self.shape.endpoints = Some(Endpoints {
                         initial_pt: self.cursor_position,
                         final_pt: self.cursor_position,
-                    })
+                    });
+                    if self.shape.shape_type == ShapeType::Freehand {
+                        self.shape.push_point(self.cursor_position);
+                    }
+                }
+                Mode::Crop if matches!(self.crop_mode, CropMode::FixedPlacement) => {
+                    self.crop_mode = CropMode::ManualSelection;
+                }
+                Mode::Crop if self.selection_handle_at(self.cursor_position).is_some() => {
+                    self.start_selection_adjust()
                 }
                 Mode::Crop => {
+                    const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+                    let now = Instant::now();
+                    let is_double_click = self.config.double_click_window
+                        && self.last_click.is_some_and(|(time, position)| {
+                            now.duration_since(time) < DOUBLE_CLICK
+                                && position.distance(self.cursor_position) < 4.0
+                        });
+                    self.last_click = (!is_double_click).then_some((now, self.cursor_position));
+                    if is_double_click {
+                        if let Some((window_id, name, endpoints)) =
+                            self.window_at(self.cursor_position)
+                        {
+                            self.endpoints = endpoints;
+                            self.crop_mode = CropMode::SpecificWindow(window_id);
+                            self.mode_desc = name;
+                            return Task::done(AppEvent::RequestClose(id));
+                        }
+                    }
+                    self.remember_selection();
                     self.crop_mode = CropMode::SelectionInProgress;
-                    self.endpoints.initial_pt = self.cursor_position;
-                    self.endpoints.final_pt = self.cursor_position;
+                    self.endpoints.initial_pt =
+                        self.snap_to_windows(self.snap_to_grid(self.cursor_position));
+                    self.endpoints.final_pt = self.endpoints.initial_pt;
                 }
             },
             CaptureEvent::UpdateCurrentPosition(final_pt) => {
                 self.cursor_position = final_pt;
-                if matches!(self.mode, Mode::Draw) {
-                    if let Some(ref mut endpoints) = self.shape.endpoints {
-                        endpoints.final_pt = final_pt;
-                    }
+                if self.dragging.is_some() {
+                    self.drag_selected_shape();
+                } else if matches!(self.mode, Mode::Draw) {
+                    self.update_drawn_shape();
+                } else if self.adjusting.is_some() {
+                    self.adjust_selection();
                 } else if matches!(
                     self.crop_mode,
                     CropMode::FullScreen | CropMode::SpecificWindow(_)
                 ) {
-                    let window = self.windows.iter().find_map(|(id, window)| {
-                        let top_left = (window.x as f32, window.y as f32);
-                        let bottom_right = (
-                            (window.x + window.width as i32) as f32,
-                            (window.y + window.height as i32) as f32,
-                        );
-                        if (top_left.0..bottom_right.0).contains(&(self.cursor_position.x))
-                            && (top_left.1..bottom_right.1).contains(&(self.cursor_position.y))
-                        {
-                            Some((id, window.name.clone(), top_left, bottom_right))
-                        } else {
-                            None
-                        }
-                    });
-                    if let Some((id, name, top_left, bottom_right)) = window {
-                        self.endpoints.initial_pt = Point::new(top_left.0, top_left.1);
-                        self.endpoints.final_pt = Point::new(bottom_right.0, bottom_right.1);
-                        self.crop_mode = CropMode::SpecificWindow(*id);
+                    if let Some((id, name, endpoints)) = self.window_at(self.cursor_position) {
+                        self.endpoints = endpoints;
+                        self.crop_mode = CropMode::SpecificWindow(id);
                         self.mode_desc = name;
                     } else {
                         self.crop_mode = CropMode::FullScreen;
                         self.mode_desc = String::from("FullScreen");
                     }
+                } else if matches!(self.crop_mode, CropMode::FixedPlacement) {
+                    self.place_fixed_selection();
                 } else if matches!(self.crop_mode, CropMode::SelectionInProgress) {
-                    self.endpoints.final_pt = final_pt;
-                    let (initial_pt, final_pt) = self.endpoints.normalize();
-                    let size = final_pt - initial_pt;
-                    self.mode_desc = format!("{} x {}", size.x as u32, size.y as u32);
+                    self.endpoints.final_pt = self.aspect_lock.constrain(
+                        self.endpoints.initial_pt,
+                        self.snap_to_windows(self.snap_to_grid(final_pt)),
+                    );
+                    // In image pixels, rounded like the crop so it matches the saved size
+                    let (_, _, width, height) = self.selection_rect();
+                    self.mode_desc = format!("{} x {}", width, height);
                 }
             }
+            CaptureEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                if matches!(self.mode, Mode::Draw) {
+                    self.update_drawn_shape();
+                }
+            }
+            CaptureEvent::ScrollTool(up) => {
+                let message = if self.modifiers.control() {
+                    let color = self
+                        .shape
+                        .stroke_color
+                        .preset()
+                        .map_or(ShapeColor::default(), |color| color.step(up));
+                    CaptureEvent::ChangeColor(color)
+                } else {
+                    CaptureEvent::ChangeStroke(self.shape.stroke_width.step(up))
+                };
+                return self.update(id, message);
+            }
             CaptureEvent::SetFinalPoint => {
                 match self.mode {
+                    Mode::Draw if self.dragging.is_some() => self.finish_shape_drag(),
+                    // Text boxes stay open for typing until committed, measurements until
+                    // their second target is clicked
+                    Mode::Draw
+                        if matches!(
+                            self.shape.shape_type,
+                            ShapeType::Text | ShapeType::Measure
+                        ) => {}
                     Mode::Draw => {
-                        if self.shape.endpoints.is_some() {
-                            self.shapes.push(self.shape);
-                            self.cache.clear();
+                        if let Some(endpoints) = self.shape.endpoints {
+                            let mut shape = self.shape.clone();
+                            if shape.shape_type == ShapeType::Blur {
+                                shape.redaction = self.redact(endpoints, shape.stroke_width);
+                            }
+                            if shape.shape_type != ShapeType::Blur || shape.redaction.is_some() {
+                                self.push_shape(shape);
+                            }
                         }
                         self.shape.endpoints = None
                     }
-                    Mode::Crop => {
-                        if self.endpoints.initial_pt != self.cursor_position {
-                            self.endpoints.final_pt = self.cursor_position;
-                            self.crop_mode = CropMode::ManualSelection
-                        } else {
+                    Mode::Crop if self.adjusting.is_some() => {
+                        self.adjusting = None;
+                        let (top_left, bottom_right) = self.endpoints.normalize();
+                        self.endpoints = Endpoints {
+                            initial_pt: top_left,
+                            final_pt: bottom_right,
+                        };
+                        self.config.last_selection = Some(self.selection_rect());
+                    }
+                    Mode::Crop if matches!(self.crop_mode, CropMode::SelectionInProgress) => {
+                        let final_pt = self.aspect_lock.constrain(
+                            self.endpoints.initial_pt,
+                            self.snap_to_windows(self.snap_to_grid(self.cursor_position)),
+                        );
+                        if self.endpoints.initial_pt != final_pt {
+                            self.endpoints.final_pt = final_pt;
+                            self.crop_mode = CropMode::ManualSelection;
+                            self.config.last_selection = Some(self.selection_rect());
+                        } else if !(self.config.detect_element && self.suggest_element()) {
                             self.crop_mode = CropMode::FullScreen;
                         }
                     }
+                    // The press finished the capture with a double click
+                    Mode::Crop => (),
                 }
                 return Task::none();
             }
@@ -176,29 +745,60 @@ impl CaptureWindow {
         let background = Image::new(Handle::from_rgba(
             self.image.width(),
             self.image.height(),
-            self.image.clone().into_raw(),
+            self.redacted_image().into_raw(),
         ))
         .height(Fill)
         .width(Fill);
 
-        const CONTAINER: u16 = 10;
-        const ROW: u16 = 10;
-        const TEXT: u16 = 24;
-        const SQUARE: u16 = 44;
+        const HISTORY: usize = 8;
+        // Below this overlay width the drawing tools collapse behind a single button
+        const COLLAPSE_WIDTH: f32 = 1500.0;
 
-        let panel = |row| {
-            container(row)
+        let ui_scale = self.config.ui_scale.clamp(0.75, 2.0);
+        let scaled = |size: f32| (size * ui_scale).round() as u16;
+        let (padding, spacing, text_size, square, small_text) = (
+            scaled(10.0),
+            scaled(10.0),
+            scaled(24.0),
+            scaled(44.0),
+            scaled(16.0),
+        );
+
+        // Nothing but the countdown covers the live screen
+        if let Some(secs) = self.countdown_remaining() {
+            return container(
+                column![
+                    text(secs.to_string()).size(scaled(160.0)),
+                    text("Esc or Alt+Shift+S to cancel").size(text_size)
+                ]
                 .align_x(Center)
-                .align_y(Center)
-                .padding(CONTAINER)
+                .spacing(spacing),
+            )
+            .padding(padding)
+            .center(Fill)
+            .into();
+        }
+
+        let collapsed = (self.image.width() as f32 / self.scale_factor) < COLLAPSE_WIDTH * ui_scale;
+        let mut tools: Vec<Element<CaptureEvent>> = Vec::new();
+
+        // Panels block the canvas across their whole bounds, not just over their buttons
+        let panel = |row| {
+            mouse_area(
+                container(row)
+                    .align_x(Center)
+                    .align_y(Center)
+                    .padding(padding),
+            )
+            .interaction(Interaction::Idle)
         };
 
-        let mut toolbar = row![].spacing(12);
+        let mut toolbar = row![].spacing(scaled(12.0));
 
         toolbar = toolbar.push(horizontal_space().width(Fill));
 
-        let shapes_icon = |utf, shape_type, is_filled, is_solid| {
-            let button_class = if matches!(self.mode, Mode::Draw)
+        let shape_class = |shape_type, is_filled, is_solid| {
+            if matches!(self.mode, Mode::Draw)
                 && self.shape.shape_type == shape_type
                 && self.shape.is_filled == is_filled
                 && self.shape.is_solid == is_solid
@@ -206,15 +806,28 @@ impl CaptureWindow {
                 ButtonClass::Selected
             } else {
                 ButtonClass::Default
-            };
+            }
+        };
 
-            button(text(utf).font(ICON).size(TEXT).center())
+        let shapes_icon = |utf, shape_type, is_filled, is_solid| {
+            button(text(utf).font(ICON).size(text_size).center())
                 .on_press(CaptureEvent::ChooseShapeType(
                     shape_type, is_filled, is_solid,
                 ))
-                .height(SQUARE)
-                .width(SQUARE)
-                .class(button_class)
+                .height(square)
+                .width(square)
+                .class(shape_class(shape_type, is_filled, is_solid))
+        };
+
+        // Tools without a glyph in the icon font
+        let shapes_text = |label, shape_type, is_filled, is_solid| {
+            button(text(label).size(small_text).center())
+                .on_press(CaptureEvent::ChooseShapeType(
+                    shape_type, is_filled, is_solid,
+                ))
+                .height(square)
+                .width(square)
+                .class(shape_class(shape_type, is_filled, is_solid))
         };
 
         let row = row![
@@ -224,11 +837,115 @@ impl CaptureWindow {
             shapes_icon(ELLIPSE_STROKE, ShapeType::Ellipse, false, true),
             shapes_icon(LINE, ShapeType::Line, false, true),
             shapes_icon(ARROW, ShapeType::Arrow, false, true),
-            shapes_icon(HIGHLIGHT, ShapeType::Rectangle, true, false)
+            shapes_icon(HIGHLIGHT, ShapeType::Rectangle, true, false),
+            shapes_text("Spot", ShapeType::Spotlight, true, true),
+            shapes_text("Text", ShapeType::Text, false, true),
+            shapes_text("Pen", ShapeType::Freehand, false, true),
+            shapes_text("Measure", ShapeType::Measure, false, true),
+            shapes_text("Steps", ShapeType::StepMarker, true, true),
+            shapes_text("Blur", ShapeType::Blur, true, true)
         ];
-        let shapes = panel(row.spacing(ROW));
+        let shapes = panel(row.spacing(spacing));
+
+        if collapsed {
+            let button_class = if self.toolbar_expanded {
+                ButtonClass::Selected
+            } else {
+                ButtonClass::Default
+            };
+            toolbar = toolbar.push(panel(row![button(text("Tools").size(text_size).center())
+                .on_press(CaptureEvent::ToggleToolbar)
+                .height(square)
+                .class(button_class)]));
+            tools.push(shapes.into());
+        } else {
+            toolbar = toolbar.push(shapes);
+        }
 
-        toolbar = toolbar.push(shapes);
+        toolbar = toolbar.push(panel(row![button(
+            text(match self.mode {
+                Mode::Crop => "Draw",
+                Mode::Draw => "Crop",
+            })
+            .size(text_size)
+            .center()
+        )
+        .on_press(CaptureEvent::ToggleMode)
+        .height(square)]));
+
+        if matches!(self.mode, Mode::Crop) {
+            let mut crop_tools = row![
+                button(text(self.safe_area.to_string()).size(text_size).center())
+                    .on_press(CaptureEvent::ChangeSafeArea(self.safe_area.next()))
+                    .height(square),
+                button(text(self.aspect_lock.to_string()).size(text_size).center())
+                    .on_press(CaptureEvent::ChangeAspectLock(self.aspect_lock.next()))
+                    .height(square)
+                    .class(if self.aspect_lock == AspectLock::Free {
+                        ButtonClass::Default
+                    } else {
+                        ButtonClass::Selected
+                    }),
+                button(text("Mirror").size(text_size).center())
+                    .on_press(CaptureEvent::FlipImage(ImageFlip::Horizontal))
+                    .height(square),
+                button(text("Flip").size(text_size).center())
+                    .on_press(CaptureEvent::FlipImage(ImageFlip::Vertical))
+                    .height(square),
+                button(
+                    text(format!("Fixed {}", self.config.fixed_size))
+                        .size(text_size)
+                        .center()
+                )
+                .on_press(CaptureEvent::StartFixedPlacement)
+                .height(square)
+            ]
+            .spacing(spacing);
+            if self.config.last_selection.is_some() {
+                crop_tools = crop_tools.push(
+                    button(text("Last").size(text_size).center())
+                        .on_press(CaptureEvent::RestoreSelection)
+                        .height(square),
+                );
+            }
+            if !matches!(self.crop_mode, CropMode::FullScreen) {
+                crop_tools = crop_tools
+                    .push(
+                        button(text("Crop In").size(text_size).center())
+                            .on_press(CaptureEvent::CropToSelection)
+                            .height(square),
+                    )
+                    .push(
+                        button(text("Focus").size(text_size).center())
+                            .on_press(CaptureEvent::ExportFocus)
+                            .height(square),
+                    );
+            }
+            if matches!(self.crop_mode, CropMode::ManualSelection) && self.monitor_id.is_some() {
+                let secs = match self.config.delay_secs {
+                    0 => TIMED_CAPTURE_SECS,
+                    secs => secs,
+                };
+                crop_tools = crop_tools.push(
+                    button(text(format!("Timer {} s", secs)).size(text_size).center())
+                        .on_press(CaptureEvent::StartTimedCapture(secs))
+                        .height(square),
+                );
+            }
+            toolbar = toolbar.push(panel(crop_tools));
+        }
+
+        if matches!(self.mode, Mode::Crop) && !self.config.profiles.is_empty() {
+            let mut profiles = row![].spacing(spacing);
+            for (index, profile) in self.config.profiles.iter().enumerate() {
+                profiles = profiles.push(
+                    button(text(&profile.name).size(text_size).center())
+                        .on_press(CaptureEvent::ApplyProfile(index))
+                        .height(square),
+                );
+            }
+            toolbar = toolbar.push(panel(profiles));
+        }
 
         if matches!(self.mode, Mode::Draw) {
             if !self.shape.is_filled {
@@ -239,24 +956,74 @@ impl CaptureWindow {
                         ButtonClass::Default
                     };
 
-                    button(text(utf).font(ICON).size(TEXT).center())
+                    button(text(utf).font(ICON).size(text_size).center())
                         .on_press(CaptureEvent::ChangeStroke(stroke))
-                        .height(SQUARE)
-                        .width(SQUARE)
+                        .height(square)
+                        .width(square)
                         .class(button_class)
                 };
-                toolbar = toolbar.push(panel(
+                tools.push(
+                    panel(
+                        row![
+                            stroke_icon(STROKE_THIN, ShapeStroke::Thin),
+                            stroke_icon(STROKE_MEDIUM, ShapeStroke::Medium),
+                            stroke_icon(STROKE_BROAD, ShapeStroke::Broad),
+                            button(
+                                text(self.shape.stroke_style.to_string())
+                                    .size(text_size)
+                                    .center()
+                            )
+                            .on_press(CaptureEvent::ChangeStrokeStyle(
+                                self.shape.stroke_style.next()
+                            ))
+                            .height(square)
+                        ]
+                        .spacing(spacing),
+                    )
+                    .into(),
+                )
+            };
+
+            if self.shape.shape_type == ShapeType::Rectangle {
+                let next_radius = CORNER_RADII
+                    .into_iter()
+                    .find(|radius| *radius > self.shape.corner_radius)
+                    .unwrap_or(CORNER_RADII[0]);
+                tools.push(
+                    panel(row![button(
+                        text(if self.shape.corner_radius > 0.0 {
+                            format!("Corners: {} px", self.shape.corner_radius)
+                        } else {
+                            String::from("Corners: Sharp")
+                        })
+                        .size(text_size)
+                        .center()
+                    )
+                    .on_press(CaptureEvent::ChangeCornerRadius(next_radius))
+                    .height(square)])
+                    .into(),
+                );
+            }
+
+            tools.push(
+                panel(
                     row![
-                        stroke_icon(STROKE_THIN, ShapeStroke::Thin),
-                        stroke_icon(STROKE_MEDIUM, ShapeStroke::Medium),
-                        stroke_icon(STROKE_BROAD, ShapeStroke::Broad)
+                        slider(0.1..=1.0, self.shape.alpha, CaptureEvent::ChangeOpacity)
+                            .step(0.05)
+                            .width(scaled(120.0)),
+                        text(format!("{} %", (self.shape.alpha * 100.0).round()))
+                            .size(text_size)
+                            .center()
+                            .width(scaled(70.0))
                     ]
-                    .spacing(ROW),
-                ))
-            };
+                    .align_y(Center)
+                    .spacing(spacing),
+                )
+                .into(),
+            );
 
             let color_icon = |color: ShapeColor| {
-                let button_class = if self.shape.color == color {
+                let button_class = if self.shape.stroke_color == color.rgba() {
                     ButtonClass::Selected
                 } else {
                     ButtonClass::Default
@@ -265,46 +1032,276 @@ impl CaptureWindow {
                 button(
                     text(RECT_FILLED)
                         .font(ICON)
-                        .size(TEXT)
+                        .size(text_size)
                         .center()
-                        .class(TextClass::Custom(color.into_iced_color(true))),
+                        .class(TextClass::Custom(color.rgba().into_iced())),
                 )
                 .on_press(CaptureEvent::ChangeColor(color))
-                .height(SQUARE)
-                .width(SQUARE)
+                .height(square)
+                .width(square)
                 .class(button_class)
             };
 
-            toolbar = toolbar.push(panel(
+            // Selected for colors that aren't one of the swatches
+            let custom = self.custom_color.is_some()
+                || ShapeColor::ALL
+                    .iter()
+                    .chain(self.palette.iter())
+                    .all(|color| color.rgba() != self.shape.stroke_color);
+            tools.push(
+                panel(
+                    row![
+                        color_icon(ShapeColor::Red),
+                        color_icon(ShapeColor::Green),
+                        color_icon(ShapeColor::Blue),
+                        color_icon(ShapeColor::Yellow),
+                        color_icon(ShapeColor::Black),
+                        color_icon(ShapeColor::White),
+                        button(
+                            text(RECT_FILLED).font(ICON).size(text_size).center().class(
+                                TextClass::Custom(
+                                    self.pixel_at(self.cursor_position)
+                                        .filter(|_| self.sampling)
+                                        .unwrap_or(self.shape.stroke_color)
+                                        .into_iced()
+                                )
+                            )
+                        )
+                        .on_press(CaptureEvent::ToggleEyedropper)
+                        .height(square)
+                        .width(square)
+                        .class(if self.sampling {
+                            ButtonClass::Selected
+                        } else {
+                            ButtonClass::Default
+                        }),
+                        button(text("#").size(text_size).center())
+                            .on_press(CaptureEvent::ToggleColorPicker)
+                            .height(square)
+                            .width(square)
+                            .class(if custom {
+                                ButtonClass::Selected
+                            } else {
+                                ButtonClass::Default
+                            })
+                    ]
+                    .spacing(spacing),
+                )
+                .into(),
+            );
+
+            if let Some(hex) = &self.custom_color {
+                let color = RgbaColor::from_hex(hex);
+                tools.push(
+                    panel(
+                        row![
+                            text_input("#rrggbbaa", hex)
+                                .on_input(CaptureEvent::EditCustomColor)
+                                .on_submit(CaptureEvent::ApplyCustomColor)
+                                .size(text_size)
+                                .width(scaled(160.0)),
+                            text(RECT_FILLED).font(ICON).size(text_size).center().class(
+                                TextClass::Custom(
+                                    color.unwrap_or(self.shape.stroke_color).into_iced()
+                                )
+                            ),
+                            button(text("Set").size(text_size).center())
+                                .on_press_maybe(color.map(|_| CaptureEvent::ApplyCustomColor))
+                                .height(square)
+                        ]
+                        .align_y(Center)
+                        .spacing(spacing),
+                    )
+                    .into(),
+                );
+            }
+
+            if !self.palette.is_empty() {
+                tools.push(
+                    panel(
+                        Row::with_children(
+                            self.palette.iter().map(|color| color_icon(*color).into()),
+                        )
+                        .spacing(spacing),
+                    )
+                    .into(),
+                )
+            }
+        };
+
+        let expanded_tools = if collapsed {
+            self.toolbar_expanded.then(|| {
                 row![
-                    color_icon(ShapeColor::Red),
-                    color_icon(ShapeColor::Green),
-                    color_icon(ShapeColor::Blue),
-                    color_icon(ShapeColor::Yellow),
-                    color_icon(ShapeColor::Black),
-                    color_icon(ShapeColor::White)
+                    horizontal_space().width(Fill),
+                    Column::with_children(tools).align_x(Center),
+                    horizontal_space().width(Fill)
                 ]
-                .spacing(ROW),
-            ))
+            })
+        } else {
+            for tool in tools {
+                toolbar = toolbar.push(tool);
+            }
+            None
         };
 
+        if matches!(self.mode, Mode::Draw)
+            && !(self.redo_stack.is_empty() && self.redo_moves.is_empty())
+        {
+            toolbar = toolbar.push(panel(row![button(text("Redo").size(text_size).center())
+                .on_press(CaptureEvent::Redo)
+                .height(square)]));
+        }
+
+        let mut file_actions = row![
+            button(text("Import").size(text_size).center())
+                .on_press(CaptureEvent::ImportSvg)
+                .height(square),
+            button(text("Save").size(text_size).center())
+                .on_press(CaptureEvent::SaveAndContinue)
+                .height(square),
+            button(text("Copy").size(text_size).center())
+                .on_press(CaptureEvent::CopyToClipboard)
+                .height(square),
+            button(text("Pin").size(text_size).center())
+                .on_press(CaptureEvent::PinToScreen)
+                .height(square)
+        ]
+        .spacing(spacing);
+        if self.version > 0 {
+            file_actions = file_actions.push(
+                button(text("New Pass").size(text_size).center())
+                    .on_press(CaptureEvent::NewPassSameImage)
+                    .height(square),
+            );
+        }
+        if !self.shapes.is_empty() && self.config.on_done != DoneAction::CopyClipboard {
+            file_actions = file_actions.push(
+                button(text("Reveal").size(text_size).center())
+                    .on_press(CaptureEvent::ExportReveal)
+                    .height(square),
+            );
+        }
+        if !self.shapes.is_empty() {
+            file_actions = file_actions.push(
+                button(
+                    text(self.config.vector_format.to_string())
+                        .size(text_size)
+                        .center(),
+                )
+                .on_press(CaptureEvent::ExportVector)
+                .height(square),
+            );
+        }
+        toolbar = toolbar.push(panel(file_actions));
+
         toolbar = toolbar.push(horizontal_space().width(Fill));
 
+        let presets = (!self.config.presets.is_empty()).then(|| {
+            let mut presets = row![].spacing(spacing);
+            for (index, preset) in self.config.presets.iter().enumerate() {
+                presets = presets.push(
+                    button(text(&preset.name).size(text_size).center())
+                        .on_press(CaptureEvent::ApplyPreset(index))
+                        .height(square),
+                );
+            }
+            row![
+                horizontal_space().width(Fill),
+                panel(presets),
+                horizontal_space().width(Fill)
+            ]
+        });
+
         let mut overlay = column![vertical_space().height(5)];
 
-        if matches!(self.crop_mode, CropMode::SelectionInProgress) || self.shape.endpoints.is_none()
+        if (matches!(self.crop_mode, CropMode::SelectionInProgress)
+            || self.shape.endpoints.is_none())
+            && !self.toolbar_hidden
         {
             overlay = overlay.push(toolbar);
+            if let Some(expanded_tools) = expanded_tools {
+                overlay = overlay.push(expanded_tools);
+            }
+            if let Some(presets) = presets {
+                overlay = overlay.push(presets);
+            }
         };
 
+        if matches!(self.mode, Mode::Draw)
+            && !self.shapes.is_empty()
+            && self.shape.endpoints.is_none()
+            && !self.toolbar_hidden
+        {
+            let history_item = |label: String, index: usize| {
+                let button_class = if index == self.shapes.len() {
+                    ButtonClass::Selected
+                } else {
+                    ButtonClass::Default
+                };
+
+                button(text(label).size(small_text))
+                    .on_press(CaptureEvent::JumpToHistory(index))
+                    .width(Fill)
+                    .class(button_class)
+            };
+
+            let mut history = column![history_item(String::from("Start"), 0)].spacing(spacing);
+            let start = self.shapes.len().saturating_sub(HISTORY);
+            for (index, shape) in self.shapes.iter().enumerate().skip(start) {
+                history = history.push(history_item(shape.label(), index + 1));
+            }
+
+            overlay = overlay.push(
+                row![container(history).padding(padding).width(scaled(220.0))].padding(padding),
+            );
+        }
+
         overlay = overlay.push(vertical_space().height(Fill));
-        if matches!(self.mode, Mode::Crop) {
+        if matches!(self.mode, Mode::Crop) && self.config.pixel_readout {
+            if let Some(color) = self.pixel_rgba_at(self.cursor_position) {
+                overlay = overlay.push(row![
+                    container(
+                        row![
+                            text(RECT_FILLED)
+                                .font(ICON)
+                                .size(text_size)
+                                .class(TextClass::Custom(color.with_alpha(255).into_iced())),
+                            text(format!(
+                                "{}  RGBA {}, {}, {}, {}",
+                                color.to_hex(),
+                                color.r,
+                                color.g,
+                                color.b,
+                                color.a
+                            ))
+                            .size(text_size)
+                        ]
+                        .align_y(Center)
+                        .spacing(spacing)
+                    )
+                    .padding(padding),
+                    horizontal_space().width(Fill)
+                ]);
+            }
+        }
+        // The size is drawn next to the selection instead while it is dragged
+        let attached_readout = self.config.readout == ReadoutPlacement::Selection
+            && matches!(self.crop_mode, CropMode::SelectionInProgress);
+        if matches!(self.mode, Mode::Crop) && !attached_readout {
             overlay = overlay.push(row![
                 horizontal_space().width(Fill),
-                container(text(self.mode_desc.clone()).size(TEXT).center())
-                    .align_x(Center)
-                    .align_y(Center)
-                    .padding(CONTAINER),
+                container(
+                    text(match self.timeout_remaining() {
+                        Some(secs) if secs <= 5 => format!("Closing in {} s", secs),
+                        _ if self.blank_capture => format!("{}\n{}", BLANK_CAPTURE, self.mode_desc),
+                        _ => self.mode_desc.clone(),
+                    })
+                    .size(text_size)
+                    .center()
+                )
+                .align_x(Center)
+                .align_y(Center)
+                .padding(padding),
                 horizontal_space().width(Fill)
             ]);
         }
@@ -1,18 +1,40 @@
-use iced::Point;
+use std::fmt::Display;
+
+use iced::{Point, Rectangle, Size};
+use serde::{Deserialize, Serialize};
+use tiny_skia::Transform;
 use xcap::image::RgbaImage;
 
-#[derive(Debug, Default)]
+use super::drawable::{text_size, VectorPath};
+use crate::config::Config;
+
+#[derive(Debug, Default, Clone, Copy)]
 pub enum CropMode {
     #[default]
     FullScreen,
     SpecificWindow(u32),
     SelectionInProgress,
     ManualSelection,
+    /// Selection of the configured fixed size following the cursor until a click places it
+    FixedPlacement,
+}
+
+/// Background of an export and what draws shapes onto it, owned so it can be rendered off the
+/// UI thread
+#[derive(Debug, Clone)]
+pub struct ExportCanvas {
+    /// Redacted selection, already resampled by the export scale
+    pub base: RgbaImage,
+    /// From overlay coordinates to pixels of `base`
+    pub transform: Transform,
+    pub watermark: Option<RgbaImage>,
+    pub config: Config,
 }
 
 #[derive(Debug)]
 pub struct CapturedWindow {
     pub name: String,
+    pub title: String,
     pub x: i32,
     pub y: i32,
     pub width: u32,
@@ -27,6 +49,125 @@ pub enum Mode {
     Crop,
 }
 
+/// Aspect ratio guide drawn inside the selection, never exported
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SafeArea {
+    #[default]
+    Off,
+    Widescreen,
+    Square,
+    Portrait,
+}
+
+impl SafeArea {
+    pub fn ratio(&self) -> Option<f32> {
+        match self {
+            SafeArea::Off => None,
+            SafeArea::Widescreen => Some(16.0 / 9.0),
+            SafeArea::Square => Some(1.0),
+            SafeArea::Portrait => Some(9.0 / 16.0),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SafeArea::Off => SafeArea::Widescreen,
+            SafeArea::Widescreen => SafeArea::Square,
+            SafeArea::Square => SafeArea::Portrait,
+            SafeArea::Portrait => SafeArea::Off,
+        }
+    }
+
+    /// Largest rectangle of the guide's ratio centered inside `top_left`..`bottom_right`
+    pub fn fit(&self, top_left: Point, bottom_right: Point) -> Option<(Point, Size)> {
+        let ratio = self.ratio()?;
+        let (width, height) = (bottom_right.x - top_left.x, bottom_right.y - top_left.y);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let size = if width / height > ratio {
+            Size::new(height * ratio, height)
+        } else {
+            Size::new(width, width / ratio)
+        };
+        let origin = Point::new(
+            top_left.x + (width - size.width) / 2.0,
+            top_left.y + (height - size.height) / 2.0,
+        );
+        Some((origin, size))
+    }
+}
+
+impl Display for SafeArea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => write!(f, "Guide: Off"),
+            Self::Widescreen => write!(f, "Guide: 16:9"),
+            Self::Square => write!(f, "Guide: 1:1"),
+            Self::Portrait => write!(f, "Guide: 9:16"),
+        }
+    }
+}
+
+/// Aspect ratio a manual selection is held to while it is drawn
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum AspectLock {
+    #[default]
+    Free,
+    Widescreen,
+    Standard,
+    Square,
+}
+
+impl AspectLock {
+    pub fn ratio(&self) -> Option<f32> {
+        match self {
+            AspectLock::Free => None,
+            AspectLock::Widescreen => Some(16.0 / 9.0),
+            AspectLock::Standard => Some(4.0 / 3.0),
+            AspectLock::Square => Some(1.0),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            AspectLock::Free => AspectLock::Widescreen,
+            AspectLock::Widescreen => AspectLock::Standard,
+            AspectLock::Standard => AspectLock::Square,
+            AspectLock::Square => AspectLock::Free,
+        }
+    }
+
+    /// Moves `final_pt` towards `initial_pt` until the rectangle between them has the ratio,
+    /// keeping the direction it was dragged in
+    pub fn constrain(&self, initial_pt: Point, final_pt: Point) -> Point {
+        let Some(ratio) = self.ratio() else {
+            return final_pt;
+        };
+        let (dx, dy) = (final_pt.x - initial_pt.x, final_pt.y - initial_pt.y);
+        let (width, height) = if dx.abs() > dy.abs() * ratio {
+            (dy.abs() * ratio, dy.abs())
+        } else {
+            (dx.abs(), dx.abs() / ratio)
+        };
+        Point::new(
+            initial_pt.x + width.copysign(dx),
+            initial_pt.y + height.copysign(dy),
+        )
+    }
+}
+
+impl Display for AspectLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Free => write!(f, "Ratio: Free"),
+            Self::Widescreen => write!(f, "Ratio: 16:9"),
+            Self::Standard => write!(f, "Ratio: 4:3"),
+            Self::Square => write!(f, "Ratio: 1:1"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Endpoints {
     pub initial_pt: Point,
@@ -46,28 +187,118 @@ impl Endpoints {
 
         (start, end)
     }
+
+    pub fn length(self) -> f32 {
+        let delta = self.final_pt - self.initial_pt;
+        delta.x.hypot(delta.y)
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Shape {
     pub shape_type: ShapeType,
     pub endpoints: Option<Endpoints>,
-    pub color: ShapeColor,
+    pub stroke_color: RgbaColor,
+    pub fill_color: RgbaColor,
     pub is_filled: bool,
     pub is_solid: bool,
     pub stroke_width: ShapeStroke,
+    /// Pattern of outlines, lines and arrows, filled shapes ignore it
+    pub stroke_style: StrokeStyle,
+    /// Typed content of text shapes, sized by the stroke width
+    pub text: String,
+    /// Every cursor position of a freehand stroke, `endpoints` then hold its bounds
+    pub points: Vec<Point>,
+    /// Number shown by step markers, counted from 1
+    pub step: u32,
+    /// Pixelated pixels a placed blur shows over the capture
+    pub redaction: Option<Redaction>,
+    /// Opacity both colors are drawn at, from 0.1 to 1
+    pub alpha: f32,
+    /// Radius of rectangle corners in logical pixels, other shapes ignore it
+    pub corner_radius: f32,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+impl Default for Shape {
+    fn default() -> Self {
+        Self {
+            shape_type: ShapeType::default(),
+            endpoints: None,
+            stroke_color: RgbaColor::default(),
+            fill_color: RgbaColor::default(),
+            is_filled: false,
+            is_solid: false,
+            stroke_width: ShapeStroke::default(),
+            stroke_style: StrokeStyle::default(),
+            text: String::new(),
+            points: Vec::new(),
+            step: 0,
+            redaction: None,
+            alpha: 1.0,
+            corner_radius: 0.0,
+        }
+    }
+}
+
+/// Part of a placed shape that follows the cursor
+#[derive(Debug, Clone, Copy)]
+pub enum ShapeDrag {
+    InitialPoint,
+    FinalPoint,
+    /// The whole shape, moved by the cursor's travel since this position
+    Body(Point),
+}
+
+/// A dragged shape as it was before the drag, for undo and redo
+#[derive(Debug, Clone)]
+pub struct ShapeMove {
+    pub index: usize,
+    pub shape: Shape,
+    /// Number of placed shapes when the drag happened, the move only applies at that count
+    pub count: usize,
+}
+
+/// Part of a finished manual selection that follows the cursor in crop mode
+#[derive(Debug, Clone, Copy)]
+pub enum SelectionHandle {
+    /// The whole selection, moved by the cursor's travel since this position
+    Body(Point),
+    /// An edge or corner, moving the sides that are set
+    Edge {
+        left: bool,
+        right: bool,
+        top: bool,
+        bottom: bool,
+    },
+}
+
+/// Patch composited onto the capture, at `x`, `y` in image pixels
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    pub x: i64,
+    pub y: i64,
+    pub pixels: RgbaImage,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ShapeType {
     #[default]
     Rectangle,
     Ellipse,
     Line,
     Arrow,
+    Spotlight,
+    Text,
+    Freehand,
+    /// Dimension line between two targets, labelled with their gap
+    Measure,
+    /// Numbered circle placed with a click, numbers count up with each one
+    StepMarker,
+    /// Pixelates the capture under it, the stroke width sets how coarse
+    Blur,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ShapeColor {
     #[default]
     Red,
@@ -76,9 +307,35 @@ pub enum ShapeColor {
     Yellow,
     Black,
     White,
+    /// Swatch loaded from a palette file or picked by hex code
+    Custom(RgbaColor),
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// Straight (not premultiplied) color with its own alpha
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RgbaColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// Opacity of the fill of translucent shapes such as highlights, unless the config sets another
+pub const DEFAULT_HIGHLIGHT_OPACITY: f32 = 0.3;
+
+/// Opacity of the darkened area around a spotlight
+pub const SPOTLIGHT_ALPHA: f32 = 0.7;
+
+/// How far past its radius a spotlight fades to full darkness, as a fraction of the radius
+pub const SPOTLIGHT_FEATHER: f32 = 0.5;
+
+/// Radius of a step marker relative to the font size of its stroke width
+pub const STEP_MARKER_RADIUS: f32 = 0.8;
+
+/// Corner radii the toolbar cycles through for rectangles, in logical pixels
+pub const CORNER_RADII: [f32; 4] = [0.0, 6.0, 12.0, 24.0];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ShapeStroke {
     Thin,
     #[default]
@@ -86,28 +343,307 @@ pub enum ShapeStroke {
     Broad,
 }
 
-impl ShapeColor {
-    pub fn into_iced_color(self, solid: bool) -> iced::Color {
-        let opacity = if solid { 1.0 } else { 0.3 };
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StrokeStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl Shape {
+    pub fn is_highlight(&self) -> bool {
+        self.shape_type == ShapeType::Rectangle && self.is_filled && !self.is_solid
+    }
+
+    /// Sets the stroke to `color` and the fill to it as well, at `highlight_opacity` (kept
+    /// within 0.1 and 0.9) unless the shape is solid
+    pub fn set_color(&mut self, color: RgbaColor, highlight_opacity: f32) {
+        self.stroke_color = color;
+        self.fill_color = if self.is_solid {
+            color
+        } else {
+            color.with_alpha((highlight_opacity.clamp(0.1, 0.9) * 255.0).round() as u8)
+        };
+    }
+
+    /// Dash pattern of the outline, only unfilled rectangles and ellipses, lines and arrows
+    /// have one
+    pub fn dash_pattern(&self) -> &'static [f32] {
+        match self.shape_type {
+            ShapeType::Rectangle | ShapeType::Ellipse | ShapeType::Line | ShapeType::Arrow
+                if !self.is_filled =>
+            {
+                self.stroke_style.dash_pattern(self.stroke_width)
+            }
+            _ => &[],
+        }
+    }
+
+    /// Corner radius of a rectangle spanning `endpoints`, at most half its smaller side. Always
+    /// 0 for other shapes
+    pub fn fitted_corner_radius(&self, endpoints: Endpoints) -> f32 {
+        if self.shape_type != ShapeType::Rectangle {
+            return 0.0;
+        }
+        let (top_left, bottom_right) = endpoints.normalize();
+        let smaller_side = (bottom_right.x - top_left.x).min(bottom_right.y - top_left.y);
+        self.corner_radius.min(smaller_side / 2.0).max(0.0)
+    }
+
+    /// Stroke color as drawn, with the shape's opacity applied
+    pub fn stroke(&self) -> RgbaColor {
+        self.stroke_color.faded(self.alpha)
+    }
+
+    /// Fill color as drawn, with the shape's opacity applied
+    pub fn fill(&self) -> RgbaColor {
+        self.fill_color.faded(self.alpha)
+    }
+
+    /// Moves every point of the shape through `map`
+    pub fn map_points(&mut self, map: impl Fn(Point) -> Point) {
+        if let Some(ref mut endpoints) = self.endpoints {
+            endpoints.initial_pt = map(endpoints.initial_pt);
+            endpoints.final_pt = map(endpoints.final_pt);
+        }
+        for point in self.points.iter_mut() {
+            *point = map(*point);
+        }
+    }
+
+    /// Extends a freehand stroke to `point`, growing its bounds to fit
+    pub fn push_point(&mut self, point: Point) {
+        self.points.push(point);
+        let (top_left, bottom_right) = match self.endpoints {
+            Some(endpoints) if self.points.len() > 1 => endpoints.normalize(),
+            _ => (point, point),
+        };
+        self.endpoints = Some(Endpoints {
+            initial_pt: Point::new(top_left.x.min(point.x), top_left.y.min(point.y)),
+            final_pt: Point::new(bottom_right.x.max(point.x), bottom_right.y.max(point.y)),
+        });
+    }
+
+    /// Stretches a text shape's endpoints over its content so it can be picked and outlined
+    pub fn fit_text(&mut self) {
+        if self.shape_type != ShapeType::Text {
+            return;
+        }
+        let size = text_size(&self.text, self.stroke_width.font_size());
+        if let Some(ref mut endpoints) = self.endpoints {
+            let (top_left, _) = endpoints.normalize();
+            endpoints.initial_pt = top_left;
+            endpoints.final_pt = Point::new(top_left.x + size.width, top_left.y + size.height);
+        }
+    }
+
+    /// Area covered by the shape, used to outline it when selected
+    pub fn bounds(&self) -> Option<Rectangle> {
+        Some(self.shape_type.drawable().bounds(self.endpoints?))
+    }
+
+    /// Distance from `point` to the drawn outline, 0 anywhere inside filled shapes.
+    /// Lines and arrows are measured to their segment rather than their bounds
+    pub fn distance_to(&self, point: Point) -> f32 {
+        let Some(endpoints) = self.endpoints else {
+            return f32::INFINITY;
+        };
+        let distance = self
+            .shape_type
+            .drawable()
+            .distance_to(self, endpoints, point);
+        let half_stroke = if self.is_filled {
+            0.0
+        } else {
+            self.stroke_width.f32() / 2.0
+        };
+        (distance - half_stroke).max(0.0)
+    }
+
+    /// Short human readable description used by the history panel
+    pub fn label(&self) -> String {
+        self.shape_type.drawable().label(self)
+    }
+
+    /// Geometry of the shape in vector exports, `None` if it has to be baked into the
+    /// background
+    pub fn serialize(&self) -> Option<VectorPath> {
+        self.shape_type.drawable().serialize(self, self.endpoints?)
+    }
+}
+
+impl ShapeType {
+    /// Whether dragging an endpoint reshapes it, the rest can only be moved
+    pub fn is_resizable(self) -> bool {
+        matches!(
+            self,
+            ShapeType::Rectangle
+                | ShapeType::Ellipse
+                | ShapeType::Line
+                | ShapeType::Arrow
+                | ShapeType::Spotlight
+        )
+    }
+
+    /// Next tool in toolbar order, wrapping around
+    pub fn next(self) -> Self {
         match self {
-            ShapeColor::Red => iced::Color::from_rgba8(255, 0, 0, opacity),
-            ShapeColor::Green => iced::Color::from_rgba8(0, 255, 0, opacity),
-            ShapeColor::Blue => iced::Color::from_rgba8(0, 0, 255, opacity),
-            ShapeColor::Yellow => iced::Color::from_rgba8(255, 255, 0, opacity),
-            ShapeColor::Black => iced::Color::from_rgba8(0, 0, 0, opacity),
-            ShapeColor::White => iced::Color::from_rgba8(255, 255, 255, opacity),
+            ShapeType::Rectangle => ShapeType::Ellipse,
+            ShapeType::Ellipse => ShapeType::Line,
+            ShapeType::Line => ShapeType::Arrow,
+            ShapeType::Arrow => ShapeType::Spotlight,
+            ShapeType::Spotlight => ShapeType::Text,
+            ShapeType::Text => ShapeType::Freehand,
+            ShapeType::Freehand => ShapeType::Measure,
+            ShapeType::Measure => ShapeType::StepMarker,
+            ShapeType::StepMarker => ShapeType::Blur,
+            ShapeType::Blur => ShapeType::Rectangle,
         }
     }
+}
 
-    pub fn into_paint(self, solid: bool) -> tiny_skia::Color {
-        let opacity = if solid { 255 } else { 77 };
+impl Display for ShapeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ShapeColor::Red => tiny_skia::Color::from_rgba8(255, 0, 0, opacity),
-            ShapeColor::Green => tiny_skia::Color::from_rgba8(0, 255, 0, opacity),
-            ShapeColor::Blue => tiny_skia::Color::from_rgba8(0, 0, 255, opacity),
-            ShapeColor::Yellow => tiny_skia::Color::from_rgba8(255, 255, 0, opacity),
-            ShapeColor::Black => tiny_skia::Color::from_rgba8(0, 0, 0, opacity),
-            ShapeColor::White => tiny_skia::Color::from_rgba8(255, 255, 255, opacity),
+            Self::Rectangle => write!(f, "Rectangle"),
+            Self::Ellipse => write!(f, "Ellipse"),
+            Self::Line => write!(f, "Line"),
+            Self::Arrow => write!(f, "Arrow"),
+            Self::Spotlight => write!(f, "Spotlight"),
+            Self::Text => write!(f, "Text"),
+            Self::Freehand => write!(f, "Pen"),
+            Self::Measure => write!(f, "Measure"),
+            Self::StepMarker => write!(f, "Steps"),
+            Self::Blur => write!(f, "Blur"),
+        }
+    }
+}
+
+impl Display for StrokeStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Solid => write!(f, "Solid"),
+            Self::Dashed => write!(f, "Dashed"),
+            Self::Dotted => write!(f, "Dotted"),
+        }
+    }
+}
+
+impl Display for ShapeColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Red => write!(f, "Red"),
+            Self::Green => write!(f, "Green"),
+            Self::Blue => write!(f, "Blue"),
+            Self::Yellow => write!(f, "Yellow"),
+            Self::Black => write!(f, "Black"),
+            Self::White => write!(f, "White"),
+            Self::Custom(color) => write!(f, "{}", color),
+        }
+    }
+}
+
+impl ShapeColor {
+    pub const ALL: [ShapeColor; 6] = [
+        ShapeColor::Red,
+        ShapeColor::Green,
+        ShapeColor::Blue,
+        ShapeColor::Yellow,
+        ShapeColor::Black,
+        ShapeColor::White,
+    ];
+
+    /// Next or previous preset, wrapping around
+    pub fn step(self, forward: bool) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|color| *color == self)
+            .unwrap_or_default();
+        let len = Self::ALL.len();
+        Self::ALL[if forward {
+            (index + 1) % len
+        } else {
+            (index + len - 1) % len
+        }]
+    }
+
+    /// Opaque color of the preset
+    pub fn rgba(self) -> RgbaColor {
+        match self {
+            ShapeColor::Red => RgbaColor::new(255, 0, 0, 255),
+            ShapeColor::Green => RgbaColor::new(0, 255, 0, 255),
+            ShapeColor::Blue => RgbaColor::new(0, 0, 255, 255),
+            ShapeColor::Yellow => RgbaColor::new(255, 255, 0, 255),
+            ShapeColor::Black => RgbaColor::new(0, 0, 0, 255),
+            ShapeColor::White => RgbaColor::new(255, 255, 255, 255),
+            ShapeColor::Custom(color) => color,
+        }
+    }
+}
+
+impl RgbaColor {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Scales the alpha by `opacity`, kept within 0 and 1
+    pub fn faded(self, opacity: f32) -> Self {
+        self.with_alpha((self.a as f32 * opacity.clamp(0.0, 1.0)).round() as u8)
+    }
+
+    pub fn into_iced(self) -> iced::Color {
+        iced::Color::from_rgba8(self.r, self.g, self.b, self.a as f32 / 255.0)
+    }
+
+    pub fn into_paint(self) -> tiny_skia::Color {
+        tiny_skia::Color::from_rgba8(self.r, self.g, self.b, self.a)
+    }
+
+    /// Parses `#rrggbb` or `#rrggbbaa`, `None` when malformed
+    pub fn from_hex(value: &str) -> Option<Self> {
+        let hex = value.trim().strip_prefix('#')?;
+        if hex.len() != 6 && hex.len() != 8 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+        Some(Self::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+    }
+
+    /// `#rrggbb`, with the alpha appended unless opaque
+    pub fn to_hex(self) -> String {
+        let hex = format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b);
+        if self.a == 255 {
+            hex
+        } else {
+            format!("{}{:02x}", hex, self.a)
+        }
+    }
+
+    /// Preset matching the color, ignoring alpha
+    pub fn preset(self) -> Option<ShapeColor> {
+        ShapeColor::ALL
+            .into_iter()
+            .find(|preset| preset.rgba().with_alpha(self.a) == self)
+    }
+}
+
+impl Default for RgbaColor {
+    fn default() -> Self {
+        ShapeColor::default().rgba()
+    }
+}
+
+impl Display for RgbaColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.preset() {
+            Some(preset) => write!(f, "{}", preset),
+            None => write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b),
         }
     }
 }
@@ -120,4 +656,111 @@ impl ShapeStroke {
             Self::Broad => 8.0,
         }
     }
+
+    /// Text size in logical pixels when the width is used by a text shape
+    pub fn font_size(&self) -> f32 {
+        match self {
+            Self::Thin => 16.0,
+            Self::Medium => 24.0,
+            Self::Broad => 36.0,
+        }
+    }
+
+    /// Block size in logical pixels when the width is used by a blur
+    pub fn blur_block(&self) -> f32 {
+        match self {
+            Self::Thin => 4.0,
+            Self::Medium => 8.0,
+            Self::Broad => 16.0,
+        }
+    }
+
+    /// Next width up or down, staying at the ends
+    pub fn step(&self, thicker: bool) -> Self {
+        match (self, thicker) {
+            (Self::Thin, true) | (Self::Broad, false) => Self::Medium,
+            (Self::Medium, true) | (Self::Broad, true) => Self::Broad,
+            (Self::Medium, false) | (Self::Thin, false) => Self::Thin,
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Solid => Self::Dashed,
+            Self::Dashed => Self::Dotted,
+            Self::Dotted => Self::Solid,
+        }
+    }
+
+    /// Alternating dash and gap lengths in logical pixels for `width`, empty when solid.
+    ///
+    /// Dashes are three widths long and dots one, so the pattern keeps its look as the
+    /// stroke gets thicker.
+    pub fn dash_pattern(&self, width: ShapeStroke) -> &'static [f32] {
+        match (self, width) {
+            (Self::Solid, _) => &[],
+            (Self::Dashed, ShapeStroke::Thin) => &[6.0, 4.0],
+            (Self::Dashed, ShapeStroke::Medium) => &[15.0, 10.0],
+            (Self::Dashed, ShapeStroke::Broad) => &[24.0, 16.0],
+            (Self::Dotted, ShapeStroke::Thin) => &[2.0, 3.0],
+            (Self::Dotted, ShapeStroke::Medium) => &[5.0, 7.5],
+            (Self::Dotted, ShapeStroke::Broad) => &[8.0, 12.0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: (f32, f32), to: (f32, f32)) -> Shape {
+        Shape {
+            shape_type: ShapeType::Line,
+            endpoints: Some(Endpoints {
+                initial_pt: Point::new(from.0, from.1),
+                final_pt: Point::new(to.0, to.1),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn push_point_grows_the_bounds_over_every_point() {
+        let mut shape = Shape {
+            shape_type: ShapeType::Freehand,
+            // Left over from an earlier stroke, the first point replaces it
+            endpoints: Some(Endpoints {
+                initial_pt: Point::new(-50.0, -50.0),
+                final_pt: Point::new(500.0, 500.0),
+            }),
+            ..Default::default()
+        };
+        for (x, y) in [(40.0, 30.0), (10.0, 60.0), (70.0, 20.0), (50.0, 45.0)] {
+            shape.push_point(Point::new(x, y));
+        }
+        let endpoints = shape.endpoints.unwrap();
+        assert_eq!(endpoints.initial_pt, Point::new(10.0, 20.0));
+        assert_eq!(endpoints.final_pt, Point::new(70.0, 60.0));
+    }
+
+    #[test]
+    fn diagonal_line_is_far_from_the_empty_corners_of_its_bounds() {
+        let shape = line((0.0, 0.0), (100.0, 100.0));
+        let corner = Point::new(90.0, 10.0);
+        assert!(shape.bounds().unwrap().contains(corner));
+        // 80 / sqrt(2) from the line, less half the medium stroke
+        assert!((shape.distance_to(corner) - 54.07).abs() < 0.01);
+        assert_eq!(shape.distance_to(Point::new(50.0, 50.0)), 0.0);
+    }
+
+    #[test]
+    fn line_is_measured_to_its_endpoints_past_its_ends() {
+        let shape = line((0.0, 0.0), (100.0, 100.0));
+        assert_eq!(shape.distance_to(Point::new(0.0, 0.0)), 0.0);
+        assert_eq!(shape.distance_to(Point::new(100.0, 100.0)), 0.0);
+        assert_eq!(shape.distance_to(Point::new(103.0, 104.0)), 2.5);
+        assert_eq!(shape.distance_to(Point::new(-3.0, -4.0)), 2.5);
+    }
 }
@@ -0,0 +1,335 @@
+use std::{fmt::Write as _, io::Write};
+
+use flate2::{write::ZlibEncoder, Compression};
+use iced::Point;
+use xcap::image::RgbaImage;
+
+use super::{
+    drawable::{VectorPath, KAPPA},
+    models::{RgbaColor, Shape},
+};
+
+/// Height of the band above the capture that holds the title, in points
+const TITLE_HEIGHT: f32 = 32.0;
+const TITLE_FONT_SIZE: f32 = 16.0;
+
+/// Writes a single page PDF with `background`, the selection at `(x, y, width, height)`, and
+/// `shapes` drawn over it as vectors, plus `title` above it unless empty.
+///
+/// The page is sized to the selection in logical pixels, one pixel per point. Shapes that
+/// aren't `is_vector` are skipped.
+pub fn export_pdf(
+    background: &RgbaImage,
+    shapes: &[Shape],
+    selection: (u32, u32, u32, u32),
+    scale_factor: f32,
+    title: &str,
+) -> Vec<u8> {
+    let (x, y, width, height) = selection;
+    let (page_width, image_height) = (width as f32 / scale_factor, height as f32 / scale_factor);
+    let title_height = if title.is_empty() { 0.0 } else { TITLE_HEIGHT };
+    let page_height = image_height + title_height;
+
+    // Opacities used by the shapes, each gets a graphics state to select it
+    let mut opacities: Vec<u8> = Vec::new();
+
+    let mut content = String::new();
+    writeln!(
+        content,
+        "q {} 0 0 {} 0 0 cm /Im0 Do Q",
+        page_width, image_height
+    )
+    .unwrap();
+    // Overlay coordinates with a downward y axis, shifted so the selection starts at the corner
+    writeln!(
+        content,
+        "q 1 0 0 -1 {} {} cm 1 J 1 j",
+        -(x as f32) / scale_factor,
+        image_height + y as f32 / scale_factor
+    )
+    .unwrap();
+    for shape in shapes {
+        let Some(path) = shape.serialize() else {
+            continue;
+        };
+        let alpha = if shape.is_filled {
+            shape.fill().a
+        } else {
+            shape.stroke().a
+        };
+        let state = match opacities.iter().position(|opacity| *opacity == alpha) {
+            Some(index) => index,
+            None => {
+                opacities.push(alpha);
+                opacities.len() - 1
+            }
+        };
+        write!(
+            content,
+            "/GS{} gs {} RG {} rg {} w [{}] 0 d ",
+            state,
+            rgb(shape.stroke_color),
+            rgb(shape.fill_color),
+            shape.stroke_width.f32(),
+            dash_array(shape)
+        )
+        .unwrap();
+
+        let paint = match path {
+            VectorPath::Rectangle {
+                top_left,
+                bottom_right,
+                radius,
+            } => {
+                if radius > 0.0 {
+                    rounded_rect_path(&mut content, top_left, bottom_right, radius);
+                } else {
+                    write!(
+                        content,
+                        "{} {} {} {} re ",
+                        top_left.x,
+                        top_left.y,
+                        bottom_right.x - top_left.x,
+                        bottom_right.y - top_left.y
+                    )
+                    .unwrap();
+                }
+                paint_operator(shape)
+            }
+            VectorPath::Ellipse {
+                top_left,
+                bottom_right,
+            } => {
+                ellipse_path(&mut content, top_left, bottom_right);
+                paint_operator(shape)
+            }
+            VectorPath::Line { from, to } => {
+                write!(content, "{} {} m {} {} l ", from.x, from.y, to.x, to.y).unwrap();
+                "S"
+            }
+            VectorPath::Arrow {
+                from,
+                base,
+                tip,
+                corners: (right_pt, left_pt),
+            } => {
+                // The head is filled with the line color
+                write!(
+                    content,
+                    "{} {} m {} {} l S [] 0 d {} rg {} {} m {} {} l {} {} l h ",
+                    from.x,
+                    from.y,
+                    base.x,
+                    base.y,
+                    rgb(shape.stroke_color),
+                    tip.x,
+                    tip.y,
+                    right_pt.x,
+                    right_pt.y,
+                    left_pt.x,
+                    left_pt.y
+                )
+                .unwrap();
+                "B"
+            }
+        };
+        writeln!(content, "{}", paint).unwrap();
+    }
+    content.push_str("Q\n");
+    if !title.is_empty() {
+        writeln!(
+            content,
+            "BT /F1 {} Tf 8 {} Td ({}) Tj ET",
+            TITLE_FONT_SIZE,
+            image_height + (TITLE_HEIGHT - TITLE_FONT_SIZE) / 2.0 + 4.0,
+            escape_text(title)
+        )
+        .unwrap();
+    }
+
+    let rgb_pixels: Vec<u8> = background
+        .pixels()
+        .flat_map(|pixel| [pixel.0[0], pixel.0[1], pixel.0[2]])
+        .collect();
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&rgb_pixels).unwrap();
+    let image_data = encoder.finish().unwrap();
+
+    let states: String = opacities
+        .iter()
+        .enumerate()
+        .map(|(index, alpha)| {
+            let alpha = *alpha as f32 / 255.0;
+            format!(
+                "/GS{} << /Type /ExtGState /CA {} /ca {} >> ",
+                index, alpha, alpha
+            )
+        })
+        .collect();
+    let font = if title.is_empty() {
+        ""
+    } else {
+        "/Font << /F1 6 0 R >> "
+    };
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R \
+             /Resources << /XObject << /Im0 5 0 R >> /ExtGState << {}>> {}>> >>",
+            page_width, page_height, states, font
+        )
+        .into_bytes(),
+        stream(
+            &format!("<< /Length {} >>", content.len()),
+            content.as_bytes(),
+        ),
+        stream(
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+                 /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+                background.width(),
+                background.height(),
+                image_data.len()
+            ),
+            &image_data,
+        ),
+    ];
+    if !title.is_empty() {
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+    }
+
+    let mut document = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(document.len());
+        document.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        document.extend_from_slice(object);
+        document.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = document.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        writeln!(trailer, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+    document.extend_from_slice(trailer.as_bytes());
+    document
+}
+
+fn stream(dictionary: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("{}\nstream\n", dictionary).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+fn rgb(color: RgbaColor) -> String {
+    format!(
+        "{} {} {}",
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0
+    )
+}
+
+fn dash_array(shape: &Shape) -> String {
+    let lengths: Vec<String> = shape
+        .dash_pattern()
+        .iter()
+        .map(|length| length.to_string())
+        .collect();
+    lengths.join(" ")
+}
+
+fn paint_operator(shape: &Shape) -> &'static str {
+    if shape.is_filled {
+        "f"
+    } else {
+        "S"
+    }
+}
+
+/// Four Bézier quarters through the midpoints of the bounding box's sides
+fn ellipse_path(content: &mut String, top_left: Point, bottom_right: Point) {
+    let (rx, ry) = (
+        (bottom_right.x - top_left.x) / 2.0,
+        (bottom_right.y - top_left.y) / 2.0,
+    );
+    let (cx, cy) = (top_left.x + rx, top_left.y + ry);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let quarters = [
+        [(cx + rx, cy + ky), (cx + kx, cy + ry), (cx, cy + ry)],
+        [(cx - kx, cy + ry), (cx - rx, cy + ky), (cx - rx, cy)],
+        [(cx - rx, cy - ky), (cx - kx, cy - ry), (cx, cy - ry)],
+        [(cx + kx, cy - ry), (cx + rx, cy - ky), (cx + rx, cy)],
+    ];
+    write!(content, "{} {} m ", cx + rx, cy).unwrap();
+    for [(x1, y1), (x2, y2), (x3, y3)] in quarters {
+        write!(content, "{} {} {} {} {} {} c ", x1, y1, x2, y2, x3, y3).unwrap();
+    }
+    content.push_str("h ");
+}
+
+/// Straight sides joined by Bézier quarter circles of `radius`
+fn rounded_rect_path(content: &mut String, top_left: Point, bottom_right: Point, radius: f32) {
+    let k = radius * (1.0 - KAPPA);
+    let (left, top, right, bottom) = (top_left.x, top_left.y, bottom_right.x, bottom_right.y);
+    write!(content, "{} {} m ", left + radius, top).unwrap();
+    let sides = [
+        [
+            (right - radius, top),
+            (right - k, top),
+            (right, top + k),
+            (right, top + radius),
+        ],
+        [
+            (right, bottom - radius),
+            (right, bottom - k),
+            (right - k, bottom),
+            (right - radius, bottom),
+        ],
+        [
+            (left + radius, bottom),
+            (left + k, bottom),
+            (left, bottom - k),
+            (left, bottom - radius),
+        ],
+        [
+            (left, top + radius),
+            (left, top + k),
+            (left + k, top),
+            (left + radius, top),
+        ],
+    ];
+    for [(lx, ly), (x1, y1), (x2, y2), (x3, y3)] in sides {
+        write!(
+            content,
+            "{} {} l {} {} {} {} {} {} c ",
+            lx, ly, x1, y1, x2, y2, x3, y3
+        )
+        .unwrap();
+    }
+    content.push_str("h ");
+}
+
+/// Escapes a string literal, characters Helvetica can't show become `?`
+fn escape_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => String::from("?"),
+        })
+        .collect()
+}
@@ -0,0 +1,233 @@
+use std::fmt::Write;
+
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use xcap::image::{Rgba, RgbaImage};
+
+use crate::{
+    config::{
+        caption::TimestampCaption,
+        snippet::{parse_hex_color, SnippetStyle},
+        watermark::Corner,
+    },
+    consts::{FONT_MEDIUM, FONT_MONO},
+};
+
+const TAB: &str = "    ";
+
+/// Height of the window-like title bar drawn above the text
+const TITLE_BAR: u32 = 36;
+
+/// Renders `text` as a code card: a title bar with window buttons above the text on a solid
+/// background
+pub fn render_snippet(text: &str, style: &SnippetStyle) -> RgbaImage {
+    // Code lines up in columns only with a monospace font
+    let font = load_font_or(&style.font_path, FONT_MONO);
+    let scale = PxScale::from(style.font_size);
+    let scaled = font.as_scaled(scale);
+    let line_height = (scaled.height() + scaled.line_gap()).ceil();
+
+    let lines: Vec<String> = text.lines().map(|line| line.replace('\t', TAB)).collect();
+    let text_width = lines
+        .iter()
+        .map(|line| line_width(&font, scale, line))
+        .fold(0.0, f32::max)
+        .ceil() as u32;
+    let text_height = (lines.len().max(1) as f32 * line_height) as u32;
+
+    let padding = style.padding;
+    let width = text_width + 2 * padding;
+    let height = TITLE_BAR + text_height + 2 * padding;
+
+    let [r, g, b] = parse_hex_color(&style.background, [30, 30, 30]);
+    let mut image = RgbaImage::from_pixel(width, height, Rgba([r, g, b, 255]));
+
+    for (index, color) in [[255, 95, 86], [255, 189, 46], [39, 201, 63]]
+        .iter()
+        .enumerate()
+    {
+        let center = (
+            padding as f32 + 8.0 + index as f32 * 22.0,
+            TITLE_BAR as f32 / 2.0 + 8.0,
+        );
+        fill_circle(&mut image, center, 6.0, *color);
+    }
+
+    let foreground = parse_hex_color(&style.foreground, [212, 212, 212]);
+    for (index, line) in lines.iter().enumerate() {
+        let baseline = (TITLE_BAR + padding) as f32 + index as f32 * line_height + scaled.ascent();
+        draw_text(
+            &mut image,
+            &font,
+            scale,
+            line,
+            (padding as f32, baseline),
+            foreground,
+        );
+    }
+
+    image
+}
+
+/// Draws the current time on a translucent box in the configured corner
+pub fn draw_timestamp(image: &mut RgbaImage, caption: &TimestampCaption) {
+    const PADDING: f32 = 6.0;
+    const MARGIN: f32 = 8.0;
+
+    let now = chrono::Local::now();
+    let mut label = String::new();
+    if write!(label, "{}", now.format(&caption.format)).is_err() {
+        println!("Invalid timestamp format {}", caption.format);
+        label = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    }
+    let font = load_font("");
+    let scale = PxScale::from(caption.font_size);
+    let scaled = font.as_scaled(scale);
+
+    let box_width = line_width(&font, scale, &label) + 2.0 * PADDING;
+    let box_height = scaled.height() + 2.0 * PADDING;
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let x = match caption.corner {
+        Corner::TopLeft | Corner::BottomLeft => MARGIN,
+        Corner::TopRight | Corner::BottomRight => width - MARGIN - box_width,
+    };
+    let y = match caption.corner {
+        Corner::TopLeft | Corner::TopRight => MARGIN,
+        Corner::BottomLeft | Corner::BottomRight => height - MARGIN - box_height,
+    };
+
+    let background = parse_hex_color(&caption.background, [0, 0, 0]);
+    let opacity = caption.background_opacity.clamp(0.0, 1.0);
+    for py in y.round() as i32..(y + box_height).round() as i32 {
+        for px in x.round() as i32..(x + box_width).round() as i32 {
+            blend(image, px, py, background, opacity);
+        }
+    }
+
+    draw_text(
+        image,
+        &font,
+        scale,
+        &label,
+        (x + PADDING, y + PADDING + scaled.ascent()),
+        parse_hex_color(&caption.foreground, [255, 255, 255]),
+    );
+}
+
+pub fn load_font(path: &str) -> FontArc {
+    load_font_or(path, FONT_MEDIUM)
+}
+
+/// Font at `path`, the `bundled` one when it is empty or can't be read
+pub fn load_font_or(path: &str, bundled: &'static [u8]) -> FontArc {
+    if !path.is_empty() {
+        match std::fs::read(path).map(FontArc::try_from_vec) {
+            Ok(Ok(font)) => return font,
+            _ => println!("Could not load font {}, using the bundled one", path),
+        }
+    }
+    FontArc::try_from_slice(bundled).unwrap()
+}
+
+pub fn line_width(font: &FontArc, scale: PxScale, line: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in line.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Draws a single line of text with its baseline starting at `origin`
+pub fn draw_text(
+    image: &mut RgbaImage,
+    font: &FontArc,
+    scale: PxScale,
+    line: &str,
+    origin: (f32, f32),
+    color: [u8; 3],
+) {
+    trace_text(font, scale, line, origin, |px, py, coverage| {
+        blend(image, px, py, color, coverage)
+    });
+}
+
+/// Calls `plot` with the coverage of every pixel a single line of text touches, its baseline
+/// starting at `origin`
+pub fn trace_text(
+    font: &FontArc,
+    scale: PxScale,
+    line: &str,
+    origin: (f32, f32),
+    mut plot: impl FnMut(i32, i32, f32),
+) {
+    let scaled = font.as_scaled(scale);
+    let (mut x, y) = origin;
+    let mut previous = None;
+    for c in line.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            x += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(scale, point(x, y));
+        x += scaled.h_advance(id);
+        previous = Some(id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i32 + gx as i32;
+            let py = bounds.min.y as i32 + gy as i32;
+            plot(px, py, coverage);
+        });
+    }
+}
+
+fn fill_circle(image: &mut RgbaImage, center: (f32, f32), radius: f32, color: [u8; 3]) {
+    let (cx, cy) = center;
+    for py in (cy - radius - 1.0) as i32..=(cy + radius + 1.0) as i32 {
+        for px in (cx - radius - 1.0) as i32..=(cx + radius + 1.0) as i32 {
+            let distance = (px as f32 + 0.5 - cx).hypot(py as f32 + 0.5 - cy);
+            blend(
+                image,
+                px,
+                py,
+                color,
+                (radius + 0.5 - distance).clamp(0.0, 1.0),
+            );
+        }
+    }
+}
+
+fn blend(image: &mut RgbaImage, x: i32, y: i32, color: [u8; 3], coverage: f32) {
+    if x < 0 || y < 0 || x as u32 >= image.width() || y as u32 >= image.height() {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for (channel, target) in pixel.0.iter_mut().zip(color) {
+        let base = *channel as f32;
+        *channel = (base + (target as f32 - base) * coverage).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_snippet_font_is_monospace() {
+        let font = load_font_or("", FONT_MONO);
+        let scale = PxScale::from(20.0);
+        assert_eq!(
+            line_width(&font, scale, "iiii"),
+            line_width(&font, scale, "WWWW")
+        );
+    }
+}
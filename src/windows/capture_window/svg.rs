@@ -0,0 +1,459 @@
+use std::{collections::HashMap, fmt::Write, io::Cursor};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use iced::Point;
+use xcap::image::{ImageFormat, RgbaImage};
+
+use super::{
+    drawable::VectorPath,
+    models::{
+        Endpoints, RgbaColor, Shape, ShapeStroke, ShapeType, StrokeStyle, DEFAULT_HIGHLIGHT_OPACITY,
+    },
+};
+
+const STROKES: [ShapeStroke; 3] = [ShapeStroke::Thin, ShapeStroke::Medium, ShapeStroke::Broad];
+
+/// Whether `export_svg` writes the shape as an element, the rest has to be baked into the
+/// background
+pub fn is_vector(shape: &Shape) -> bool {
+    shape.serialize().is_some()
+}
+
+/// Writes `shapes` over `background`, the selection at `(x, y, width, height)`, as an SVG
+/// document that `import_svg` can read back.
+///
+/// Coordinates are image pixels of the whole capture and the view box crops them to the
+/// selection. Shapes that aren't `is_vector` are skipped.
+pub fn export_svg(
+    background: &RgbaImage,
+    shapes: &[Shape],
+    selection: (u32, u32, u32, u32),
+    scale_factor: f32,
+) -> String {
+    let (x, y, width, height) = selection;
+    let mut png = Cursor::new(Vec::new());
+    background.write_to(&mut png, ImageFormat::Png).unwrap();
+
+    let mut document = String::new();
+    writeln!(
+        document,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="{x} {y} {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(
+        document,
+        r#"  <image x="{x}" y="{y}" width="{width}" height="{height}" href="data:image/png;base64,{}"/>"#,
+        STANDARD.encode(png.into_inner())
+    )
+    .unwrap();
+
+    for shape in shapes {
+        let Some(path) = shape.serialize() else {
+            continue;
+        };
+        let element = match path.scaled(scale_factor) {
+            VectorPath::Rectangle {
+                top_left,
+                bottom_right,
+                radius,
+            } => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}"{} {}/>"#,
+                top_left.x,
+                top_left.y,
+                bottom_right.x - top_left.x,
+                bottom_right.y - top_left.y,
+                corner_radius(radius),
+                paint(shape, scale_factor)
+            ),
+            VectorPath::Ellipse {
+                top_left,
+                bottom_right,
+            } => {
+                let (rx, ry) = (
+                    (bottom_right.x - top_left.x) / 2.0,
+                    (bottom_right.y - top_left.y) / 2.0,
+                );
+                format!(
+                    r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {}/>"#,
+                    top_left.x + rx,
+                    top_left.y + ry,
+                    rx,
+                    ry,
+                    paint(shape, scale_factor)
+                )
+            }
+            VectorPath::Line { from, to } => format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                from.x,
+                from.y,
+                to.x,
+                to.y,
+                line_paint(shape, scale_factor)
+            ),
+            VectorPath::Arrow {
+                from,
+                base,
+                tip,
+                corners: (right_pt, left_pt),
+            } => format!(
+                r#"<path d="M{} {} L{} {} M{} {} L{} {} L{} {} Z" fill="{}" {}/>"#,
+                from.x,
+                from.y,
+                base.x,
+                base.y,
+                right_pt.x,
+                right_pt.y,
+                tip.x,
+                tip.y,
+                left_pt.x,
+                left_pt.y,
+                hex(shape.stroke_color),
+                line_paint(shape, scale_factor)
+            ),
+        };
+        writeln!(document, "  {}", element).unwrap();
+    }
+
+    document.push_str("</svg>\n");
+    document
+}
+
+fn hex(color: RgbaColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Fill, or stroke for outlines, of a rectangle or ellipse; highlights keep their translucency
+fn paint(shape: &Shape, scale_factor: f32) -> String {
+    if shape.is_filled {
+        format!(
+            r#"fill="{}" fill-opacity="{}"{}"#,
+            hex(shape.fill_color),
+            shape.fill_color.a as f32 / 255.0,
+            opacity(shape)
+        )
+    } else {
+        format!(r#"fill="none" {}"#, line_paint(shape, scale_factor))
+    }
+}
+
+fn line_paint(shape: &Shape, scale_factor: f32) -> String {
+    format!(
+        r#"stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"{}{}"#,
+        hex(shape.stroke_color),
+        shape.stroke_width.f32() * scale_factor,
+        dash_array(shape, scale_factor),
+        opacity(shape)
+    )
+}
+
+/// Dash pattern of the outline, left out for solid ones. Arrowheads get dashed along with
+/// the shaft, unlike on the canvas
+fn dash_array(shape: &Shape, scale_factor: f32) -> String {
+    let pattern = shape.dash_pattern();
+    if pattern.is_empty() {
+        return String::new();
+    }
+    let lengths: Vec<String> = pattern
+        .iter()
+        .map(|length| (length * scale_factor).to_string())
+        .collect();
+    format!(r#" stroke-dasharray="{}""#, lengths.join(" "))
+}
+
+/// Rounded corners of a rectangle, left out for sharp ones
+fn corner_radius(radius: f32) -> String {
+    if radius > 0.0 {
+        format!(r#" rx="{}""#, radius)
+    } else {
+        String::new()
+    }
+}
+
+/// Opacity of the whole element, left out for opaque shapes
+fn opacity(shape: &Shape) -> String {
+    if shape.alpha < 1.0 {
+        format!(r#" opacity="{}""#, shape.alpha)
+    } else {
+        String::new()
+    }
+}
+
+/// Parses the subset of SVG that Capter exports back into shapes.
+///
+/// Coordinates in the document are image pixels and are converted into overlay coordinates
+/// using `scale_factor`. Unknown or malformed elements are skipped with a warning.
+pub fn import_svg(contents: &str, scale_factor: f32) -> Vec<Shape> {
+    let mut shapes = Vec::new();
+
+    for element in contents.split('<').skip(1) {
+        let tag = element.split('>').next().unwrap_or_default();
+        let tag = tag.trim().trim_end_matches('/').trim_end();
+        if tag.starts_with(['/', '?', '!']) {
+            continue;
+        }
+
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let attributes = parse_attributes(attributes);
+
+        let shape = match name {
+            "svg" | "image" | "g" | "title" | "desc" | "defs" => continue,
+            "rect" => parse_rect(&attributes, scale_factor),
+            "ellipse" => parse_ellipse(&attributes, scale_factor),
+            "line" => parse_line(&attributes, scale_factor),
+            "path" => parse_arrow(&attributes, scale_factor),
+            _ => {
+                println!("Skipping unsupported SVG element <{}>", name);
+                continue;
+            }
+        };
+
+        match shape {
+            Some(shape) => shapes.push(shape),
+            None => println!("Skipping malformed SVG element <{}>", name),
+        }
+    }
+
+    shapes
+}
+
+fn parse_attributes(input: &str) -> HashMap<&str, &str> {
+    let mut attributes = HashMap::new();
+    let mut rest = input;
+    while let Some((key, tail)) = rest.split_once('=') {
+        let tail = tail.trim_start();
+        let quote = match tail.chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => break,
+        };
+        let Some((value, tail)) = tail[1..].split_once(quote) else {
+            break;
+        };
+        attributes.insert(key.trim(), value);
+        rest = tail;
+    }
+    attributes
+}
+
+fn number(attributes: &HashMap<&str, &str>, key: &str, scale_factor: f32) -> Option<f32> {
+    attributes
+        .get(key)?
+        .trim()
+        .trim_end_matches("px")
+        .parse::<f32>()
+        .ok()
+        .map(|value| value / scale_factor)
+}
+
+fn parse_color(value: &str) -> Option<RgbaColor> {
+    let hex = value.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(RgbaColor::new(channel(0)?, channel(2)?, channel(4)?, 255))
+}
+
+fn parse_opacity(attributes: &HashMap<&str, &str>) -> f32 {
+    attributes
+        .get("opacity")
+        .and_then(|opacity| opacity.parse::<f32>().ok())
+        .unwrap_or(1.0)
+        .clamp(0.1, 1.0)
+}
+
+/// Dots are as long as the stroke is wide and dashes three times as long
+fn parse_stroke_style(
+    attributes: &HashMap<&str, &str>,
+    stroke_width: ShapeStroke,
+    scale_factor: f32,
+) -> StrokeStyle {
+    let dash = attributes
+        .get("stroke-dasharray")
+        .and_then(|lengths| lengths.split([' ', ',']).next())
+        .and_then(|length| length.trim().parse::<f32>().ok())
+        .map(|length| length / scale_factor);
+    match dash {
+        Some(length) if length >= 2.0 * stroke_width.f32() => StrokeStyle::Dashed,
+        Some(length) if length > 0.0 => StrokeStyle::Dotted,
+        _ => StrokeStyle::Solid,
+    }
+}
+
+fn parse_stroke(attributes: &HashMap<&str, &str>, scale_factor: f32) -> ShapeStroke {
+    let width = number(attributes, "stroke-width", scale_factor).unwrap_or(0.0);
+    STROKES
+        .into_iter()
+        .min_by(|a, b| (a.f32() - width).abs().total_cmp(&(b.f32() - width).abs()))
+        .unwrap_or_default()
+}
+
+/// Resolves color, fill and opacity of a rectangle or ellipse
+fn parse_paint(attributes: &HashMap<&str, &str>, scale_factor: f32) -> Option<Shape> {
+    let fill = attributes.get("fill").copied().unwrap_or("none");
+    let mut shape = Shape::default();
+    if fill != "none" {
+        let opacity = attributes
+            .get("fill-opacity")
+            .and_then(|opacity| opacity.parse::<f32>().ok())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        shape.is_filled = true;
+        shape.is_solid = opacity >= 1.0;
+        shape.set_color(parse_color(fill)?, DEFAULT_HIGHLIGHT_OPACITY);
+        shape.fill_color.a = (opacity * 255.0).round() as u8;
+    } else {
+        shape.is_filled = false;
+        shape.is_solid = true;
+        shape.set_color(
+            parse_color(attributes.get("stroke")?)?,
+            DEFAULT_HIGHLIGHT_OPACITY,
+        );
+        shape.stroke_width = parse_stroke(attributes, scale_factor);
+        shape.stroke_style = parse_stroke_style(attributes, shape.stroke_width, scale_factor);
+    }
+    shape.alpha = parse_opacity(attributes);
+    Some(shape)
+}
+
+fn parse_line_paint(attributes: &HashMap<&str, &str>, scale_factor: f32) -> Option<Shape> {
+    let color = parse_color(attributes.get("stroke")?)?;
+    let stroke_width = parse_stroke(attributes, scale_factor);
+    Some(Shape {
+        stroke_color: color,
+        fill_color: color,
+        is_filled: false,
+        is_solid: true,
+        stroke_width,
+        stroke_style: parse_stroke_style(attributes, stroke_width, scale_factor),
+        alpha: parse_opacity(attributes),
+        ..Default::default()
+    })
+}
+
+fn parse_rect(attributes: &HashMap<&str, &str>, scale_factor: f32) -> Option<Shape> {
+    let x = number(attributes, "x", scale_factor)?;
+    let y = number(attributes, "y", scale_factor)?;
+    let width = number(attributes, "width", scale_factor)?;
+    let height = number(attributes, "height", scale_factor)?;
+    Some(Shape {
+        shape_type: ShapeType::Rectangle,
+        endpoints: Some(Endpoints {
+            initial_pt: Point::new(x, y),
+            final_pt: Point::new(x + width, y + height),
+        }),
+        corner_radius: number(attributes, "rx", scale_factor).unwrap_or(0.0),
+        ..parse_paint(attributes, scale_factor)?
+    })
+}
+
+fn parse_ellipse(attributes: &HashMap<&str, &str>, scale_factor: f32) -> Option<Shape> {
+    let cx = number(attributes, "cx", scale_factor)?;
+    let cy = number(attributes, "cy", scale_factor)?;
+    let rx = number(attributes, "rx", scale_factor)?;
+    let ry = number(attributes, "ry", scale_factor)?;
+    Some(Shape {
+        shape_type: ShapeType::Ellipse,
+        endpoints: Some(Endpoints {
+            initial_pt: Point::new(cx - rx, cy - ry),
+            final_pt: Point::new(cx + rx, cy + ry),
+        }),
+        ..parse_paint(attributes, scale_factor)?
+    })
+}
+
+fn parse_line(attributes: &HashMap<&str, &str>, scale_factor: f32) -> Option<Shape> {
+    Some(Shape {
+        shape_type: ShapeType::Line,
+        endpoints: Some(Endpoints {
+            initial_pt: Point::new(
+                number(attributes, "x1", scale_factor)?,
+                number(attributes, "y1", scale_factor)?,
+            ),
+            final_pt: Point::new(
+                number(attributes, "x2", scale_factor)?,
+                number(attributes, "y2", scale_factor)?,
+            ),
+        }),
+        ..parse_line_paint(attributes, scale_factor)?
+    })
+}
+
+/// Capter only emits paths for arrows, the shaft followed by the head with the tip as its
+/// second corner. Files from before the head was filled end the shaft at the tip instead
+fn parse_arrow(attributes: &HashMap<&str, &str>, scale_factor: f32) -> Option<Shape> {
+    let values: Vec<f32> = attributes
+        .get("d")?
+        .split(|c: char| c.is_ascii_alphabetic() || c.is_whitespace() || c == ',')
+        .filter_map(|value| value.parse::<f32>().ok())
+        .map(|value| value / scale_factor)
+        .collect();
+    if values.len() < 4 {
+        return None;
+    }
+    let tip = if values.len() >= 8 { 6 } else { 2 };
+    Some(Shape {
+        shape_type: ShapeType::Arrow,
+        endpoints: Some(Endpoints {
+            initial_pt: Point::new(values[0], values[1]),
+            final_pt: Point::new(values[tip], values[tip + 1]),
+        }),
+        ..parse_line_paint(attributes, scale_factor)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(shape_type: ShapeType, initial_pt: Point, final_pt: Point) -> Shape {
+        Shape {
+            shape_type,
+            endpoints: Some(Endpoints {
+                initial_pt,
+                final_pt,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exported_shapes_import_back() {
+        let shapes = [
+            shape(
+                ShapeType::Rectangle,
+                Point::new(10.0, 20.0),
+                Point::new(60.0, 40.0),
+            ),
+            shape(
+                ShapeType::Ellipse,
+                Point::new(70.0, 10.0),
+                Point::new(90.0, 50.0),
+            ),
+            shape(
+                ShapeType::Line,
+                Point::new(5.0, 5.0),
+                Point::new(50.0, 80.0),
+            ),
+            shape(
+                ShapeType::Arrow,
+                Point::new(100.0, 90.0),
+                Point::new(20.0, 70.0),
+            ),
+            shape(
+                ShapeType::Blur,
+                Point::new(0.0, 0.0),
+                Point::new(30.0, 30.0),
+            ),
+        ];
+        let document = export_svg(&RgbaImage::new(400, 200), &shapes, (0, 0, 400, 200), 2.0);
+        let imported = import_svg(&document, 2.0);
+
+        // The blur has no vector form and stays in the background
+        assert_eq!(imported.len(), 4);
+        for (exported, imported) in shapes.iter().zip(&imported) {
+            assert_eq!(exported.shape_type, imported.shape_type);
+            let (exported, imported) = (exported.endpoints.unwrap(), imported.endpoints.unwrap());
+            assert_eq!(exported.normalize(), imported.normalize());
+        }
+    }
+}
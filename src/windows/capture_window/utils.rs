@@ -1,19 +1,128 @@
 use std::f32::consts::PI;
 
-use iced::Point;
+use iced::{Point, Rectangle};
 
-pub fn resolve_arrow_points(initial_pt: Point, final_pt: Point) -> (Point, Point) {
+use super::models::{Endpoints, ShapeStroke};
+
+/// Right and left corners of the triangular head with its tip at `final_pt`, then the middle
+/// of its base where the shaft stops. The head grows with `stroke` but never takes more than
+/// half of the arrow
+pub fn arrowhead_points(
+    initial_pt: Point,
+    final_pt: Point,
+    stroke: ShapeStroke,
+) -> (Point, Point, Point) {
     let line = final_pt - initial_pt;
-    let length = f32::sqrt(line.x.powf(2.0) + line.y.powf(2.0));
-    let size = if length < 60.0 { length / 2.0 } else { 30.0 };
+    let size = (stroke.f32() * 3.0 + 12.0).min(line.x.hypot(line.y) / 2.0);
     let rad = line.y.atan2(line.x);
-    let right_pt = Point::new(
-        final_pt.x - size * (rad - PI / 5.0).cos(),
-        final_pt.y - size * (rad - PI / 5.0).sin(),
-    );
-    let left_pt = Point::new(
-        final_pt.x - size * (rad + PI / 5.0).cos(),
-        final_pt.y - size * (rad + PI / 5.0).sin(),
-    );
-    (right_pt , left_pt)
-}
\ No newline at end of file
+    let back = |angle: f32, distance: f32| {
+        Point::new(
+            final_pt.x - distance * angle.cos(),
+            final_pt.y - distance * angle.sin(),
+        )
+    };
+    (
+        back(rad - PI / 6.0, size),
+        back(rad + PI / 6.0, size),
+        back(rad, size * (PI / 6.0).cos()),
+    )
+}
+
+/// Moves `cursor` so the line from `origin` points along the nearest multiple of 45°
+pub fn snap_angle(origin: Point, cursor: Point) -> Point {
+    let line = cursor - origin;
+    let length = line.x.hypot(line.y);
+    let step = PI / 4.0;
+    let angle = (line.y.atan2(line.x) / step).round() * step;
+    Point::new(
+        origin.x + length * angle.cos(),
+        origin.y + length * angle.sin(),
+    )
+}
+
+/// Moves `cursor` so the box from `origin` is a square, its side the shorter of the two
+pub fn snap_square(origin: Point, cursor: Point) -> Point {
+    let line = cursor - origin;
+    let side = line.x.abs().min(line.y.abs());
+    Point::new(
+        origin.x + side.copysign(line.x),
+        origin.y + side.copysign(line.y),
+    )
+}
+
+/// Closest points between the edges of two rectangles, level with each other along any
+/// axis where they overlap
+pub fn nearest_edges(from: Rectangle, to: Rectangle) -> Endpoints {
+    let axis = |from_start: f32, from_end: f32, to_start: f32, to_end: f32| {
+        if from_end < to_start {
+            (from_end, to_start)
+        } else if to_end < from_start {
+            (from_start, to_end)
+        } else {
+            let middle = (from_start.max(to_start) + from_end.min(to_end)) / 2.0;
+            (middle, middle)
+        }
+    };
+    let (from_x, to_x) = axis(from.x, from.x + from.width, to.x, to.x + to.width);
+    let (from_y, to_y) = axis(from.y, from.y + from.height, to.y, to.y + to.height);
+    Endpoints {
+        initial_pt: Point::new(from_x, from_y),
+        final_pt: Point::new(to_x, to_y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Point, x: f32, y: f32) {
+        assert!(
+            actual.distance(Point::new(x, y)) < 0.01,
+            "{:?} is not near ({}, {})",
+            actual,
+            x,
+            y
+        );
+    }
+
+    /// Head of a Medium arrow, 27 px from the tip to either corner
+    #[test]
+    fn arrowhead_points_at_every_angle() {
+        let half_width = 13.5;
+        let depth = 27.0 * (PI / 6.0).cos();
+
+        let tip = Point::new(100.0, 0.0);
+        let (right, left, base) = arrowhead_points(Point::ORIGIN, tip, ShapeStroke::Medium);
+        assert_near(right, 100.0 - depth, half_width);
+        assert_near(left, 100.0 - depth, -half_width);
+        assert_near(base, 100.0 - depth, 0.0);
+
+        let tip = Point::new(50.0, 100.0);
+        let (right, left, base) = arrowhead_points(Point::new(50.0, 0.0), tip, ShapeStroke::Medium);
+        assert_near(right, 50.0 - half_width, 100.0 - depth);
+        assert_near(left, 50.0 + half_width, 100.0 - depth);
+        assert_near(base, 50.0, 100.0 - depth);
+
+        // Along a 3-4-5 line, the head keeps its size and stays symmetric about the shaft
+        let tip = Point::new(60.0, 80.0);
+        let (right, left, base) = arrowhead_points(Point::ORIGIN, tip, ShapeStroke::Medium);
+        assert_near(right, 35.17, 69.394);
+        assert_near(left, 56.77, 53.194);
+        assert_near(base, 60.0 - 0.6 * depth, 80.0 - 0.8 * depth);
+        for corner in [right, left] {
+            assert!((corner.distance(tip) - 27.0).abs() < 0.01);
+            assert!((corner.distance(base) - half_width).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn arrowhead_takes_half_of_a_short_arrow() {
+        let tip = Point::new(20.0, 0.0);
+        let (right, left, base) = arrowhead_points(Point::ORIGIN, tip, ShapeStroke::Broad);
+        let depth = 10.0 * (PI / 6.0).cos();
+        assert_near(right, 20.0 - depth, 5.0);
+        assert_near(left, 20.0 - depth, -5.0);
+        assert_near(base, 20.0 - depth, 0.0);
+        assert!((right.distance(tip) - 10.0).abs() < 0.01);
+    }
+}
@@ -1,14 +1,17 @@
 use iced_anim::Spring;
 
-use crate::theme::Theme;
+use crate::config::{utils::shorten_path, Config};
 
 use super::ConfigureWindow;
 
 impl ConfigureWindow {
-    pub fn new(path: String, theme: Theme) -> Self {
+    pub fn new(config: Config, onboarding: bool) -> Self {
         Self {
-            path,
-            theme: Spring::new(theme),
+            path: shorten_path(config.directory.clone()),
+            theme: Spring::new(config.theme.clone()),
+            opened: config.clone(),
+            config,
+            onboarding,
         }
     }
 }
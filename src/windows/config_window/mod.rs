@@ -1,25 +1,43 @@
 use iced::{
     alignment::Horizontal::Left,
-    widget::{button, column, container, horizontal_space, row, text},
+    widget::{
+        button, column, container, horizontal_space, row, scrollable, slider, text, text_input,
+    },
     window::Id,
     Alignment::{self, Center},
     Length::Fill,
     Task,
 };
 use iced_anim::{Animation, Spring, SpringEvent};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
 
 use crate::{
     app::AppEvent,
+    config::{
+        contact_sheet::ContactSheet,
+        preset::default_presets,
+        utils::{is_valid_filename_template, shorten_path, DEFAULT_FILENAME_TEMPLATE},
+        watermark::Corner,
+        Config, DoneAction, EmptyDoneAction, ExportFilter, FixedSize, ImageFlip, ImageFormat,
+        ReadoutPlacement, RevealFormat, TileGrid, VectorFormat, HIGHLIGHT_OPACITY_RANGE,
+        MAX_DELAY_SECS,
+    },
     consts::{BOLD, FOLDER_ICON, ICON},
-    theme::{button::ButtonClass, Element, Theme},
+    theme::{button::ButtonClass, Element, Theme, ThemeMode},
+    windows::capture_window::models::ShapeType,
 };
 
 pub mod config;
 
 #[derive(Debug)]
 pub struct ConfigureWindow {
+    pub config: Config,
+    /// `config` before any edits in the window, to tell them apart on close
+    pub opened: Config,
     pub theme: Spring<Theme>,
     pub path: String,
+    /// Shows the first run setup instead of every setting
+    pub onboarding: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +45,48 @@ pub enum ConfigEvent {
     UpdateFolderPath,
     OpenFolder,
     UpdateTheme(SpringEvent<Theme>),
+    SetThemeMode(ThemeMode),
+    UpdateEmptyDoneAction(EmptyDoneAction),
+    UpdateCropSnapStep(u32),
+    UpdateFocusBlurRadius(f32),
+    UpdateRevealFormat(RevealFormat),
+    UpdateVectorFormat(VectorFormat),
+    UpdateFilenameTemplate(String),
+    UpdateFormat(ImageFormat),
+    UpdateMergeHighlights(bool),
+    UpdateHighlightOpacity(f32),
+    UpdateOverlayTimeout(u64),
+    UpdateDelay(u32),
+    UpdateExportScale(f32),
+    UpdateExportFilter(ExportFilter),
+    UpdateFlipOnOpen(ImageFlip),
+    UpdateMaxDimension(Option<u32>),
+    UpdateExactPixels(bool),
+    UpdateTileGrid(TileGrid),
+    UpdateFixedSize(FixedSize),
+    UpdateUiScale(f32),
+    UpdateDoubleClickWindow(bool),
+    UpdateDetectElement(bool),
+    UpdateReadout(ReadoutPlacement),
+    UpdateMagnifier(bool),
+    UpdateWindowSnap(bool, f32),
+    UpdatePixelReadout(bool),
+    UpdateBlankThreshold(f32),
+    PickWatermark,
+    ClearWatermark,
+    UpdateWatermarkCorner(Corner),
+    PickPalette,
+    ClearPalette,
+    UpdateDoneAction(DoneAction),
+    UpdateTimestamp(bool),
+    UpdateTimestampCorner(Corner),
+    RemovePreset(usize),
+    RestoreDefaultPresets,
+    ResetDefaults,
+    UpdateContactSheet(ContactSheet),
+    CreateContactSheet,
+    UpdateDefaultTool(ShapeType),
+    FinishOnboarding,
     RequestExit,
 }
 
@@ -39,6 +99,211 @@ impl ConfigureWindow {
                 self.theme.update(event);
                 Task::none()
             }
+            ConfigEvent::SetThemeMode(mode) => {
+                self.config.theme_mode = mode;
+                match mode {
+                    // Animates to the OS appearance once it has been read
+                    ThemeMode::Auto => Task::done(AppEvent::CheckSystemTheme),
+                    ThemeMode::Manual => {
+                        self.theme.update(Theme::Light.into());
+                        Task::none()
+                    }
+                }
+            }
+            ConfigEvent::UpdateEmptyDoneAction(action) => {
+                self.config.on_empty_done = action;
+                Task::none()
+            }
+            ConfigEvent::UpdateCropSnapStep(step) => {
+                self.config.crop_snap_step = step;
+                Task::none()
+            }
+            ConfigEvent::UpdateWindowSnap(enabled, distance) => {
+                self.config.window_snap = enabled;
+                self.config.window_snap_distance = distance;
+                Task::none()
+            }
+            ConfigEvent::UpdateFocusBlurRadius(radius) => {
+                self.config.focus_blur_radius = radius;
+                Task::none()
+            }
+            ConfigEvent::UpdateRevealFormat(format) => {
+                self.config.reveal_format = format;
+                Task::none()
+            }
+            ConfigEvent::UpdateVectorFormat(format) => {
+                self.config.vector_format = format;
+                Task::none()
+            }
+            // Path separators would let captures land outside the directory, typing one does
+            // nothing
+            ConfigEvent::UpdateFilenameTemplate(template) => {
+                if is_valid_filename_template(&template) || template.is_empty() {
+                    self.config.filename_template = template;
+                }
+                Task::none()
+            }
+            ConfigEvent::UpdateFormat(format) => {
+                self.config.format = format.validated();
+                Task::none()
+            }
+            ConfigEvent::UpdateHighlightOpacity(opacity) => {
+                self.config.highlight_opacity = opacity.clamp(
+                    *HIGHLIGHT_OPACITY_RANGE.start(),
+                    *HIGHLIGHT_OPACITY_RANGE.end(),
+                );
+                Task::none()
+            }
+            ConfigEvent::UpdateMergeHighlights(merge) => {
+                self.config.merge_highlights = merge;
+                Task::none()
+            }
+            ConfigEvent::UpdateOverlayTimeout(secs) => {
+                self.config.overlay_timeout_secs = secs;
+                Task::none()
+            }
+            ConfigEvent::UpdateDelay(secs) => {
+                self.config.delay_secs = secs.min(MAX_DELAY_SECS);
+                Task::none()
+            }
+            ConfigEvent::UpdateExportScale(scale) => {
+                self.config.export_scale = scale;
+                Task::none()
+            }
+            ConfigEvent::UpdateExportFilter(filter) => {
+                self.config.export_filter = filter;
+                Task::none()
+            }
+            ConfigEvent::UpdateFlipOnOpen(flip) => {
+                self.config.flip_on_open = flip;
+                Task::none()
+            }
+            ConfigEvent::UpdateMaxDimension(max_dimension) => {
+                self.config.max_dimension = max_dimension;
+                Task::none()
+            }
+            ConfigEvent::PickWatermark => {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Image", &["png", "jpg", "jpeg", "webp"])
+                    .pick_file()
+                {
+                    self.config.watermark.path = path.into_os_string().into_string().unwrap();
+                }
+                Task::none()
+            }
+            ConfigEvent::ClearWatermark => {
+                self.config.watermark.path.clear();
+                Task::none()
+            }
+            ConfigEvent::UpdateWatermarkCorner(corner) => {
+                self.config.watermark.corner = corner;
+                Task::none()
+            }
+            ConfigEvent::PickPalette => {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Palette", &["gpl", "json"])
+                    .pick_file()
+                {
+                    self.config.palette_path = path.into_os_string().into_string().unwrap();
+                }
+                Task::none()
+            }
+            ConfigEvent::ClearPalette => {
+                self.config.palette_path.clear();
+                Task::none()
+            }
+            ConfigEvent::UpdateDoneAction(action) => {
+                self.config.on_done = action;
+                Task::none()
+            }
+            ConfigEvent::UpdateTimestamp(enabled) => {
+                self.config.timestamp.enabled = enabled;
+                Task::none()
+            }
+            ConfigEvent::UpdateTimestampCorner(corner) => {
+                self.config.timestamp.corner = corner;
+                Task::none()
+            }
+            ConfigEvent::RemovePreset(index) => {
+                if index < self.config.presets.len() {
+                    self.config.presets.remove(index);
+                }
+                Task::none()
+            }
+            ConfigEvent::RestoreDefaultPresets => {
+                self.config.presets = default_presets();
+                Task::none()
+            }
+            ConfigEvent::UpdateExactPixels(exact_pixels) => {
+                self.config.exact_pixels = exact_pixels;
+                Task::none()
+            }
+            ConfigEvent::UpdateTileGrid(grid) => {
+                self.config.tile_grid = grid;
+                Task::none()
+            }
+            ConfigEvent::UpdateFixedSize(size) => {
+                self.config.fixed_size = size;
+                Task::none()
+            }
+            ConfigEvent::UpdateUiScale(scale) => {
+                self.config.ui_scale = scale;
+                Task::none()
+            }
+            ConfigEvent::UpdateDoubleClickWindow(enabled) => {
+                self.config.double_click_window = enabled;
+                Task::none()
+            }
+            ConfigEvent::UpdateDetectElement(enabled) => {
+                self.config.detect_element = enabled;
+                Task::none()
+            }
+            ConfigEvent::UpdateMagnifier(enabled) => {
+                self.config.magnifier = enabled;
+                Task::none()
+            }
+            ConfigEvent::UpdatePixelReadout(enabled) => {
+                self.config.pixel_readout = enabled;
+                Task::none()
+            }
+            ConfigEvent::UpdateReadout(readout) => {
+                self.config.readout = readout;
+                Task::none()
+            }
+            ConfigEvent::UpdateContactSheet(contact_sheet) => {
+                self.config.contact_sheet = contact_sheet;
+                Task::none()
+            }
+            ConfigEvent::CreateContactSheet => Task::done(AppEvent::CreateContactSheet),
+            ConfigEvent::UpdateBlankThreshold(threshold) => {
+                self.config.blank_threshold = threshold;
+                Task::none()
+            }
+            ConfigEvent::ResetDefaults => {
+                let confirmed = MessageDialog::new()
+                    .set_level(MessageLevel::Warning)
+                    .set_title("Reset Settings")
+                    .set_description("Restore every setting to its default value?")
+                    .set_buttons(MessageButtons::YesNo)
+                    .show();
+                if confirmed == MessageDialogResult::Yes {
+                    self.config = Config::default();
+                    self.config.update_config();
+                    self.path = shorten_path(self.config.directory.clone());
+                    self.theme.update(self.config.theme.clone().into());
+                }
+                Task::none()
+            }
+            ConfigEvent::UpdateDefaultTool(tool) => {
+                self.config.default_tool = tool;
+                Task::none()
+            }
+            ConfigEvent::FinishOnboarding => {
+                self.onboarding = false;
+                self.config.theme = self.theme.target().clone();
+                self.config.update_config();
+                Task::none()
+            }
             ConfigEvent::RequestExit => Task::done(AppEvent::ExitApp),
         }
     }
@@ -47,6 +312,11 @@ impl ConfigureWindow {
         let header = row![
             text("Capter").size(60).font(BOLD),
             horizontal_space(),
+            button(text("Reset").align_x(Center).align_y(Center).size(20))
+                .on_press(ConfigEvent::ResetDefaults)
+                .height(40)
+                .width(80),
+            horizontal_space().width(10),
             button(text("Exit").align_x(Center).align_y(Center).size(20))
                 .on_press(ConfigEvent::RequestExit)
                 .height(40)
@@ -55,49 +325,577 @@ impl ConfigureWindow {
         ]
         .align_y(Center);
 
-        let body = column![
+        let setting = |label: &'static str, control: Element<'static, ConfigEvent>| {
             container(
                 row![
-                    text("Screenshots Directory")
-                        .align_x(Left)
-                        .size(22)
-                        .font(BOLD),
+                    text(label).align_x(Left).size(22).font(BOLD),
                     horizontal_space().width(Fill),
+                    control
+                ]
+                .align_y(Alignment::Center)
+                .width(Fill)
+                .height(Fill)
+                .padding(10),
+            )
+            .height(80)
+        };
+
+        let on_off = |value: bool| String::from(if value { "On" } else { "Off" });
+
+        let toggle = |value: String, width: u16, message: ConfigEvent| -> Element<ConfigEvent> {
+            button(text(value).size(20).center())
+                .height(40)
+                .width(width)
+                .on_press(message)
+                .into()
+        };
+
+        // Also offered by the first run setup
+        let done_setting = || {
+            setting(
+                "When Done",
+                toggle(
+                    self.config.on_done.to_string(),
+                    200,
+                    ConfigEvent::UpdateDoneAction(self.config.on_done.next()),
+                ),
+            )
+        };
+        let directory_setting = || {
+            setting(
+                "Screenshots Directory",
+                row![
                     button(text(FOLDER_ICON).font(ICON).size(20).center())
                         .height(40)
                         .width(40)
                         .on_press(ConfigEvent::OpenFolder),
                     horizontal_space().width(10),
-                    button(text(&self.path).size(20).center())
+                    button(text(self.path.clone()).size(20).center())
                         .height(40)
                         .width(250)
                         .on_press(ConfigEvent::UpdateFolderPath)
                 ]
-                .align_y(Alignment::Center)
-                .width(Fill)
-                .height(Fill)
-                .padding(10)
+                .into(),
             )
-            .height(80),
-            container(
+        };
+        let theme_setting = || {
+            setting(
+                "App Theme",
+                // Cycles Light, Dark and following the system
+                match (self.config.theme_mode, self.theme.target()) {
+                    (ThemeMode::Auto, _) => toggle(
+                        String::from("System"),
+                        160,
+                        ConfigEvent::SetThemeMode(ThemeMode::Manual),
+                    ),
+                    (ThemeMode::Manual, Theme::Dark) => toggle(
+                        String::from("Dark"),
+                        160,
+                        ConfigEvent::SetThemeMode(ThemeMode::Auto),
+                    ),
+                    (ThemeMode::Manual, theme) => toggle(
+                        theme.to_string(),
+                        160,
+                        ConfigEvent::UpdateTheme(theme.toggle().into()),
+                    ),
+                },
+            )
+        };
+        let tool_setting = || {
+            setting(
+                "Default Tool",
+                toggle(
+                    self.config.default_tool.to_string(),
+                    160,
+                    ConfigEvent::UpdateDefaultTool(self.config.default_tool.next()),
+                ),
+            )
+        };
+
+        if self.onboarding {
+            let welcome = column![
+                text("Welcome").size(60).font(BOLD),
+                text("Pick a few defaults to start with, everything can be changed later").size(20),
+                directory_setting(),
+                done_setting(),
+                theme_setting(),
+                tool_setting(),
                 row![
-                    text("App Theme").align_x(Left).size(22).font(BOLD),
                     horizontal_space().width(Fill),
-                    button(text(self.theme.target().to_string()).size(20).center())
+                    button(text("Get Started").size(20).center())
                         .height(40)
                         .width(160)
-                        .on_press(ConfigEvent::UpdateTheme(
-                            self.theme.target().toggle().into()
+                        .on_press(ConfigEvent::FinishOnboarding)
+                ]
+                .padding([0, 10])
+            ]
+            .spacing(10)
+            .padding([0, 12]);
+            let content = scrollable(welcome).height(Fill);
+
+            return Animation::new(&self.theme, container(content).padding(15))
+                .on_update(ConfigEvent::UpdateTheme)
+                .into();
+        }
+
+        let body = column![
+            done_setting(),
+            directory_setting(),
+            setting(
+                "File Name (%Y %m %d %H %M %S %n)",
+                text_input(DEFAULT_FILENAME_TEMPLATE, &self.config.filename_template)
+                    .on_input(ConfigEvent::UpdateFilenameTemplate)
+                    .size(20)
+                    .padding(8)
+                    .width(250)
+                    .into()
+            ),
+            setting("Image Format", {
+                let format = self.config.format;
+                let mut controls = row![toggle(
+                    format.to_string(),
+                    100,
+                    ConfigEvent::UpdateFormat(format.next())
+                )];
+                if let ImageFormat::Jpeg { quality } = format {
+                    controls = controls
+                        .push(horizontal_space().width(10))
+                        .push(toggle(
+                            String::from("-"),
+                            40,
+                            ConfigEvent::UpdateFormat(ImageFormat::Jpeg {
+                                quality: quality.saturating_sub(5),
+                            }),
                         ))
+                        .push(text(format!("{} %", quality)).size(20).center().width(80))
+                        .push(toggle(
+                            String::from("+"),
+                            40,
+                            ConfigEvent::UpdateFormat(ImageFormat::Jpeg {
+                                quality: quality.saturating_add(5),
+                            }),
+                        ));
+                }
+                controls.align_y(Alignment::Center).into()
+            }),
+            theme_setting(),
+            tool_setting(),
+            setting(
+                "Done Without Annotations",
+                toggle(
+                    self.config.on_empty_done.to_string(),
+                    200,
+                    ConfigEvent::UpdateEmptyDoneAction(self.config.on_empty_done.toggle())
+                )
+            ),
+            setting(
+                "Double Click Captures Window",
+                toggle(
+                    on_off(self.config.double_click_window),
+                    160,
+                    ConfigEvent::UpdateDoubleClickWindow(!self.config.double_click_window)
+                )
+            ),
+            setting(
+                "Click Selects Element",
+                toggle(
+                    on_off(self.config.detect_element),
+                    160,
+                    ConfigEvent::UpdateDetectElement(!self.config.detect_element)
+                )
+            ),
+            setting(
+                "Selection Size Readout",
+                toggle(
+                    self.config.readout.to_string(),
+                    200,
+                    ConfigEvent::UpdateReadout(self.config.readout.toggle())
+                )
+            ),
+            setting(
+                "Crop Magnifier",
+                toggle(
+                    on_off(self.config.magnifier),
+                    160,
+                    ConfigEvent::UpdateMagnifier(!self.config.magnifier)
+                )
+            ),
+            setting(
+                "Pixel Color",
+                toggle(
+                    on_off(self.config.pixel_readout),
+                    160,
+                    ConfigEvent::UpdatePixelReadout(!self.config.pixel_readout)
+                )
+            ),
+            setting(
+                "Crop Snap Step",
+                toggle(
+                    match self.config.crop_snap_step {
+                        0 => String::from("Off"),
+                        step => format!("{} px", step),
+                    },
+                    160,
+                    ConfigEvent::UpdateCropSnapStep(match self.config.crop_snap_step {
+                        0 => 2,
+                        step if step >= 32 => 0,
+                        step => step * 2,
+                    })
+                )
+            ),
+            setting(
+                "Window Edge Snap",
+                toggle(
+                    if self.config.window_snap {
+                        format!("{} px", self.config.window_snap_distance)
+                    } else {
+                        String::from("Off")
+                    },
+                    160,
+                    match (self.config.window_snap, self.config.window_snap_distance) {
+                        (false, _) => ConfigEvent::UpdateWindowSnap(true, 4.0),
+                        (true, distance) if distance >= 16.0 => {
+                            ConfigEvent::UpdateWindowSnap(false, 8.0)
+                        }
+                        (true, distance) => ConfigEvent::UpdateWindowSnap(true, distance * 2.0),
+                    }
+                )
+            ),
+            setting(
+                "Focus Blur Radius",
+                toggle(
+                    format!("{} px", self.config.focus_blur_radius),
+                    160,
+                    ConfigEvent::UpdateFocusBlurRadius(match self.config.focus_blur_radius {
+                        radius if radius >= 32.0 => 4.0,
+                        radius => radius * 2.0,
+                    })
+                )
+            ),
+            setting(
+                "Reveal Export",
+                toggle(
+                    self.config.reveal_format.to_string(),
+                    160,
+                    ConfigEvent::UpdateRevealFormat(self.config.reveal_format.toggle())
+                )
+            ),
+            setting(
+                "Vector Export",
+                toggle(
+                    self.config.vector_format.to_string(),
+                    160,
+                    ConfigEvent::UpdateVectorFormat(self.config.vector_format.toggle())
+                )
+            ),
+            setting(
+                "Merge Overlapping Highlights",
+                toggle(
+                    on_off(self.config.merge_highlights),
+                    160,
+                    ConfigEvent::UpdateMergeHighlights(!self.config.merge_highlights)
+                )
+            ),
+            setting(
+                "Highlight Opacity",
+                row![
+                    slider(
+                        HIGHLIGHT_OPACITY_RANGE,
+                        self.config.highlight_opacity,
+                        ConfigEvent::UpdateHighlightOpacity
+                    )
+                    .step(0.05)
+                    .width(160),
+                    text(format!(
+                        "{} %",
+                        (self.config.highlight_opacity * 100.0).round()
+                    ))
+                    .size(20)
+                    .center()
+                    .width(80)
                 ]
                 .align_y(Alignment::Center)
-                .width(Fill)
-                .height(Fill)
-                .padding(10)
-            )
-            .height(80)
+                .into()
+            ),
+            setting("Capture Delay", {
+                let secs = self.config.delay_secs;
+                row![
+                    toggle(
+                        String::from("-"),
+                        40,
+                        ConfigEvent::UpdateDelay(secs.saturating_sub(1))
+                    ),
+                    text(match secs {
+                        0 => String::from("None"),
+                        secs => format!("{} s", secs),
+                    })
+                    .size(20)
+                    .center()
+                    .width(80),
+                    toggle(String::from("+"), 40, ConfigEvent::UpdateDelay(secs + 1))
+                ]
+                .align_y(Alignment::Center)
+                .into()
+            }),
+            setting(
+                "Idle Overlay Timeout",
+                toggle(
+                    match self.config.overlay_timeout_secs {
+                        0 => String::from("Never"),
+                        secs => format!("{} s", secs),
+                    },
+                    160,
+                    ConfigEvent::UpdateOverlayTimeout(match self.config.overlay_timeout_secs {
+                        0 => 30,
+                        secs if secs >= 240 => 0,
+                        secs => secs * 2,
+                    })
+                )
+            ),
+            setting(
+                "Blank Capture Warning",
+                toggle(
+                    match self.config.blank_threshold {
+                        threshold if threshold <= 0.0 => String::from("Off"),
+                        threshold => format!("{}% Uniform", (threshold * 100.0).round()),
+                    },
+                    200,
+                    ConfigEvent::UpdateBlankThreshold(match self.config.blank_threshold {
+                        threshold if threshold <= 0.0 => 0.9,
+                        threshold if threshold < 0.95 => 0.95,
+                        threshold if threshold < 0.99 => 0.99,
+                        threshold if threshold < 1.0 => 1.0,
+                        _ => 0.0,
+                    })
+                )
+            ),
+            setting(
+                "Exact Pixels",
+                toggle(
+                    on_off(self.config.exact_pixels),
+                    160,
+                    ConfigEvent::UpdateExactPixels(!self.config.exact_pixels)
+                )
+            ),
+            setting(
+                "Toolbar Size",
+                toggle(
+                    format!("{}%", (self.config.ui_scale * 100.0).round()),
+                    160,
+                    ConfigEvent::UpdateUiScale(match self.config.ui_scale {
+                        scale if scale < 1.25 => 1.25,
+                        scale if scale < 1.5 => 1.5,
+                        scale if scale < 2.0 => 2.0,
+                        _ => 1.0,
+                    })
+                )
+            ),
+            setting(
+                "Fixed Selection Size",
+                toggle(
+                    self.config.fixed_size.to_string(),
+                    160,
+                    ConfigEvent::UpdateFixedSize(self.config.fixed_size.next())
+                )
+            ),
+            setting("Split Into Tiles", {
+                let grid = self.config.tile_grid;
+                row![
+                    toggle(
+                        format!("{} Rows", grid.rows),
+                        120,
+                        ConfigEvent::UpdateTileGrid(TileGrid {
+                            rows: grid.rows % 6 + 1,
+                            ..grid
+                        })
+                    ),
+                    horizontal_space().width(10),
+                    toggle(
+                        format!("{} Columns", grid.columns),
+                        140,
+                        ConfigEvent::UpdateTileGrid(TileGrid {
+                            columns: grid.columns % 6 + 1,
+                            ..grid
+                        })
+                    )
+                ]
+                .into()
+            }),
+            setting(
+                "Export Scale",
+                toggle(
+                    format!("{}x", self.config.export_scale),
+                    160,
+                    ConfigEvent::UpdateExportScale(match self.config.export_scale {
+                        scale if scale < 1.5 => 1.5,
+                        scale if scale < 2.0 => 2.0,
+                        scale if scale < 3.0 => 3.0,
+                        scale if scale < 4.0 => 4.0,
+                        _ => 1.0,
+                    })
+                )
+            ),
+            setting(
+                "Upscale Filter",
+                toggle(
+                    self.config.export_filter.to_string(),
+                    160,
+                    ConfigEvent::UpdateExportFilter(self.config.export_filter.toggle())
+                )
+            ),
+            setting(
+                "Flip Captures",
+                toggle(
+                    self.config.flip_on_open.to_string(),
+                    160,
+                    ConfigEvent::UpdateFlipOnOpen(self.config.flip_on_open.next())
+                )
+            ),
+            setting(
+                "Max Image Size",
+                toggle(
+                    match self.config.max_dimension {
+                        Some(max) => format!("{} px", max),
+                        None => String::from("Off"),
+                    },
+                    160,
+                    ConfigEvent::UpdateMaxDimension(match self.config.max_dimension {
+                        None => Some(1080),
+                        Some(max) if max < 1440 => Some(1440),
+                        Some(max) if max < 2048 => Some(2048),
+                        Some(max) if max < 3840 => Some(3840),
+                        Some(_) => None,
+                    })
+                )
+            ),
+            setting("Watermark", {
+                let watermark = &self.config.watermark;
+                let mut controls = row![
+                    toggle(
+                        watermark.corner.to_string(),
+                        160,
+                        ConfigEvent::UpdateWatermarkCorner(watermark.corner.next())
+                    ),
+                    horizontal_space().width(10),
+                    toggle(
+                        if watermark.path.is_empty() {
+                            String::from("None")
+                        } else {
+                            shorten_path(watermark.path.clone())
+                        },
+                        250,
+                        ConfigEvent::PickWatermark
+                    )
+                ];
+                if !watermark.path.is_empty() {
+                    controls = controls.push(horizontal_space().width(10)).push(
+                        button(text("Clear").size(20).center())
+                            .height(40)
+                            .width(80)
+                            .on_press(ConfigEvent::ClearWatermark)
+                            .class(ButtonClass::Danger),
+                    );
+                }
+                controls.into()
+            }),
+            setting("Color Palette", {
+                let path = &self.config.palette_path;
+                let mut controls = row![toggle(
+                    if path.is_empty() {
+                        String::from("None")
+                    } else {
+                        shorten_path(path.clone())
+                    },
+                    250,
+                    ConfigEvent::PickPalette
+                )];
+                if !path.is_empty() {
+                    controls = controls.push(horizontal_space().width(10)).push(
+                        button(text("Clear").size(20).center())
+                            .height(40)
+                            .width(80)
+                            .on_press(ConfigEvent::ClearPalette)
+                            .class(ButtonClass::Danger),
+                    );
+                }
+                controls.into()
+            }),
+            setting("Timestamp Caption", {
+                let timestamp = &self.config.timestamp;
+                let mut controls = row![toggle(
+                    on_off(timestamp.enabled),
+                    80,
+                    ConfigEvent::UpdateTimestamp(!timestamp.enabled)
+                )];
+                if timestamp.enabled {
+                    controls = controls.push(horizontal_space().width(10)).push(toggle(
+                        timestamp.corner.to_string(),
+                        160,
+                        ConfigEvent::UpdateTimestampCorner(timestamp.corner.next()),
+                    ));
+                }
+                controls.into()
+            }),
+            setting("Contact Sheet", {
+                let sheet = self.config.contact_sheet;
+                row![
+                    toggle(
+                        format!("{} Columns", sheet.columns),
+                        130,
+                        ConfigEvent::UpdateContactSheet(ContactSheet {
+                            columns: sheet.columns % 6 + 1,
+                            ..sheet
+                        })
+                    ),
+                    horizontal_space().width(10),
+                    toggle(
+                        format!("{} px", sheet.thumbnail_size),
+                        100,
+                        ConfigEvent::UpdateContactSheet(ContactSheet {
+                            thumbnail_size: match sheet.thumbnail_size {
+                                size if size < 240 => 240,
+                                size if size < 320 => 320,
+                                _ => 160,
+                            },
+                            ..sheet
+                        })
+                    ),
+                    horizontal_space().width(10),
+                    toggle(
+                        String::from(if sheet.labels { "Labels" } else { "No Labels" }),
+                        120,
+                        ConfigEvent::UpdateContactSheet(ContactSheet {
+                            labels: !sheet.labels,
+                            ..sheet
+                        })
+                    ),
+                    horizontal_space().width(10),
+                    toggle(String::from("Create"), 90, ConfigEvent::CreateContactSheet)
+                ]
+                .into()
+            }),
+            setting("Annotation Presets", {
+                let mut controls = row![].spacing(10);
+                for (index, preset) in self.config.presets.iter().enumerate() {
+                    controls = controls.push(
+                        button(text(format!("{} x", preset.name)).size(20).center())
+                            .height(40)
+                            .on_press(ConfigEvent::RemovePreset(index)),
+                    );
+                }
+                controls
+                    .push(
+                        button(text("Defaults").size(20).center())
+                            .height(40)
+                            .on_press(ConfigEvent::RestoreDefaultPresets),
+                    )
+                    .into()
+            })
         ]
-        .spacing(10);
+        .spacing(10)
+        .padding([0, 12]);
+
+        let body = scrollable(body).height(Fill);
 
         let content = column![header, body].spacing(10).padding(15);
 
@@ -0,0 +1,252 @@
+use iced::{
+    event::Status,
+    mouse::{Button, Cursor, Interaction},
+    widget::{
+        button, canvas,
+        canvas::{Cache, Event, Geometry, Program},
+        container, horizontal_space, row, stack, text, vertical_space, Column,
+    },
+    window::{disable_mouse_passthrough, enable_mouse_passthrough, Id},
+    Alignment::Center,
+    Length::Fill,
+    Point, Rectangle, Renderer, Task,
+};
+
+use crate::{
+    app::AppEvent,
+    consts::{ARROW, ELLIPSE_STROKE, HIGHLIGHT, ICON, RECT_FILLED, RECT_STROKE},
+    theme::{button::ButtonClass, text::TextClass, Element, Theme},
+    windows::capture_window::{
+        annotate::draw_shape,
+        models::{Endpoints, Shape, ShapeColor, ShapeType, DEFAULT_HIGHLIGHT_OPACITY},
+    },
+};
+
+/// Transparent always-on-top window for drawing over the live desktop.
+///
+/// Clicks pass through to the windows below unless drawing is toggled on.
+pub struct LiveWindow {
+    pub drawing: bool,
+    pub cursor_position: Point,
+    pub shape: Shape,
+    pub shapes: Vec<Shape>,
+    pub cache: Cache,
+}
+
+#[derive(Debug, Clone)]
+pub enum LiveEvent {
+    ToggleDrawing,
+    Clear,
+    ChooseShapeType(ShapeType, bool, bool),
+    ChangeColor(ShapeColor),
+    SetInitialPoint,
+    UpdateCurrentPosition(Point),
+    SetFinalPoint,
+}
+
+impl Default for LiveWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveWindow {
+    pub fn new() -> Self {
+        Self {
+            drawing: false,
+            cursor_position: Point::ORIGIN,
+            shape: Shape {
+                is_solid: true,
+                ..Default::default()
+            },
+            shapes: Vec::new(),
+            cache: Cache::new(),
+        }
+    }
+
+    pub fn update(&mut self, id: Id, message: LiveEvent) -> Task<AppEvent> {
+        match message {
+            LiveEvent::ToggleDrawing => {
+                self.drawing = !self.drawing;
+                self.shape.endpoints = None;
+                return if self.drawing {
+                    disable_mouse_passthrough(id)
+                } else {
+                    enable_mouse_passthrough(id)
+                };
+            }
+            LiveEvent::Clear => {
+                self.shapes.clear();
+                self.cache.clear();
+            }
+            LiveEvent::ChooseShapeType(shape_type, is_filled, is_solid) => {
+                self.shape.shape_type = shape_type;
+                self.shape.is_filled = is_filled;
+                self.shape.is_solid = is_solid;
+                self.shape
+                    .set_color(self.shape.stroke_color, DEFAULT_HIGHLIGHT_OPACITY);
+            }
+            LiveEvent::ChangeColor(color) => self
+                .shape
+                .set_color(color.rgba(), DEFAULT_HIGHLIGHT_OPACITY),
+            LiveEvent::SetInitialPoint => {
+                self.shape.endpoints = Some(Endpoints {
+                    initial_pt: self.cursor_position,
+                    final_pt: self.cursor_position,
+                });
+            }
+            LiveEvent::UpdateCurrentPosition(position) => {
+                self.cursor_position = position;
+                if let Some(ref mut endpoints) = self.shape.endpoints {
+                    endpoints.final_pt = position;
+                }
+            }
+            LiveEvent::SetFinalPoint => {
+                if self.shape.endpoints.is_some() {
+                    self.shapes.push(self.shape.clone());
+                    self.cache.clear();
+                }
+                self.shape.endpoints = None;
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, LiveEvent> {
+        const SQUARE: u16 = 44;
+        const TEXT: u16 = 24;
+
+        let mut overlay = Column::new();
+
+        if self.drawing && self.shape.endpoints.is_none() {
+            let shape_icon = |utf, shape_type, is_filled, is_solid| {
+                let button_class = if self.shape.shape_type == shape_type
+                    && self.shape.is_filled == is_filled
+                    && self.shape.is_solid == is_solid
+                {
+                    ButtonClass::Selected
+                } else {
+                    ButtonClass::Default
+                };
+                button(text(utf).font(ICON).size(TEXT).center())
+                    .on_press(LiveEvent::ChooseShapeType(shape_type, is_filled, is_solid))
+                    .height(SQUARE)
+                    .width(SQUARE)
+                    .class(button_class)
+            };
+
+            let color_icon = |color: ShapeColor| {
+                let button_class = if self.shape.stroke_color == color.rgba() {
+                    ButtonClass::Selected
+                } else {
+                    ButtonClass::Default
+                };
+                button(
+                    text(RECT_FILLED)
+                        .font(ICON)
+                        .size(TEXT)
+                        .center()
+                        .class(TextClass::Custom(color.rgba().into_iced())),
+                )
+                .on_press(LiveEvent::ChangeColor(color))
+                .height(SQUARE)
+                .width(SQUARE)
+                .class(button_class)
+            };
+
+            let toolbar = row![
+                shape_icon(RECT_STROKE, ShapeType::Rectangle, false, true),
+                shape_icon(ELLIPSE_STROKE, ShapeType::Ellipse, false, true),
+                shape_icon(ARROW, ShapeType::Arrow, false, true),
+                shape_icon(HIGHLIGHT, ShapeType::Rectangle, true, false),
+                color_icon(ShapeColor::Red),
+                color_icon(ShapeColor::Green),
+                color_icon(ShapeColor::Blue),
+                color_icon(ShapeColor::Yellow),
+                button(text("Clear").size(TEXT).center())
+                    .on_press(LiveEvent::Clear)
+                    .height(SQUARE),
+                button(text("Stop").size(TEXT).center())
+                    .on_press(LiveEvent::ToggleDrawing)
+                    .height(SQUARE)
+            ]
+            .spacing(10);
+
+            overlay = overlay.push(vertical_space().height(5)).push(row![
+                horizontal_space().width(Fill),
+                container(toolbar).padding(10).align_y(Center),
+                horizontal_space().width(Fill)
+            ]);
+        }
+
+        stack![canvas(self).height(Fill).width(Fill), overlay]
+            .height(Fill)
+            .width(Fill)
+            .into()
+    }
+}
+
+impl Program<LiveEvent, Theme> for LiveWindow {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry<Renderer>> {
+        let shapes_frame = self.cache.draw(renderer, bounds.size(), |frame| {
+            for shape in self.shapes.iter() {
+                draw_shape(frame, shape);
+            }
+        });
+
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        draw_shape(&mut frame, &self.shape);
+
+        vec![shapes_frame, frame.into_geometry()]
+    }
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        _bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> (Status, Option<LiveEvent>) {
+        if !self.drawing {
+            return (Status::Ignored, None);
+        }
+        match event {
+            Event::Mouse(event) => match event {
+                iced::mouse::Event::CursorMoved { position } => (
+                    Status::Captured,
+                    Some(LiveEvent::UpdateCurrentPosition(position)),
+                ),
+                iced::mouse::Event::ButtonPressed(Button::Left) => {
+                    (Status::Captured, Some(LiveEvent::SetInitialPoint))
+                }
+                iced::mouse::Event::ButtonReleased(Button::Left) => {
+                    (Status::Captured, Some(LiveEvent::SetFinalPoint))
+                }
+                _ => (Status::Ignored, None),
+            },
+            _ => (Status::Ignored, None),
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> Interaction {
+        if self.drawing && cursor.is_over(bounds) {
+            Interaction::Crosshair
+        } else {
+            Interaction::default()
+        }
+    }
+}
@@ -1,10 +1,19 @@
 use capture_window::CaptureWindow;
 use config_window::ConfigureWindow;
+use live_window::LiveWindow;
+use picker_window::PickerWindow;
+use pin_window::PinWindow;
 
 pub mod capture_window;
 pub mod config_window;
+pub mod live_window;
+pub mod picker_window;
+pub mod pin_window;
 
 pub enum AppWindow {
     Configure(Box<ConfigureWindow>),
     Capture(Box<CaptureWindow>),
+    Picker(PickerWindow),
+    Live(Box<LiveWindow>),
+    Pin(PinWindow),
 }
@@ -0,0 +1,43 @@
+use iced::{
+    widget::{button, column, container, text},
+    Alignment::Center,
+    Length::Fill,
+};
+
+use crate::{app::AppEvent, consts::BOLD, theme::Element};
+
+/// Numbered overlay shown on each monitor while choosing which one to capture, or all of them
+#[derive(Debug)]
+pub struct PickerWindow {
+    pub index: usize,
+    pub name: String,
+}
+
+impl PickerWindow {
+    pub fn new(index: usize, name: String) -> Self {
+        Self { index, name }
+    }
+
+    pub fn view(&self) -> Element<'_, AppEvent> {
+        let content = column![
+            text((self.index + 1).to_string()).size(120).font(BOLD),
+            text(&self.name).size(20)
+        ]
+        .align_x(Center);
+
+        container(
+            column![
+                button(content)
+                    .on_press(AppEvent::SelectMonitor(self.index))
+                    .padding(20),
+                button(text("All Monitors").size(16))
+                    .on_press(AppEvent::SelectDesktop)
+                    .padding(8)
+            ]
+            .spacing(10)
+            .align_x(Center),
+        )
+        .center(Fill)
+        .into()
+    }
+}
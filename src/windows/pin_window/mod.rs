@@ -0,0 +1,60 @@
+use iced::{
+    widget::{container, image::Handle, mouse_area, Image},
+    window::Id,
+    Length::Fill,
+    Size,
+};
+use xcap::image::RgbaImage;
+
+use crate::{
+    app::AppEvent,
+    theme::{container::ContainerClass, Element},
+};
+
+/// Room around the image for its shadow, in logical pixels
+pub const SHADOW_MARGIN: f32 = 12.0;
+
+/// Borderless always-on-top window showing a capture as a reference
+///
+/// Dragging anywhere moves it, a right click or `Esc` closes it.
+#[derive(Debug)]
+pub struct PinWindow {
+    pub image: Handle,
+    /// Size of the image on screen, one image pixel per physical pixel
+    pub size: Size,
+}
+
+impl PinWindow {
+    pub fn new(image: RgbaImage, scale_factor: f32) -> Self {
+        let size = Size::new(
+            image.width() as f32 / scale_factor,
+            image.height() as f32 / scale_factor,
+        );
+        Self {
+            image: Handle::from_rgba(image.width(), image.height(), image.into_raw()),
+            size,
+        }
+    }
+
+    /// Size of the window, the image with the shadow around it
+    pub fn window_size(&self) -> Size {
+        Size::new(
+            self.size.width + 2.0 * SHADOW_MARGIN,
+            self.size.height + 2.0 * SHADOW_MARGIN,
+        )
+    }
+
+    pub fn view(&self, id: Id) -> Element<'_, AppEvent> {
+        let image = container(
+            Image::new(self.image.clone())
+                .width(self.size.width)
+                .height(self.size.height),
+        )
+        .class(ContainerClass::Pinned);
+
+        mouse_area(container(image).center(Fill))
+            .on_press(AppEvent::DragPin(id))
+            .on_right_press(AppEvent::RequestClose(id))
+            .into()
+    }
+}